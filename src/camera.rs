@@ -13,6 +13,10 @@ pub struct Camera {
     lens_radius: crate::Float,
     time_0: crate::Float,
     time_1: crate::Float,
+    /// Height of the viewport a unit away
+    viewport_height: crate::Float,
+    /// Angle between the rays of neighbouring pixels
+    pixel_spread: crate::Float,
 }
 
 impl Camera {
@@ -59,6 +63,17 @@ impl Camera {
             lens_radius,
             time_0: 0.0,
             time_1: 0.0,
+            viewport_height,
+            pixel_spread: 0.0,
+        }
+    }
+
+    /// Rays carry the footprint of pixels of an image this tall, for
+    /// filtering textures.
+    pub fn with_image_height(self, image_height: u32) -> Self {
+        Self {
+            pixel_spread: self.viewport_height / image_height.max(1) as crate::Float,
+            ..self
         }
     }
 
@@ -96,10 +111,13 @@ impl Camera {
         } else {
             utils::gen_range(self.time_0..self.time_1)
         };
-        Ray::new_with_time(
+        let mut ray = Ray::new_with_time(
             self.origin + offset,
             self.lower_left_corner + u * self.horizontal + v * self.vertical - self.origin - offset,
             time,
-        )
+        );
+        ray.from_camera = true;
+        ray.spread = self.pixel_spread;
+        ray
     }
 }
@@ -69,7 +69,7 @@ pub fn build_app() -> App<'static> {
         .arg(
             Arg::with_name("scene")
                 .default_value("0")
-                .value_parser(clap::builder::RangedU64ValueParser::<u32>::new().range(0..=8))
+                .value_parser(clap::builder::RangedU64ValueParser::<u32>::new().range(0..=10))
                 .takes_value(true)
                 .long("scene"),
         )
@@ -92,6 +92,28 @@ pub fn build_app() -> App<'static> {
                 .value_parser(clap::builder::RangedU64ValueParser::<u32>::new().range(1..=100000)),
         )
         .arg(Arg::with_name("use bvh").takes_value(false).long("bvh"))
+        .arg(
+            Arg::with_name("environment")
+                .help("Equirectangular .hdr or .exr image lighting the scene")
+                .takes_value(true)
+                .long("environment"),
+        )
+        .arg(
+            Arg::with_name("environment rotation")
+                .help("Rotation of the environment about the vertical axis, in degrees")
+                .takes_value(true)
+                .long("environment-rotation")
+                .value_parser(clap::value_parser!(crate::Float))
+                .requires("environment"),
+        )
+        .arg(
+            Arg::with_name("environment intensity")
+                .help("Scale of the environment radiance")
+                .takes_value(true)
+                .long("environment-intensity")
+                .value_parser(clap::value_parser!(crate::Float))
+                .requires("environment"),
+        )
         .arg(
             Arg::with_name("job")
                 .takes_value(true)
@@ -2,6 +2,29 @@ use crate::Vec3;
 
 pub type Color = Vec3;
 
+/// Relative luminance of linear Rec. 709 primaries.
+pub fn luminance(color: Color) -> crate::Float {
+    0.2126 * color.x + 0.7152 * color.y + 0.0722 * color.z
+}
+
+/// Linear sRGB of CIE XYZ tristimulus values.
+pub fn xyz_to_rgb(xyz: Vec3) -> Color {
+    Color::new(
+        3.2406 * xyz.x - 1.5372 * xyz.y - 0.4986 * xyz.z,
+        -0.9689 * xyz.x + 1.8758 * xyz.y + 0.0415 * xyz.z,
+        0.0557 * xyz.x - 0.2040 * xyz.y + 1.0570 * xyz.z,
+    )
+}
+
+/// Linear value of an sRGB encoded channel in [0, 1].
+pub fn srgb_to_linear(c: crate::Float) -> crate::Float {
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
+
 pub fn write_color(
     img: &mut image::RgbImage,
     x: u32,
@@ -0,0 +1,196 @@
+//! Piecewise constant distributions for importance sampling tabulated
+//! functions (Pharr et al., Physically Based Rendering, 13.3 and 13.6.7).
+
+pub struct Distribution1D {
+    function: Vec<crate::Float>,
+    cdf: Vec<crate::Float>,
+    integral: crate::Float,
+}
+
+impl Distribution1D {
+    /// `function` over [0, 1] in equal steps, negative values count as 0.
+    pub fn new(function: Vec<crate::Float>) -> Self {
+        assert!(
+            !function.is_empty(),
+            "distribution needs at least one value"
+        );
+        let function: Vec<crate::Float> = function.into_iter().map(|f| f.max(0.0)).collect();
+        let n = function.len() as crate::Float;
+
+        let mut cdf = Vec::with_capacity(function.len() + 1);
+        cdf.push(0.0);
+        for f in &function {
+            cdf.push(cdf.last().unwrap() + f / n);
+        }
+        let integral = *cdf.last().unwrap();
+        if integral == 0.0 {
+            // nothing to favour, sample uniformly
+            for (i, c) in cdf.iter_mut().enumerate() {
+                *c = i as crate::Float / n;
+            }
+        } else {
+            for c in cdf.iter_mut() {
+                *c /= integral;
+            }
+        }
+
+        Self {
+            function,
+            cdf,
+            integral,
+        }
+    }
+
+    pub fn count(&self) -> usize {
+        self.function.len()
+    }
+
+    /// Average of the function over [0, 1].
+    pub fn integral(&self) -> crate::Float {
+        self.integral
+    }
+
+    /// Index of the step containing `u` in the cdf.
+    fn find(&self, u: crate::Float) -> usize {
+        let index = self.cdf.partition_point(|c| *c <= u);
+        index.clamp(1, self.count()) - 1
+    }
+
+    /// A point in [0, 1) for `u` uniform in [0, 1), with its density and step.
+    pub fn sample_continuous(&self, u: crate::Float) -> (crate::Float, crate::Float, usize) {
+        let offset = self.find(u);
+        let width = self.cdf[offset + 1] - self.cdf[offset];
+        let du = if width > 0.0 {
+            (u - self.cdf[offset]) / width
+        } else {
+            0.0
+        };
+        let x = (offset as crate::Float + du) / self.count() as crate::Float;
+        (x, self.pdf_at(offset), offset)
+    }
+
+    fn pdf_at(&self, offset: usize) -> crate::Float {
+        if self.integral == 0.0 {
+            1.0
+        } else {
+            self.function[offset] / self.integral
+        }
+    }
+
+    /// Density of `x` in [0, 1].
+    pub fn pdf(&self, x: crate::Float) -> crate::Float {
+        let offset = ((x * self.count() as crate::Float) as usize).min(self.count() - 1);
+        self.pdf_at(offset)
+    }
+}
+
+/// Distribution over [0, 1]^2, sampling v first and u given v.
+pub struct Distribution2D {
+    conditional: Vec<Distribution1D>,
+    marginal: Distribution1D,
+}
+
+impl Distribution2D {
+    /// `function` is `width * height` values, row major along u.
+    pub fn new(function: &[crate::Float], width: usize, height: usize) -> Self {
+        assert_eq!(function.len(), width * height);
+        let conditional: Vec<Distribution1D> = function
+            .chunks(width)
+            .map(|row| Distribution1D::new(row.to_vec()))
+            .collect();
+        let marginal = Distribution1D::new(conditional.iter().map(|d| d.integral()).collect());
+        Self {
+            conditional,
+            marginal,
+        }
+    }
+
+    /// A point in [0, 1)^2 for `u` uniform in [0, 1)^2, with its density.
+    pub fn sample_continuous(
+        &self,
+        u: (crate::Float, crate::Float),
+    ) -> ((crate::Float, crate::Float), crate::Float) {
+        let (v, pdf_v, row) = self.marginal.sample_continuous(u.1);
+        let (u, pdf_u, _) = self.conditional[row].sample_continuous(u.0);
+        ((u, v), pdf_u * pdf_v)
+    }
+
+    /// Density of a point in [0, 1]^2.
+    pub fn pdf(&self, uv: (crate::Float, crate::Float)) -> crate::Float {
+        let height = self.conditional.len();
+        let row = ((uv.1 * height as crate::Float) as usize).min(height - 1);
+        self.marginal.pdf(uv.1) * self.conditional[row].pdf(uv.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils;
+
+    fn ramp() -> Distribution2D {
+        let (width, height) = (5, 3);
+        let function: Vec<crate::Float> = (0..width * height)
+            .map(|i| ((i % width) * (i / width + 1)) as crate::Float)
+            .collect();
+        Distribution2D::new(&function, width, height)
+    }
+
+    #[test]
+    fn pdf_integrates_to_one() {
+        let distribution = ramp();
+        // midpoints of a grid finer than the steps, exact for a piecewise
+        // constant density
+        let n = 60;
+        let mut integral = 0.0;
+        for j in 0..n {
+            for i in 0..n {
+                let uv = (
+                    (i as crate::Float + 0.5) / n as crate::Float,
+                    (j as crate::Float + 0.5) / n as crate::Float,
+                );
+                integral += distribution.pdf(uv);
+            }
+        }
+        integral /= (n * n) as crate::Float;
+        assert!((integral - 1.0).abs() < 1e3 * crate::Float::EPSILON);
+    }
+
+    #[test]
+    fn sample_matches_pdf() {
+        let distribution = ramp();
+        let mut counts = [0usize; 15];
+        const SAMPLES: usize = 150_000;
+        for _ in 0..SAMPLES {
+            let ((u, v), pdf) =
+                distribution.sample_continuous((utils::gen_float(), utils::gen_float()));
+            assert!((0.0..1.0).contains(&u) && (0.0..1.0).contains(&v));
+            assert!(pdf > 0.0);
+            assert_eq!(pdf, distribution.pdf((u, v)));
+            counts[(v * 3.0) as usize * 5 + (u * 5.0) as usize] += 1;
+        }
+        // zero steps are never picked, the others by their share
+        for (i, count) in counts.iter().enumerate() {
+            let expected = distribution.pdf((
+                ((i % 5) as crate::Float + 0.5) / 5.0,
+                ((i / 5) as crate::Float + 0.5) / 3.0,
+            )) / 15.0;
+            let fraction = *count as crate::Float / SAMPLES as crate::Float;
+            assert!(
+                (fraction - expected).abs() < 0.01,
+                "{} != {}",
+                fraction,
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn zero_is_uniform() {
+        let distribution = Distribution1D::new(vec![0.0; 4]);
+        assert_eq!(distribution.pdf(0.3), 1.0);
+        let (x, pdf, offset) = distribution.sample_continuous(0.6);
+        assert!((x - 0.6).abs() < 1e3 * crate::Float::EPSILON);
+        assert_eq!((pdf, offset), (1.0, 2));
+    }
+}
@@ -0,0 +1,196 @@
+use std::path::Path;
+
+use crate::color::{self, Color};
+use crate::texture;
+use crate::Vec3;
+
+use super::{direction_to_uv, Environment, LatLongDistribution};
+
+/// Equirectangular high dynamic range image, e.g. a Radiance `.hdr` or
+/// OpenEXR panorama, with +y at the top row and -z at the centre.
+pub struct ImageEnvironment {
+    width: usize,
+    height: usize,
+    /// Linear radiance, row major from the top row
+    pixels: Vec<Color>,
+    /// About +y, in radians
+    rotation: crate::Float,
+    distribution: LatLongDistribution,
+}
+
+impl ImageEnvironment {
+    pub fn new<P>(p: P) -> Self
+    where
+        P: AsRef<Path>,
+    {
+        Self::new_with_transform(p, 0.0, 1.0)
+    }
+
+    /// * `rotation` - About the vertical axis, in degrees
+    /// * `intensity` - Scale of the radiance stored in the image
+    pub fn new_with_transform<P>(p: P, rotation: crate::Float, intensity: crate::Float) -> Self
+    where
+        P: AsRef<Path>,
+    {
+        let (width, height, pixels) = texture::load_image(p.as_ref(), true);
+        let pixels: Vec<Color> = pixels.into_iter().map(|p| p * intensity).collect();
+        Self::new_with_pixels(width, height, pixels, rotation)
+    }
+
+    /// * `pixels` - Linear radiance, row major from the top row
+    /// * `rotation` - About the vertical axis, in degrees
+    pub fn new_with_pixels(
+        width: usize,
+        height: usize,
+        pixels: Vec<Color>,
+        rotation: crate::Float,
+    ) -> Self {
+        assert_eq!(pixels.len(), width * height);
+        let luminance: Vec<crate::Float> = pixels.iter().map(|p| color::luminance(*p)).collect();
+
+        Self {
+            width,
+            height,
+            distribution: LatLongDistribution::new(&luminance, width, height),
+            pixels,
+            rotation: rotation.to_radians(),
+        }
+    }
+
+    /// Turns a direction in the world into one in the image and back.
+    fn rotate(&self, direction: Vec3, angle: crate::Float) -> Vec3 {
+        let (sin, cos) = angle.sin_cos();
+        Vec3::new(
+            direction.x * cos + direction.z * sin,
+            direction.y,
+            -direction.x * sin + direction.z * cos,
+        )
+    }
+
+    fn pixel(&self, direction: Vec3) -> Color {
+        let (u, v) = direction_to_uv(direction);
+        let i = ((u * self.width as crate::Float) as usize).min(self.width - 1);
+        let j = ((v * self.height as crate::Float) as usize).min(self.height - 1);
+        self.pixels[j * self.width + i]
+    }
+}
+
+impl Environment for ImageEnvironment {
+    fn radiance(&self, direction: Vec3) -> Color {
+        self.pixel(self.rotate(direction, -self.rotation))
+    }
+
+    fn sample(&self) -> Option<(Vec3, Color, crate::Float)> {
+        let (direction, pdf) = self.distribution.sample()?;
+        Some((
+            self.rotate(direction, self.rotation),
+            self.pixel(direction),
+            pdf,
+        ))
+    }
+
+    fn pdf(&self, direction: Vec3) -> crate::Float {
+        self.distribution
+            .pdf(self.rotate(direction, -self.rotation))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::environment::uv_to_direction;
+    use crate::types::PI;
+    use crate::utils;
+
+    /// Dim, with one bright pixel above the horizon and a dimmer one below.
+    fn spots(rotation: crate::Float) -> ImageEnvironment {
+        let (width, height) = (16, 8);
+        let mut pixels = vec![Color::splat(0.1); width * height];
+        pixels[2 * width + 3] = Color::new(50.0, 40.0, 30.0);
+        pixels[6 * width + 12] = Color::splat(5.0);
+        ImageEnvironment::new_with_pixels(width, height, pixels, rotation)
+    }
+
+    #[test]
+    fn uv_round_trip() {
+        let tolerance = 10.0 * crate::Float::EPSILON.sqrt();
+        for _ in 0..1000 {
+            let (u, v) = (utils::gen_float(), utils::gen_float());
+            let direction = uv_to_direction(u, v);
+            assert!((direction.length() - 1.0).abs() < tolerance);
+            let (u2, v2) = direction_to_uv(direction);
+            let du = (u2 - u).abs();
+            assert!(du.min(1.0 - du) < tolerance, "{} != {}", u2, u);
+            assert!((v2 - v).abs() < tolerance, "{} != {}", v2, v);
+        }
+    }
+
+    #[test]
+    fn rotation_round_trip() {
+        let tolerance = 1e3 * crate::Float::EPSILON;
+        let environment = spots(70.0);
+        for _ in 0..1000 {
+            let direction = utils::rand_vec3_unit();
+            let angle = utils::gen_float() * 10.0;
+            let back = environment.rotate(environment.rotate(direction, angle), -angle);
+            assert!((back - direction).length() < tolerance);
+        }
+    }
+
+    #[test]
+    fn sample_matches_radiance_and_pdf() {
+        // the directions go through acos and back
+        let tolerance = 10.0 * crate::Float::EPSILON.sqrt();
+        for rotation in [0.0, 70.0] {
+            let environment = spots(rotation);
+            for _ in 0..1000 {
+                let (direction, radiance, pdf) = environment.sample().unwrap();
+                // away from pixel edges, where rounding may pick a neighbour
+                let (u, v) = direction_to_uv(environment.rotate(direction, -environment.rotation));
+                let (x, y) = ((u * 16.0).fract(), (v * 8.0).fract());
+                if x.min(1.0 - x) < 1e-3 || y.min(1.0 - y) < 1e-3 {
+                    continue;
+                }
+                assert_eq!(radiance, environment.radiance(direction));
+                let expected = environment.pdf(direction);
+                assert!(
+                    (pdf - expected).abs() <= tolerance * expected,
+                    "{} != {}",
+                    pdf,
+                    expected
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn pdf_integrates_to_one() {
+        let environment = spots(70.0);
+        // midpoints of a grid finer than the pixels, with the solid angle
+        // 2 pi^2 sin(theta) du dv of each cell
+        let n = 256;
+        let mut integral = 0.0;
+        for j in 0..n {
+            for i in 0..n {
+                let (u, v) = (
+                    (i as crate::Float + 0.5) / n as crate::Float,
+                    (j as crate::Float + 0.5) / n as crate::Float,
+                );
+                let direction = environment.rotate(uv_to_direction(u, v), environment.rotation);
+                let solid_angle = 2.0 * PI * PI * (v * PI).sin();
+                integral += environment.pdf(direction) * solid_angle;
+            }
+        }
+        integral /= (n * n) as crate::Float;
+        assert!((integral - 1.0).abs() < 1e-3, "{}", integral);
+
+        // and the bright pixel gets most samples
+        let bright = (0..10_000)
+            .filter(|_| {
+                let (direction, _, _) = environment.sample().unwrap();
+                environment.radiance(direction).x > 10.0
+            })
+            .count();
+        assert!(bright > 2500, "{}", bright);
+    }
+}
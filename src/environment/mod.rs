@@ -0,0 +1,100 @@
+//! Light arriving from infinitely far away, where rays that leave the scene
+//! end up.
+
+mod image;
+mod sky;
+
+pub use self::image::ImageEnvironment;
+pub use sky::Sky;
+
+use crate::color::Color;
+use crate::distribution::Distribution2D;
+use crate::types::PI;
+use crate::utils;
+use crate::Vec3;
+
+pub trait Environment: Sync + Send {
+    /// Radiance arriving along `-direction`, i.e. seen looking towards
+    /// `direction`.
+    fn radiance(&self, direction: Vec3) -> Color;
+
+    /// A normalized direction towards the environment, its radiance and its
+    /// density over solid angle. None if the environment is not worth
+    /// sampling directly and is only found by rays bouncing off surfaces.
+    fn sample(&self) -> Option<(Vec3, Color, crate::Float)> {
+        None
+    }
+
+    /// Density over solid angle of `sample` returning `direction`.
+    fn pdf(&self, direction: Vec3) -> crate::Float {
+        0.0
+    }
+}
+
+/// The same radiance in every direction.
+impl Environment for Color {
+    fn radiance(&self, direction: Vec3) -> Color {
+        *self
+    }
+}
+
+/// Equirectangular coordinates in [0, 1]^2 of a direction, +y at v = 0 and
+/// -z at the centre.
+fn direction_to_uv(direction: Vec3) -> (crate::Float, crate::Float) {
+    let d = direction.normalize();
+    let u = (d.x.atan2(-d.z) / (2.0 * PI) + 0.5).rem_euclid(1.0);
+    let v = d.y.clamp(-1.0, 1.0).acos() / PI;
+    (u, v)
+}
+
+fn uv_to_direction(u: crate::Float, v: crate::Float) -> Vec3 {
+    let phi = (u - 0.5) * 2.0 * PI;
+    let (sin_theta, cos_theta) = (v * PI).sin_cos();
+    Vec3::new(sin_theta * phi.sin(), cos_theta, -sin_theta * phi.cos())
+}
+
+/// Distribution of directions following a function tabulated over an
+/// equirectangular grid.
+struct LatLongDistribution {
+    distribution: Distribution2D,
+}
+
+impl LatLongDistribution {
+    /// * `function` - Row major from the top row, e.g. the luminance of pixels
+    fn new(function: &[crate::Float], width: usize, height: usize) -> Self {
+        // rows near the poles cover less solid angle
+        let function: Vec<crate::Float> = function
+            .iter()
+            .enumerate()
+            .map(|(i, f)| {
+                let theta = PI * ((i / width) as crate::Float + 0.5) / height as crate::Float;
+                f * theta.sin()
+            })
+            .collect();
+        Self {
+            distribution: Distribution2D::new(&function, width, height),
+        }
+    }
+
+    /// A normalized direction with its density over solid angle.
+    fn sample(&self) -> Option<(Vec3, crate::Float)> {
+        let ((u, v), pdf) = self
+            .distribution
+            .sample_continuous((utils::gen_float(), utils::gen_float()));
+        let sin_theta = (v * PI).sin();
+        if pdf <= 0.0 || sin_theta <= 0.0 {
+            return None;
+        }
+        // the grid spans 2 pi by pi radians
+        Some((uv_to_direction(u, v), pdf / (2.0 * PI * PI * sin_theta)))
+    }
+
+    fn pdf(&self, direction: Vec3) -> crate::Float {
+        let (u, v) = direction_to_uv(direction);
+        let sin_theta = (v * PI).sin();
+        if sin_theta <= 0.0 {
+            return 0.0;
+        }
+        self.distribution.pdf((u, v)) / (2.0 * PI * PI * sin_theta)
+    }
+}
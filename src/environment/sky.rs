@@ -0,0 +1,398 @@
+use crate::color::{self, Color};
+use crate::onb::Onb;
+use crate::spectrum;
+use crate::types::PI;
+use crate::utils;
+use crate::Vec3;
+
+use super::{uv_to_direction, Environment, LatLongDistribution};
+
+/// Scene radiance per kilocandela per square metre, a clear sky with the
+/// sun high up then lights a white surface about like a constant background
+/// of 1.
+const LUMINANCE_SCALE: crate::Float = 0.03;
+/// Illuminance of the sun above the atmosphere, in kilolux.
+const SUN_ILLUMINANCE: crate::Float = 128.0;
+/// Seen from the earth, in degrees.
+const SUN_ANGULAR_RADIUS: crate::Float = 0.267;
+
+/// Resolution of the table the sky is sampled with.
+const SAMPLING_WIDTH: usize = 128;
+const SAMPLING_HEIGHT: usize = 64;
+
+/// Coefficients A to E of Perez's sky luminance distribution.
+type Perez = [crate::Float; 5];
+
+/// Clear daylight sky (Preetham et al., A Practical Analytic Model for
+/// Daylight) with the sun. +y is up, the sky is black below the horizon
+/// where the ground would be.
+pub struct Sky {
+    sun_direction: Vec3,
+    /// Of the sun disk, in radians
+    sun_angular_radius: crate::Float,
+    /// Reaching the ground, lighting a surface facing the sun
+    sun_illuminance: Color,
+    /// Luminance Y and chromaticity x, y at the zenith
+    zenith: Vec3,
+    perez: [Perez; 3],
+    intensity: crate::Float,
+    sky_distribution: LatLongDistribution,
+    /// Probability of sampling the sun rather than the sky
+    sun_probability: crate::Float,
+}
+
+impl Sky {
+    /// * `sun_direction` - Towards the sun
+    /// * `turbidity` - Haziness of the air, 2 is a very clear sky and 10 a
+    ///   hazy one
+    pub fn new(sun_direction: Vec3, turbidity: crate::Float) -> Self {
+        let sun_direction = sun_direction.normalize();
+        let turbidity = turbidity.clamp(1.7, 10.0);
+        // the model is fitted for the sun above the horizon
+        let theta_sun = sun_direction.y.clamp(0.0, 1.0).acos();
+        let t = turbidity;
+
+        let chi = (4.0 / 9.0 - t / 120.0) * (PI - 2.0 * theta_sun);
+        let zenith_luminance = (4.0453 * t - 4.9710) * chi.tan() - 0.2155 * t + 2.4192;
+        let chromaticity = |m: [[crate::Float; 4]; 3]| {
+            let theta = [theta_sun.powi(3), theta_sun.powi(2), theta_sun, 1.0];
+            let row = |r: [crate::Float; 4]| r.iter().zip(theta.iter()).map(|(a, b)| a * b).sum();
+            let rows: [crate::Float; 3] = [row(m[0]), row(m[1]), row(m[2])];
+            t * t * rows[0] + t * rows[1] + rows[2]
+        };
+        let zenith_x = chromaticity([
+            [0.00166, -0.00375, 0.00209, 0.0],
+            [-0.02903, 0.06377, -0.03202, 0.00394],
+            [0.11693, -0.21196, 0.06052, 0.25886],
+        ]);
+        let zenith_y = chromaticity([
+            [0.00275, -0.00610, 0.00317, 0.0],
+            [-0.04214, 0.08970, -0.04153, 0.00516],
+            [0.15346, -0.26756, 0.06670, 0.26688],
+        ]);
+
+        let perez = [
+            [
+                0.1787 * t - 1.4630,
+                -0.3554 * t + 0.4275,
+                -0.0227 * t + 5.3251,
+                0.1206 * t - 2.5771,
+                -0.0670 * t + 0.3703,
+            ],
+            [
+                -0.0193 * t - 0.2592,
+                -0.0665 * t + 0.0008,
+                -0.0004 * t + 0.2125,
+                -0.0641 * t - 0.8989,
+                -0.0033 * t + 0.0452,
+            ],
+            [
+                -0.0167 * t - 0.2608,
+                -0.0950 * t + 0.0092,
+                -0.0079 * t + 0.2102,
+                -0.0441 * t - 1.6537,
+                -0.0109 * t + 0.0529,
+            ],
+        ];
+
+        let mut sky = Self {
+            sun_direction,
+            sun_angular_radius: SUN_ANGULAR_RADIUS.to_radians(),
+            sun_illuminance: SUN_ILLUMINANCE * sun_transmittance(theta_sun, turbidity),
+            zenith: Vec3::new(zenith_luminance, zenith_x, zenith_y),
+            perez,
+            intensity: 1.0,
+            sky_distribution: LatLongDistribution::new(&[1.0], 1, 1),
+            sun_probability: 0.0,
+        };
+
+        let luminance: Vec<crate::Float> = (0..SAMPLING_WIDTH * SAMPLING_HEIGHT)
+            .map(|i| {
+                let u =
+                    ((i % SAMPLING_WIDTH) as crate::Float + 0.5) / SAMPLING_WIDTH as crate::Float;
+                let v =
+                    ((i / SAMPLING_WIDTH) as crate::Float + 0.5) / SAMPLING_HEIGHT as crate::Float;
+                color::luminance(sky.sky_radiance(uv_to_direction(u, v)))
+            })
+            .collect();
+        // luminance integrated over the sky, to share samples with the sun
+        let sky_power = luminance
+            .iter()
+            .enumerate()
+            .map(|(i, l)| {
+                let v =
+                    ((i / SAMPLING_WIDTH) as crate::Float + 0.5) / SAMPLING_HEIGHT as crate::Float;
+                l * (v * PI).sin()
+            })
+            .sum::<crate::Float>()
+            * 2.0
+            * PI
+            * PI
+            / luminance.len() as crate::Float;
+        let sun_power = if sun_direction.y > -sky.sun_angular_radius.sin() {
+            color::luminance(sky.sun_illuminance) * LUMINANCE_SCALE
+        } else {
+            0.0
+        };
+        sky.sky_distribution =
+            LatLongDistribution::new(&luminance, SAMPLING_WIDTH, SAMPLING_HEIGHT);
+        sky.sun_probability = if sun_power > 0.0 {
+            (sun_power / (sun_power + sky_power)).clamp(0.1, 0.9)
+        } else {
+            0.0
+        };
+        sky
+    }
+
+    /// Sun at the position it has at a place and time.
+    ///
+    /// * `latitude` - In degrees, positive to the north
+    /// * `day` - Of the year, 1 is the 1st of January
+    /// * `hour` - Solar time, 12 is noon
+    ///
+    /// North is -z and east is +x.
+    pub fn new_at_time(
+        latitude: crate::Float,
+        day: crate::Float,
+        hour: crate::Float,
+        turbidity: crate::Float,
+    ) -> Self {
+        let latitude = latitude.to_radians();
+        let declination = 0.4093 * (2.0 * PI * (day - 81.0) / 368.0).sin();
+        let hour_angle = PI * (hour - 12.0) / 12.0;
+
+        let sin_elevation = latitude.sin() * declination.sin()
+            + latitude.cos() * declination.cos() * hour_angle.cos();
+        let cos_elevation = (1.0 - sin_elevation * sin_elevation).max(0.0).sqrt();
+        // clockwise from north
+        let azimuth = (-declination.cos() * hour_angle.sin()).atan2(
+            declination.sin() * latitude.cos()
+                - declination.cos() * latitude.sin() * hour_angle.cos(),
+        );
+        let sun_direction = Vec3::new(
+            azimuth.sin() * cos_elevation,
+            sin_elevation,
+            -azimuth.cos() * cos_elevation,
+        );
+        Self::new(sun_direction, turbidity)
+    }
+
+    /// The sun keeps lighting the scene as brightly, a larger one casts
+    /// softer shadows.
+    pub fn with_sun_radius(self, degrees: crate::Float) -> Self {
+        Self {
+            sun_angular_radius: degrees.max(0.01).to_radians(),
+            ..self
+        }
+    }
+
+    /// Scales the radiance of the sky and the sun.
+    pub fn with_intensity(self, intensity: crate::Float) -> Self {
+        Self { intensity, ..self }
+    }
+
+    fn sun_solid_angle(&self) -> crate::Float {
+        2.0 * PI * (1.0 - self.sun_angular_radius.cos())
+    }
+
+    fn sky_radiance(&self, direction: Vec3) -> Color {
+        let direction = direction.normalize();
+        if direction.y <= 0.0 {
+            return Color::splat(0.0);
+        }
+        let cos_theta = direction.y.max(0.01);
+        let cos_gamma = direction.dot(self.sun_direction).clamp(-1.0, 1.0);
+        let theta_sun = self.sun_direction.y.clamp(0.0, 1.0).acos();
+
+        let relative = |perez: &Perez| {
+            perez_luminance(perez, cos_theta, cos_gamma)
+                / perez_luminance(perez, 1.0, theta_sun.cos())
+        };
+        let luminance = self.zenith.x * relative(&self.perez[0]);
+        let x = self.zenith.y * relative(&self.perez[1]);
+        let y = self.zenith.z * relative(&self.perez[2]);
+        if luminance <= 0.0 || y <= 0.0 {
+            return Color::splat(0.0);
+        }
+        let xyz = Vec3::new(x / y * luminance, luminance, (1.0 - x - y) / y * luminance);
+        color::xyz_to_rgb(xyz).max(Color::splat(0.0)) * LUMINANCE_SCALE
+    }
+
+    fn sun_radiance(&self, direction: Vec3) -> Color {
+        let direction = direction.normalize();
+        if direction.y <= 0.0 || direction.dot(self.sun_direction) < self.sun_angular_radius.cos() {
+            return Color::splat(0.0);
+        }
+        self.sun_illuminance * LUMINANCE_SCALE / self.sun_solid_angle()
+    }
+}
+
+/// Perez's relative luminance of the sky at angle theta from the zenith and
+/// gamma from the sun.
+fn perez_luminance(
+    perez: &Perez,
+    cos_theta: crate::Float,
+    cos_gamma: crate::Float,
+) -> crate::Float {
+    let [a, b, c, d, e] = *perez;
+    let gamma = cos_gamma.acos();
+    (1.0 + a * (b / cos_theta).exp()) * (1.0 + c * (d * gamma).exp() + e * cos_gamma * cos_gamma)
+}
+
+/// Of the sunlight through the atmosphere by Rayleigh and aerosol scattering
+/// at the wavelengths of the primaries (Preetham et al., appendix A.2).
+fn sun_transmittance(theta_sun: crate::Float, turbidity: crate::Float) -> Color {
+    // relative optical mass of the air along the path
+    let degrees = theta_sun.to_degrees().min(93.0);
+    let mass = 1.0 / (theta_sun.cos().max(0.0) + 0.15 * (93.885 - degrees).powf(-1.253));
+    let beta = 0.04608 * turbidity - 0.04586;
+    let at = |wavelength: crate::Float| {
+        let micrometres = wavelength / 1000.0;
+        let rayleigh = (-0.008735 * micrometres.powf(-4.08) * mass).exp();
+        let aerosol = (-beta * micrometres.powf(-1.3) * mass).exp();
+        rayleigh * aerosol
+    };
+    let [red, green, blue] = spectrum::PRIMARY_WAVELENGTHS;
+    Color::new(at(red), at(green), at(blue))
+}
+
+impl Environment for Sky {
+    fn radiance(&self, direction: Vec3) -> Color {
+        (self.sky_radiance(direction) + self.sun_radiance(direction)) * self.intensity
+    }
+
+    fn sample(&self) -> Option<(Vec3, Color, crate::Float)> {
+        let direction = if utils::gen_float() < self.sun_probability {
+            // uniform in the cone of the disk
+            let cos_theta = 1.0 - utils::gen_float() * (1.0 - self.sun_angular_radius.cos());
+            let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+            let phi = 2.0 * PI * utils::gen_float();
+            Onb::new_from_w(self.sun_direction).to_world(Vec3::new(
+                sin_theta * phi.cos(),
+                sin_theta * phi.sin(),
+                cos_theta,
+            ))
+        } else {
+            self.sky_distribution.sample()?.0
+        };
+        let pdf = self.pdf(direction);
+        if pdf <= 0.0 {
+            return None;
+        }
+        Some((direction, self.radiance(direction), pdf))
+    }
+
+    fn pdf(&self, direction: Vec3) -> crate::Float {
+        let direction = direction.normalize();
+        let sun = if direction.dot(self.sun_direction) >= self.sun_angular_radius.cos() {
+            1.0 / self.sun_solid_angle()
+        } else {
+            0.0
+        };
+        self.sun_probability * sun
+            + (1.0 - self.sun_probability) * self.sky_distribution.pdf(direction)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TOLERANCE: crate::Float = 1e3 * crate::Float::EPSILON;
+
+    /// Sun 70 degrees above the horizon towards +x.
+    fn high_sun() -> Sky {
+        let elevation = (70.0 as crate::Float).to_radians();
+        Sky::new(Vec3::new(elevation.cos(), elevation.sin(), 0.0), 3.0)
+    }
+
+    #[test]
+    fn zenith_brighter_and_bluer() {
+        let sky = high_sun();
+        let zenith = sky.radiance(Vec3::Y);
+        for horizon in [Vec3::new(-1.0, 0.05, 0.0), Vec3::new(0.0, 0.05, 1.0)] {
+            let horizon = sky.radiance(horizon);
+            assert!(color::luminance(zenith) > color::luminance(horizon));
+            assert!(zenith.z / zenith.x > horizon.z / horizon.x);
+        }
+        assert_eq!(sky.radiance(Vec3::new(0.3, -0.5, 0.1)), Color::splat(0.0));
+    }
+
+    #[test]
+    fn sun_position() {
+        let tolerance = 1e-2;
+        let direction =
+            |latitude, day, hour| Sky::new_at_time(latitude, day, hour, 3.0).sun_direction;
+        // overhead at noon on the equator at the equinox
+        let noon = direction(0.0, 81.0, 12.0);
+        assert!((noon - Vec3::Y).length() < tolerance, "{}", noon);
+        // rising in the east
+        let morning = direction(0.0, 81.0, 6.0);
+        assert!((morning - Vec3::X).length() < tolerance, "{}", morning);
+        // due south and halfway up at 45 degrees north
+        let south = direction(45.0, 81.0, 12.0);
+        let half = std::f64::consts::FRAC_1_SQRT_2 as crate::Float;
+        assert!(
+            (south - Vec3::new(0.0, half, half)).length() < tolerance,
+            "{}",
+            south
+        );
+        // higher in summer than in winter
+        assert!(direction(45.0, 172.0, 12.0).y > direction(45.0, 355.0, 12.0).y + 0.5);
+    }
+
+    #[test]
+    fn sun_radius_keeps_illuminance() {
+        let sky = high_sun();
+        let sun = sky.sun_direction;
+        let sun_radiance = |sky: &Sky| sky.radiance(sun) - sky.sky_radiance(sun);
+        let illuminance = sun_radiance(&sky) * sky.sun_solid_angle();
+
+        let large = high_sun().with_sun_radius(2.0);
+        assert!(color::luminance(sun_radiance(&large)) < color::luminance(sun_radiance(&sky)));
+        let large_illuminance = sun_radiance(&large) * large.sun_solid_angle();
+        assert!((large_illuminance - illuminance).length() < TOLERANCE * illuminance.length());
+
+        // the larger disk is seen further from its centre
+        let edge = Onb::new_from_w(sun).to_world(Vec3::new(
+            (1.0 as crate::Float).to_radians().sin(),
+            0.0,
+            (1.0 as crate::Float).to_radians().cos(),
+        ));
+        assert_eq!(sky.sun_radiance(edge), Color::splat(0.0));
+        assert!(color::luminance(large.sun_radiance(edge)) > 0.0);
+    }
+
+    #[test]
+    fn sample_matches_pdf() {
+        const SAMPLES: usize = 200_000;
+        let sky = high_sun().with_sun_radius(1.0);
+        let mut solid_angle = 0.0;
+        let mut sun = 0;
+        for _ in 0..SAMPLES {
+            let (direction, radiance, pdf) = sky.sample().unwrap();
+            assert!(direction.y > 0.0);
+            assert_eq!(pdf, sky.pdf(direction));
+            assert_eq!(radiance, sky.radiance(direction));
+            solid_angle += 1.0 / pdf;
+            if direction.dot(sky.sun_direction) >= sky.sun_angular_radius.cos() {
+                sun += 1;
+            }
+        }
+        // every direction the sky is sampled in, i.e. the upper hemisphere
+        solid_angle /= SAMPLES as crate::Float;
+        assert!(
+            (solid_angle - 2.0 * PI).abs() < 0.05 * 2.0 * PI,
+            "{}",
+            solid_angle
+        );
+        let fraction = sun as crate::Float / SAMPLES as crate::Float;
+        // the sky around the sun adds few samples to the disk
+        assert!(
+            (fraction - sky.sun_probability).abs() < 0.01,
+            "{} != {}",
+            fraction,
+            sky.sun_probability
+        );
+    }
+}
@@ -24,6 +24,16 @@ impl Aabb {
     }
 
     pub fn hit(&self, r: &Ray, t_min: crate::Float, t_max: crate::Float) -> bool {
+        self.hit_interval(r, t_min, t_max).is_some()
+    }
+
+    /// The part of `[t_min, t_max]` where the ray is inside the box.
+    pub fn hit_interval(
+        &self,
+        r: &Ray,
+        t_min: crate::Float,
+        t_max: crate::Float,
+    ) -> Option<(crate::Float, crate::Float)> {
         let mut t_min = t_min;
         let mut t_max = t_max;
         for i in 0..3 {
@@ -36,10 +46,10 @@ impl Aabb {
             t_min = t0.max(t_min);
             t_max = t1.min(t_max);
             if t_max <= t_min {
-                return false;
+                return None;
             }
         }
-        true
+        Some((t_min, t_max))
     }
 
     pub fn surrounding_box(&self, other: &Aabb) -> Aabb {
@@ -1,9 +1,11 @@
 use std::sync::Arc;
 
-use super::Aabb;
+use super::{area_pdf, Aabb};
 use crate::hittable::{HitRecord, Hittable};
+use crate::light::DirectionCone;
 use crate::material::Material;
 use crate::types::Point3;
+use crate::utils;
 use crate::Vec3;
 
 const AABB_EPSILON: crate::Float = 0.0001;
@@ -118,7 +120,11 @@ impl Hittable for XYRect {
             t,
             u: (x - self.x0) / (self.x1 - self.x0),
             v: (y - self.y0) / (self.y1 - self.y0),
+            dpdu: Vec3::new(self.x1 - self.x0, 0.0, 0.0),
+            dpdv: Vec3::new(0.0, self.y1 - self.y0, 0.0),
             front_face: false,
+            light: None,
+            uv_footprint: (0.0, 0.0),
         };
         rec.set_face_normal(r, Vec3::new(0.0, 0.0, 1.0));
         Some(rec)
@@ -130,6 +136,27 @@ impl Hittable for XYRect {
             Point3::new(self.x1, self.y1, self.k + AABB_EPSILON),
         ))
     }
+
+    fn pdf_value(&self, origin: Point3, direction: Vec3) -> crate::Float {
+        area_pdf(
+            self,
+            (self.x1 - self.x0) * (self.y1 - self.y0),
+            origin,
+            direction,
+        )
+    }
+
+    fn random(&self, origin: Point3) -> Vec3 {
+        Point3::new(
+            self.x0 + utils::gen_float() * (self.x1 - self.x0),
+            self.y0 + utils::gen_float() * (self.y1 - self.y0),
+            self.k,
+        ) - origin
+    }
+
+    fn normal_bounds(&self) -> DirectionCone {
+        DirectionCone::new(Vec3::Z, 1.0)
+    }
 }
 
 impl Hittable for XZRect {
@@ -155,7 +182,11 @@ impl Hittable for XZRect {
             t,
             u: (x - self.x0) / (self.x1 - self.x0),
             v: (z - self.z0) / (self.z1 - self.z0),
+            dpdu: Vec3::new(self.x1 - self.x0, 0.0, 0.0),
+            dpdv: Vec3::new(0.0, 0.0, self.z1 - self.z0),
             front_face: false,
+            light: None,
+            uv_footprint: (0.0, 0.0),
         };
         rec.set_face_normal(r, Vec3::new(0.0, 1.0, 0.0));
         Some(rec)
@@ -167,6 +198,27 @@ impl Hittable for XZRect {
             Point3::new(self.x1, self.k + AABB_EPSILON, self.z1),
         ))
     }
+
+    fn pdf_value(&self, origin: Point3, direction: Vec3) -> crate::Float {
+        area_pdf(
+            self,
+            (self.x1 - self.x0) * (self.z1 - self.z0),
+            origin,
+            direction,
+        )
+    }
+
+    fn random(&self, origin: Point3) -> Vec3 {
+        Point3::new(
+            self.x0 + utils::gen_float() * (self.x1 - self.x0),
+            self.k,
+            self.z0 + utils::gen_float() * (self.z1 - self.z0),
+        ) - origin
+    }
+
+    fn normal_bounds(&self) -> DirectionCone {
+        DirectionCone::new(Vec3::Y, 1.0)
+    }
 }
 
 impl Hittable for YZRect {
@@ -192,7 +244,11 @@ impl Hittable for YZRect {
             t,
             u: (y - self.y0) / (self.y1 - self.y0),
             v: (z - self.z0) / (self.z1 - self.z0),
+            dpdu: Vec3::new(0.0, self.y1 - self.y0, 0.0),
+            dpdv: Vec3::new(0.0, 0.0, self.z1 - self.z0),
             front_face: false,
+            light: None,
+            uv_footprint: (0.0, 0.0),
         };
         rec.set_face_normal(r, Vec3::new(1.0, 0.0, 0.0));
         Some(rec)
@@ -204,4 +260,25 @@ impl Hittable for YZRect {
             Point3::new(self.k + AABB_EPSILON, self.y1, self.z1),
         ))
     }
+
+    fn pdf_value(&self, origin: Point3, direction: Vec3) -> crate::Float {
+        area_pdf(
+            self,
+            (self.y1 - self.y0) * (self.z1 - self.z0),
+            origin,
+            direction,
+        )
+    }
+
+    fn random(&self, origin: Point3) -> Vec3 {
+        Point3::new(
+            self.k,
+            self.y0 + utils::gen_float() * (self.y1 - self.y0),
+            self.z0 + utils::gen_float() * (self.z1 - self.z0),
+        ) - origin
+    }
+
+    fn normal_bounds(&self) -> DirectionCone {
+        DirectionCone::new(Vec3::X, 1.0)
+    }
 }
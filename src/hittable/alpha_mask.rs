@@ -0,0 +1,91 @@
+use std::sync::Arc;
+
+use super::{Aabb, HitRecord, Hittable, HIT_ALL_EPSILON};
+use crate::light::DirectionCone;
+use crate::ray::Ray;
+use crate::texture::Texture;
+use crate::types::Point3;
+use crate::utils;
+use crate::Vec3;
+
+#[derive(Clone, Copy)]
+pub enum AlphaMode {
+    /// Hits are kept with the alpha as probability, partial alpha blends
+    /// smoothly once averaged over samples.
+    Stochastic,
+    /// Hits with alpha below the threshold are skipped, giving hard edges.
+    Threshold(crate::Float),
+}
+
+/// Cutout of a surface, e.g. leaves or a fence on a flat rect.
+///
+/// Wherever the alpha texture makes a hit transparent the ray carries on to
+/// the next surface behind it, of this object or of anything else, so the
+/// mask works the same inside a `HittableList` or a `BVHNode`. Sampling
+/// directions towards it covers the whole surface, cut out parts included.
+pub struct AlphaMask {
+    hittable: Arc<dyn Hittable>,
+    /// opacity, read from the red channel
+    alpha: Arc<dyn Texture>,
+    mode: AlphaMode,
+}
+
+impl AlphaMask {
+    /// Deterministic mask, opaque where alpha is at least `threshold`.
+    pub fn new(
+        hittable: Arc<dyn Hittable>,
+        alpha: Arc<dyn Texture>,
+        threshold: crate::Float,
+    ) -> Self {
+        Self::new_with_mode(hittable, alpha, AlphaMode::Threshold(threshold))
+    }
+
+    pub fn new_with_mode(
+        hittable: Arc<dyn Hittable>,
+        alpha: Arc<dyn Texture>,
+        mode: AlphaMode,
+    ) -> Self {
+        Self {
+            hittable,
+            alpha,
+            mode,
+        }
+    }
+
+    fn is_opaque(&self, rec: &HitRecord) -> bool {
+        let alpha = self.alpha.scalar(rec.u, rec.v, rec.p);
+        match self.mode {
+            AlphaMode::Stochastic => utils::gen_float() < alpha,
+            AlphaMode::Threshold(threshold) => alpha >= threshold,
+        }
+    }
+}
+
+impl Hittable for AlphaMask {
+    fn hit(&self, r: &Ray, t_min: crate::Float, t_max: crate::Float) -> Option<HitRecord> {
+        let mut t_min = t_min;
+        loop {
+            let rec = self.hittable.hit(r, t_min, t_max)?;
+            if self.is_opaque(&rec) {
+                return Some(rec);
+            }
+            t_min = rec.t + HIT_ALL_EPSILON;
+        }
+    }
+
+    fn bounding_box(&self, time_0: crate::Float, time_1: crate::Float) -> Option<Aabb> {
+        self.hittable.bounding_box(time_0, time_1)
+    }
+
+    fn pdf_value(&self, origin: Point3, direction: Vec3) -> crate::Float {
+        self.hittable.pdf_value(origin, direction)
+    }
+
+    fn random(&self, origin: Point3) -> Vec3 {
+        self.hittable.random(origin)
+    }
+
+    fn normal_bounds(&self) -> DirectionCone {
+        self.hittable.normal_bounds()
+    }
+}
@@ -1,14 +1,15 @@
 use std::cmp::Ordering;
 use std::sync::Arc;
 
-use super::Aabb;
+use super::{Aabb, HittableList};
 use crate::hittable::Hittable;
 use crate::utils;
 
 pub struct BVHNode {
     left: Arc<dyn Hittable>,
     right: Arc<dyn Hittable>,
-    root: Aabb,
+    /// `None` if the node holds unbounded objects, which is only ever the root
+    root: Option<Aabb>,
 }
 
 #[inline(always)]
@@ -37,8 +38,39 @@ fn box_z_compare(a: &Arc<dyn Hittable>, b: &Arc<dyn Hittable>) -> Ordering {
 }
 
 impl BVHNode {
+    /// Objects without a bounding box are kept out of the hierarchy and
+    /// tested against every ray.
     pub fn new(objects: &[Arc<dyn Hittable>], time_0: crate::Float, time_1: crate::Float) -> Self {
-        let mut objects = objects.to_vec();
+        let (bounded, unbounded): (Vec<_>, Vec<_>) = objects
+            .iter()
+            .cloned()
+            .partition(|object| object.bounding_box(time_0, time_1).is_some());
+        if unbounded.is_empty() && !bounded.is_empty() {
+            return Self::new_bounded(bounded, time_0, time_1);
+        }
+
+        let left: Arc<dyn Hittable> = if bounded.is_empty() {
+            Arc::new(HittableList::new())
+        } else {
+            Arc::new(Self::new_bounded(bounded, time_0, time_1))
+        };
+        let mut right = HittableList::new();
+        for object in unbounded {
+            right.add(object);
+        }
+
+        Self {
+            left,
+            right: Arc::new(right),
+            root: None,
+        }
+    }
+
+    fn new_bounded(
+        mut objects: Vec<Arc<dyn Hittable>>,
+        time_0: crate::Float,
+        time_1: crate::Float,
+    ) -> Self {
         let axis = utils::gen_range(0..=2);
         let comparator = match axis {
             0 => box_x_compare,
@@ -63,13 +95,13 @@ impl BVHNode {
             }
         } else {
             objects.sort_unstable_by(comparator);
-            let mid = objects.len() / 2;
-            left = Arc::new(BVHNode::new(&objects[0..mid], time_0, time_1));
-            right = Arc::new(BVHNode::new(&objects[mid..], time_0, time_1));
+            let right_objects = objects.split_off(objects.len() / 2);
+            left = Arc::new(BVHNode::new_bounded(objects, time_0, time_1));
+            right = Arc::new(BVHNode::new_bounded(right_objects, time_0, time_1));
         }
         let box_left = left.bounding_box(time_0, time_1).unwrap();
         let box_right = right.bounding_box(time_0, time_1).unwrap();
-        let root = box_left.surrounding_box(&box_right);
+        let root = Some(box_left.surrounding_box(&box_right));
 
         Self { left, right, root }
     }
@@ -82,8 +114,10 @@ impl Hittable for BVHNode {
         t_min: crate::Float,
         t_max: crate::Float,
     ) -> Option<crate::hittable::HitRecord> {
-        if !self.root.hit(r, t_min, t_max) {
-            return None;
+        if let Some(root) = &self.root {
+            if !root.hit(r, t_min, t_max) {
+                return None;
+            }
         }
         let hit_left = self.left.hit(r, t_min, t_max);
         let hit_right = self.right.hit(
@@ -105,6 +139,63 @@ impl Hittable for BVHNode {
     }
 
     fn bounding_box(&self, _time_0: crate::Float, _time_1: crate::Float) -> Option<Aabb> {
-        Some(self.root.clone())
+        self.root.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::Color;
+    use crate::hittable::{Plane, Sphere};
+    use crate::material::Lambertian;
+    use crate::ray::Ray;
+    use crate::types::Point3;
+    use crate::Vec3;
+
+    #[test]
+    fn unbounded_objects() {
+        let material = Arc::new(Lambertian::new_with_color(Color::splat(0.5)));
+        let mut objects: Vec<Arc<dyn Hittable>> = (0..20)
+            .map(|i| {
+                let center = Point3::new((i % 5) as crate::Float, (i / 5) as crate::Float, 0.0);
+                Arc::new(Sphere::new(center, 0.3, material.clone())) as Arc<dyn Hittable>
+            })
+            .collect();
+        objects.insert(
+            7,
+            Arc::new(Plane::new(
+                Point3::new(0.0, -1.0, 0.0),
+                Vec3::Y,
+                material.clone(),
+            )),
+        );
+        let bvh = BVHNode::new(&objects, 0.0, 1.0);
+        assert!(bvh.bounding_box(0.0, 1.0).is_none());
+
+        // the same hits as testing every object
+        let mut list = HittableList::new();
+        for object in &objects {
+            list.add(object.clone());
+        }
+        for _ in 0..2000 {
+            let r = Ray::new(utils::rand_vec3_range(-3.0, 7.0), utils::rand_vec3_unit());
+            let hit = bvh.hit(&r, 0.001, crate::Float::INFINITY).map(|rec| rec.t);
+            let expected = list.hit(&r, 0.001, crate::Float::INFINITY).map(|rec| rec.t);
+            assert_eq!(hit, expected);
+        }
+
+        // far from the spheres only the plane is there
+        let r = Ray::new(Point3::new(500.0, 10.0, 500.0), -Vec3::Y);
+        let rec = bvh.hit(&r, 0.001, crate::Float::INFINITY).unwrap();
+        assert_eq!(rec.t, 11.0);
+
+        // only a plane, or only bounded objects
+        let plane = BVHNode::new(&objects[7..8], 0.0, 1.0);
+        assert!(plane.hit(&r, 0.001, crate::Float::INFINITY).is_some());
+        objects.remove(7);
+        assert!(BVHNode::new(&objects, 0.0, 1.0)
+            .bounding_box(0.0, 1.0)
+            .is_some());
     }
 }
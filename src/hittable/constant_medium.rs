@@ -48,23 +48,14 @@ impl Hittable for ConstantMedium {
         t_max: crate::Float,
     ) -> Option<super::HitRecord> {
         // get smallest t hit
-        let mut rec_1 =
-            match self
-                .boundary
-                .hit(r, crate::Float::NEG_INFINITY, crate::Float::INFINITY)
-            {
-                Some(rec) => rec,
-                None => return None,
-            };
+        let mut rec_1 = self
+            .boundary
+            .hit(r, crate::Float::NEG_INFINITY, crate::Float::INFINITY)?;
 
         // get second hit point, must be convex
-        let mut rec_2 = match self
+        let mut rec_2 = self
             .boundary
-            .hit(r, rec_1.t + 0.0001, crate::Float::INFINITY)
-        {
-            Some(rec) => rec,
-            None => return None,
-        };
+            .hit(r, rec_1.t + 0.0001, crate::Float::INFINITY)?;
 
         // handle cases where ray origin inside volume
         if rec_1.t < t_min {
@@ -103,7 +94,11 @@ impl Hittable for ConstantMedium {
             t,
             u: 0.0,
             v: 0.0,
+            dpdu: Vec3::ZERO,
+            dpdv: Vec3::ZERO,
             front_face: true,
+            light: None,
+            uv_footprint: (0.0, 0.0),
         };
 
         Some(rec)
@@ -0,0 +1,251 @@
+use std::sync::Arc;
+
+use super::Aabb;
+use crate::hittable::{HitRecord, Hittable};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum CsgOperation {
+    Union,
+    Intersection,
+    /// left minus right
+    Difference,
+}
+
+impl CsgOperation {
+    fn inside(self, inside_left: bool, inside_right: bool) -> bool {
+        match self {
+            CsgOperation::Union => inside_left || inside_right,
+            CsgOperation::Intersection => inside_left && inside_right,
+            CsgOperation::Difference => inside_left && !inside_right,
+        }
+    }
+}
+
+/// Boolean combination of two closed hittables.
+///
+/// Both operands must be closed surfaces: the inside/outside state along the
+/// ray is reconstructed from the parity of the crossings returned by
+/// `Hittable::hit_all`, each one entering or leaving the operand.
+pub struct Csg {
+    operation: CsgOperation,
+    left: Arc<dyn Hittable>,
+    right: Arc<dyn Hittable>,
+}
+
+impl Csg {
+    pub fn new(operation: CsgOperation, left: Arc<dyn Hittable>, right: Arc<dyn Hittable>) -> Self {
+        Self {
+            operation,
+            left,
+            right,
+        }
+    }
+
+    pub fn union(left: Arc<dyn Hittable>, right: Arc<dyn Hittable>) -> Self {
+        Self::new(CsgOperation::Union, left, right)
+    }
+
+    pub fn intersection(left: Arc<dyn Hittable>, right: Arc<dyn Hittable>) -> Self {
+        Self::new(CsgOperation::Intersection, left, right)
+    }
+
+    pub fn difference(left: Arc<dyn Hittable>, right: Arc<dyn Hittable>) -> Self {
+        Self::new(CsgOperation::Difference, left, right)
+    }
+}
+
+/// A ray starting inside a closed shape crosses it an odd number of times.
+fn starts_inside(hits: &[HitRecord]) -> bool {
+    hits.len() % 2 == 1
+}
+
+impl Hittable for Csg {
+    fn hit(
+        &self,
+        r: &crate::ray::Ray,
+        t_min: crate::Float,
+        t_max: crate::Float,
+    ) -> Option<HitRecord> {
+        self.hit_all(r, t_min, t_max).into_iter().next()
+    }
+
+    fn hit_all(
+        &self,
+        r: &crate::ray::Ray,
+        t_min: crate::Float,
+        t_max: crate::Float,
+    ) -> Vec<HitRecord> {
+        // Crossings past t_max are still needed to know whether the ray
+        // starts inside an operand.
+        let left_hits = self.left.hit_all(r, t_min, crate::Float::INFINITY);
+        if left_hits.is_empty() && self.operation != CsgOperation::Union {
+            return vec![];
+        }
+        let right_hits = self.right.hit_all(r, t_min, crate::Float::INFINITY);
+
+        let mut inside_left = starts_inside(&left_hits);
+        let mut inside_right = starts_inside(&right_hits);
+        let mut inside = self.operation.inside(inside_left, inside_right);
+
+        let mut result = vec![];
+        let mut left_iter = left_hits.into_iter().peekable();
+        let mut right_iter = right_hits.into_iter().peekable();
+        loop {
+            let from_left = match (left_iter.peek(), right_iter.peek()) {
+                (Some(l), Some(r)) => l.t <= r.t,
+                (Some(_), None) => true,
+                (None, Some(_)) => false,
+                (None, None) => break,
+            };
+            let mut rec = if from_left {
+                let rec = left_iter.next().unwrap();
+                inside_left = !inside_left;
+                rec
+            } else {
+                let rec = right_iter.next().unwrap();
+                inside_right = !inside_right;
+                rec
+            };
+            if rec.t > t_max {
+                break;
+            }
+
+            let now_inside = self.operation.inside(inside_left, inside_right);
+            if now_inside != inside {
+                // the normal already faces the ray, only the side changes,
+                // e.g. entering the subtracted shape exits the difference
+                rec.front_face = now_inside;
+                result.push(rec);
+                inside = now_inside;
+            }
+        }
+
+        result
+    }
+
+    fn bounding_box(&self, time_0: crate::Float, time_1: crate::Float) -> Option<Aabb> {
+        let left = self.left.bounding_box(time_0, time_1);
+        match self.operation {
+            CsgOperation::Union => {
+                let right = self.right.bounding_box(time_0, time_1)?;
+                left.map(|left| left.surrounding_box(&right))
+            }
+            CsgOperation::Intersection => match (left, self.right.bounding_box(time_0, time_1)) {
+                (Some(left), Some(right)) => {
+                    let min = left.min().max(right.min());
+                    let max = left.max().min(right.max());
+                    Some(Aabb::new(min, max.max(min)))
+                }
+                (left, right) => left.or(right),
+            },
+            CsgOperation::Difference => left,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hittable::{Box, RotateY, Sphere, Translate};
+    use crate::material::{Lambertian, Material};
+    use crate::ray::Ray;
+    use crate::types::Point3;
+    use crate::{Color, Vec3};
+
+    const TOLERANCE: crate::Float = 1e-4;
+
+    fn material() -> Arc<dyn Material> {
+        Arc::new(Lambertian::new_with_color(Color::splat(0.5)))
+    }
+
+    fn sphere(x: crate::Float, radius: crate::Float) -> Arc<dyn Hittable> {
+        Arc::new(Sphere::new(Point3::new(x, 0.0, 0.0), radius, material()))
+    }
+
+    /// `t` and `front_face` of every crossing of a ray along x.
+    fn crossings(csg: &Csg, x: crate::Float) -> Vec<(crate::Float, bool)> {
+        let r = Ray::new(Point3::new(x, 0.0, 0.0), Vec3::X);
+        csg.hit_all(&r, 0.001, crate::Float::INFINITY)
+            .iter()
+            .map(|rec| (rec.t, rec.front_face))
+            .collect()
+    }
+
+    fn assert_crossings(actual: &[(crate::Float, bool)], expected: &[(crate::Float, bool)]) {
+        assert_eq!(actual.len(), expected.len(), "{:?}", actual);
+        for ((t, front_face), (expected_t, expected_front_face)) in actual.iter().zip(expected) {
+            assert!((t - expected_t).abs() < TOLERANCE, "{:?}", actual);
+            assert_eq!(front_face, expected_front_face, "{:?}", actual);
+        }
+    }
+
+    #[test]
+    fn union() {
+        // spheres over [-1, 1] and [0, 2]
+        let csg = Csg::union(sphere(0.0, 1.0), sphere(1.0, 1.0));
+        assert_crossings(&crossings(&csg, -5.0), &[(4.0, true), (7.0, false)]);
+        assert_crossings(&crossings(&csg, 0.5), &[(1.5, false)]);
+    }
+
+    #[test]
+    fn intersection() {
+        let csg = Csg::intersection(sphere(0.0, 1.0), sphere(1.0, 1.0));
+        assert_crossings(&crossings(&csg, -5.0), &[(5.0, true), (6.0, false)]);
+        // inside the first sphere only
+        assert_crossings(&crossings(&csg, -0.5), &[(0.5, true), (1.5, false)]);
+        // disjoint operands
+        let csg = Csg::intersection(sphere(0.0, 1.0), sphere(3.0, 1.0));
+        assert!(crossings(&csg, -5.0).is_empty());
+    }
+
+    #[test]
+    fn difference() {
+        // a shell: [-2, 2] minus [-1, 1]
+        let csg = Csg::difference(sphere(0.0, 2.0), sphere(0.0, 1.0));
+        assert_crossings(
+            &crossings(&csg, -5.0),
+            &[(3.0, true), (4.0, false), (6.0, true), (7.0, false)],
+        );
+        // from the hollow, leaving the subtracted sphere enters the shell
+        assert_crossings(&crossings(&csg, 0.0), &[(1.0, true), (2.0, false)]);
+        // from inside the shell
+        assert_crossings(
+            &crossings(&csg, -1.5),
+            &[(0.5, false), (2.5, true), (3.5, false)],
+        );
+        // from the hollow
+        let r = Ray::new(Point3::new(-5.0, 0.0, 0.0), Vec3::X);
+        let rec = csg.hit(&r, 4.5, 10.0).unwrap();
+        assert!((rec.t - 6.0).abs() < TOLERANCE);
+        assert!(rec.front_face);
+    }
+
+    #[test]
+    fn transformed_operands() {
+        // boxes over [-1, 1] and [0, 2] on x, moved and turned
+        let block = |x: crate::Float| -> Arc<dyn Hittable> {
+            let b = Arc::new(Box::new(
+                Point3::new(-1.0, -1.0, -1.0),
+                Point3::new(1.0, 1.0, 1.0),
+                material(),
+            ));
+            let b = Arc::new(RotateY::new(b, 90.0));
+            Arc::new(Translate::new(b, Vec3::new(x, 0.0, 0.0)))
+        };
+        let csg = Csg::difference(block(0.0), block(1.0));
+        assert_crossings(&crossings(&csg, -5.0), &[(4.0, true), (5.0, false)]);
+        let csg = Csg::intersection(block(0.0), block(1.0));
+        assert_crossings(&crossings(&csg, 0.5), &[(0.5, false)]);
+    }
+
+    #[test]
+    fn bounds() {
+        let csg = Csg::intersection(sphere(0.0, 1.0), sphere(1.0, 1.0));
+        let bounds = csg.bounding_box(0.0, 1.0).unwrap();
+        assert!((bounds.min().x - 0.0).abs() < TOLERANCE);
+        assert!((bounds.max().x - 1.0).abs() < TOLERANCE);
+        let csg = Csg::difference(sphere(0.0, 1.0), sphere(1.0, 1.0));
+        let bounds = csg.bounding_box(0.0, 1.0).unwrap();
+        assert!((bounds.max().x - 1.0).abs() < TOLERANCE);
+    }
+}
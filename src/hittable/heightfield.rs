@@ -0,0 +1,535 @@
+use std::path::Path;
+use std::sync::Arc;
+
+use super::Aabb;
+use crate::color;
+use crate::hittable::{HitRecord, Hittable};
+use crate::material::Material;
+use crate::perlin::Perlin;
+use crate::ray::Ray;
+use crate::texture;
+use crate::types::Point3;
+use crate::Vec3;
+
+const TRIANGLE_EPSILON: crate::Float = 1e-9;
+/// Padding keeping the boxes of flat blocks from having zero thickness.
+const AABB_EPSILON: crate::Float = 0.0001;
+
+/// Terrain made of a regular grid of height samples in the xz plane.
+///
+/// Every grid cell is split into two triangles, shading normals are
+/// interpolated from per sample normals. Rays walk a quadtree of per block
+/// minimum and maximum heights, so only cells near the ray are tested.
+pub struct Heightfield {
+    /// normalized to [0, 1], row major along x
+    heights: Vec<crate::Float>,
+    normals: Vec<Vec3>,
+    /// samples along x and z
+    width: usize,
+    depth: usize,
+    min: Point3,
+    size: Vec3,
+    /// min and max heights of 2^level x 2^level blocks of cells, level 0 is single cells
+    levels: Vec<MinMaxLevel>,
+    material: Arc<dyn Material>,
+}
+
+struct MinMaxLevel {
+    width: usize,
+    depth: usize,
+    bounds: Vec<(crate::Float, crate::Float)>,
+}
+
+impl MinMaxLevel {
+    fn get(&self, i: usize, j: usize) -> (crate::Float, crate::Float) {
+        self.bounds[j * self.width + i]
+    }
+}
+
+impl Heightfield {
+    /// * `heights` - `width * depth` samples in [0, 1], row major along x
+    /// * `min` - Corner of the terrain with the lowest coordinates
+    /// * `size` - Extent along x and z, and the height of a sample of 1
+    ///
+    /// Panics for fewer than 2x2 samples.
+    pub fn new(
+        heights: Vec<crate::Float>,
+        width: usize,
+        depth: usize,
+        min: Point3,
+        size: Vec3,
+        material: Arc<dyn Material>,
+    ) -> Self {
+        assert!(
+            width >= 2 && depth >= 2,
+            "heightfield needs at least 2x2 samples"
+        );
+        assert_eq!(heights.len(), width * depth);
+
+        let mut this = Self {
+            heights,
+            normals: vec![],
+            width,
+            depth,
+            min,
+            size,
+            levels: vec![],
+            material,
+        };
+        this.normals = (0..depth)
+            .flat_map(|j| (0..width).map(move |i| (i, j)))
+            .map(|(i, j)| this.sample_normal(i, j))
+            .collect();
+        this.build_levels();
+        this
+    }
+
+    /// Heights from the luminance of an image, the image x axis runs along x and y along z.
+    pub fn from_image(
+        p: impl AsRef<Path>,
+        min: Point3,
+        size: Vec3,
+        material: Arc<dyn Material>,
+    ) -> Self {
+        let (width, depth, texels) = texture::load_image(p.as_ref(), false);
+        let heights = texels.into_iter().map(color::luminance).collect();
+        Self::new(heights, width, depth, min, size, material)
+    }
+
+    /// Heights from turbulence, rescaled to fill the whole [0, 1] range.
+    ///
+    /// * `resolution` - Samples along x and z, at least 2
+    /// * `frequency` - Noise features per unit of terrain size
+    pub fn from_noise(
+        noise: &Perlin,
+        resolution: usize,
+        frequency: crate::Float,
+        min: Point3,
+        size: Vec3,
+        material: Arc<dyn Material>,
+    ) -> Self {
+        assert!(resolution >= 2, "heightfield needs at least 2x2 samples");
+        let step = 1.0 / (resolution - 1) as crate::Float;
+        let mut heights: Vec<crate::Float> = (0..resolution)
+            .flat_map(|j| (0..resolution).map(move |i| (i, j)))
+            .map(|(i, j)| {
+                let p = Point3::new(i as crate::Float * step, 0.0, j as crate::Float * step);
+                noise.turb(frequency * p, 7)
+            })
+            .collect();
+
+        let lowest = heights
+            .iter()
+            .copied()
+            .fold(crate::Float::INFINITY, crate::Float::min);
+        let highest = heights
+            .iter()
+            .copied()
+            .fold(crate::Float::NEG_INFINITY, crate::Float::max);
+        let range = (highest - lowest).max(crate::Float::EPSILON);
+        for h in heights.iter_mut() {
+            *h = (*h - lowest) / range;
+        }
+
+        Self::new(heights, resolution, resolution, min, size, material)
+    }
+
+    fn height(&self, i: usize, j: usize) -> crate::Float {
+        self.heights[j * self.width + i]
+    }
+
+    fn cell_size(&self) -> (crate::Float, crate::Float) {
+        (
+            self.size.x / (self.width - 1) as crate::Float,
+            self.size.z / (self.depth - 1) as crate::Float,
+        )
+    }
+
+    fn vertex(&self, i: usize, j: usize) -> Point3 {
+        let (dx, dz) = self.cell_size();
+        self.min
+            + Vec3::new(
+                i as crate::Float * dx,
+                self.height(i, j) * self.size.y,
+                j as crate::Float * dz,
+            )
+    }
+
+    /// Central differences, one sided at the borders.
+    fn sample_normal(&self, i: usize, j: usize) -> Vec3 {
+        let (dx, dz) = self.cell_size();
+        let (i0, i1) = (i.saturating_sub(1), (i + 1).min(self.width - 1));
+        let (j0, j1) = (j.saturating_sub(1), (j + 1).min(self.depth - 1));
+        let dh_dx = (self.height(i1, j) - self.height(i0, j)) * self.size.y
+            / ((i1 - i0) as crate::Float * dx);
+        let dh_dz = (self.height(i, j1) - self.height(i, j0)) * self.size.y
+            / ((j1 - j0) as crate::Float * dz);
+        Vec3::new(-dh_dx, 1.0, -dh_dz).normalize()
+    }
+
+    fn build_levels(&mut self) {
+        let mut bounds = Vec::with_capacity((self.width - 1) * (self.depth - 1));
+        for j in 0..self.depth - 1 {
+            for i in 0..self.width - 1 {
+                let corners = [
+                    self.height(i, j),
+                    self.height(i + 1, j),
+                    self.height(i, j + 1),
+                    self.height(i + 1, j + 1),
+                ];
+                let low = corners
+                    .iter()
+                    .copied()
+                    .fold(crate::Float::INFINITY, crate::Float::min);
+                let high = corners
+                    .iter()
+                    .copied()
+                    .fold(crate::Float::NEG_INFINITY, crate::Float::max);
+                bounds.push((low, high));
+            }
+        }
+        self.levels.push(MinMaxLevel {
+            width: self.width - 1,
+            depth: self.depth - 1,
+            bounds,
+        });
+
+        loop {
+            let below = self.levels.last().unwrap();
+            if below.width == 1 && below.depth == 1 {
+                break;
+            }
+            let width = (below.width + 1) / 2;
+            let depth = (below.depth + 1) / 2;
+            let mut bounds = Vec::with_capacity(width * depth);
+            for j in 0..depth {
+                for i in 0..width {
+                    let mut low = crate::Float::INFINITY;
+                    let mut high = crate::Float::NEG_INFINITY;
+                    for (ci, cj) in [
+                        (2 * i, 2 * j),
+                        (2 * i + 1, 2 * j),
+                        (2 * i, 2 * j + 1),
+                        (2 * i + 1, 2 * j + 1),
+                    ] {
+                        if ci < below.width && cj < below.depth {
+                            let (l, h) = below.get(ci, cj);
+                            low = low.min(l);
+                            high = high.max(h);
+                        }
+                    }
+                    bounds.push((low, high));
+                }
+            }
+            self.levels.push(MinMaxLevel {
+                width,
+                depth,
+                bounds,
+            });
+        }
+    }
+
+    fn block_box(&self, level: usize, i: usize, j: usize) -> Aabb {
+        let (dx, dz) = self.cell_size();
+        let cells = 1 << level;
+        let i1 = ((i + 1) * cells).min(self.width - 1);
+        let j1 = ((j + 1) * cells).min(self.depth - 1);
+        let (low, high) = self.levels[level].get(i, j);
+        Aabb::new(
+            self.min
+                + Vec3::new(
+                    (i * cells) as crate::Float * dx,
+                    low * self.size.y - AABB_EPSILON,
+                    (j * cells) as crate::Float * dz,
+                ),
+            self.min
+                + Vec3::new(
+                    i1 as crate::Float * dx,
+                    high * self.size.y + AABB_EPSILON,
+                    j1 as crate::Float * dz,
+                ),
+        )
+    }
+
+    fn traverse(
+        &self,
+        level: usize,
+        i: usize,
+        j: usize,
+        r: &Ray,
+        t_min: crate::Float,
+        t_max: crate::Float,
+    ) -> Option<HitRecord> {
+        if !self.block_box(level, i, j).hit(r, t_min, t_max) {
+            return None;
+        }
+        if level == 0 {
+            return self.hit_cell(i, j, r, t_min, t_max);
+        }
+
+        let below = &self.levels[level - 1];
+        // visit the children nearer to the ray origin first
+        let order_i = if r.direction().x < 0.0 {
+            [1, 0]
+        } else {
+            [0, 1]
+        };
+        let order_j = if r.direction().z < 0.0 {
+            [1, 0]
+        } else {
+            [0, 1]
+        };
+
+        let mut closest_so_far = t_max;
+        let mut result = None;
+        for dj in order_j {
+            for di in order_i {
+                let (ci, cj) = (2 * i + di, 2 * j + dj);
+                if ci >= below.width || cj >= below.depth {
+                    continue;
+                }
+                if let Some(rec) = self.traverse(level - 1, ci, cj, r, t_min, closest_so_far) {
+                    closest_so_far = rec.t;
+                    result = Some(rec);
+                }
+            }
+        }
+        result
+    }
+
+    fn hit_cell(
+        &self,
+        i: usize,
+        j: usize,
+        r: &Ray,
+        t_min: crate::Float,
+        t_max: crate::Float,
+    ) -> Option<HitRecord> {
+        let triangles = [
+            [(i, j), (i + 1, j + 1), (i + 1, j)],
+            [(i, j), (i, j + 1), (i + 1, j + 1)],
+        ];
+
+        let mut closest_so_far = t_max;
+        let mut result = None;
+        for corners in triangles {
+            if let Some(rec) = self.hit_triangle(corners, r, t_min, closest_so_far) {
+                closest_so_far = rec.t;
+                result = Some(rec);
+            }
+        }
+        result
+    }
+
+    /// Möller-Trumbore, corners wound so the geometric normal points up.
+    fn hit_triangle(
+        &self,
+        corners: [(usize, usize); 3],
+        r: &Ray,
+        t_min: crate::Float,
+        t_max: crate::Float,
+    ) -> Option<HitRecord> {
+        let [a, b, c] = corners.map(|(i, j)| self.vertex(i, j));
+        let edge_1 = b - a;
+        let edge_2 = c - a;
+        let p_vec = r.direction().cross(edge_2);
+        let determinant = edge_1.dot(p_vec);
+        if determinant.abs() < TRIANGLE_EPSILON {
+            return None;
+        }
+        let inv_determinant = 1.0 / determinant;
+        let t_vec = r.origin() - a;
+        let beta = t_vec.dot(p_vec) * inv_determinant;
+        if !(0.0..=1.0).contains(&beta) {
+            return None;
+        }
+        let q_vec = t_vec.cross(edge_1);
+        let gamma = r.direction().dot(q_vec) * inv_determinant;
+        if gamma < 0.0 || beta + gamma > 1.0 {
+            return None;
+        }
+        let t = edge_2.dot(q_vec) * inv_determinant;
+        if t < t_min || t_max < t {
+            return None;
+        }
+
+        let [na, nb, nc] = corners.map(|(i, j)| self.normals[j * self.width + i]);
+        let shading_normal = ((1.0 - beta - gamma) * na + beta * nb + gamma * nc).normalize();
+        let geometric_normal = edge_1.cross(edge_2);
+
+        let p = r.at(t);
+        let mut rec = HitRecord::new(&p, &shading_normal, t, &self.material);
+        rec.front_face = r.direction().dot(geometric_normal) < 0.0;
+        if !rec.front_face {
+            rec.normal = -shading_normal;
+        }
+        rec.u = (p.x - self.min.x) / self.size.x;
+        rec.v = (p.z - self.min.z) / self.size.z;
+        // along the triangle plane, the height follows the slope
+        rec.dpdu = Vec3::new(1.0, -geometric_normal.x / geometric_normal.y, 0.0) * self.size.x;
+        rec.dpdv = Vec3::new(0.0, -geometric_normal.z / geometric_normal.y, 1.0) * self.size.z;
+        Some(rec)
+    }
+}
+
+impl Hittable for Heightfield {
+    fn hit(&self, r: &Ray, t_min: crate::Float, t_max: crate::Float) -> Option<HitRecord> {
+        self.traverse(self.levels.len() - 1, 0, 0, r, t_min, t_max)
+    }
+
+    fn bounding_box(&self, _time_0: crate::Float, _time_1: crate::Float) -> Option<Aabb> {
+        Some(self.block_box(self.levels.len() - 1, 0, 0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material::Lambertian;
+    use crate::utils;
+    use crate::Color;
+
+    const TOLERANCE: crate::Float = 1e3 * crate::Float::EPSILON;
+
+    fn material() -> Arc<dyn Material> {
+        Arc::new(Lambertian::new_with_color(Color::splat(0.5)))
+    }
+
+    fn terrain() -> Heightfield {
+        Heightfield::from_noise(
+            &Perlin::default(),
+            33,
+            4.0,
+            Point3::new(-1.0, 0.0, -1.0),
+            Vec3::new(2.0, 0.5, 2.0),
+            material(),
+        )
+    }
+
+    /// Closest hit testing every cell.
+    fn brute_force(heightfield: &Heightfield, r: &Ray) -> Option<HitRecord> {
+        let mut closest_so_far = crate::Float::INFINITY;
+        let mut result = None;
+        for j in 0..heightfield.depth - 1 {
+            for i in 0..heightfield.width - 1 {
+                if let Some(rec) = heightfield.hit_cell(i, j, r, 0.001, closest_so_far) {
+                    closest_so_far = rec.t;
+                    result = Some(rec);
+                }
+            }
+        }
+        result
+    }
+
+    fn assert_same_hit(heightfield: &Heightfield, r: &Ray) {
+        let hit = heightfield.hit(r, 0.001, crate::Float::INFINITY);
+        let expected = brute_force(heightfield, r);
+        match (hit, expected) {
+            (Some(hit), Some(expected)) => assert!(
+                (hit.t - expected.t).abs() < TOLERANCE * expected.t,
+                "{} != {}",
+                hit.t,
+                expected.t
+            ),
+            (None, None) => {}
+            (hit, expected) => panic!(
+                "ray from {} along {}: hit {:?}, expected {:?}",
+                r.origin(),
+                r.direction(),
+                hit.map(|rec| rec.t),
+                expected.map(|rec| rec.t)
+            ),
+        }
+    }
+
+    #[test]
+    fn hit_and_miss() {
+        // a ramp rising along x, half as high as it is long
+        let heights = [0.0, 0.5, 1.0].repeat(3);
+        let ramp = Heightfield::new(
+            heights,
+            3,
+            3,
+            Point3::ZERO,
+            Vec3::new(2.0, 1.0, 2.0),
+            material(),
+        );
+        let down = -Vec3::Y;
+
+        let rec = ramp
+            .hit(&Ray::new(Point3::new(1.5, 5.0, 0.3), down), 0.001, 100.0)
+            .unwrap();
+        assert!((rec.p.y - 0.75).abs() < TOLERANCE);
+        assert!((rec.normal - Vec3::new(-0.5, 1.0, 0.0).normalize()).length() < TOLERANCE);
+        assert!(rec.front_face);
+
+        // from below
+        let rec = ramp
+            .hit(&Ray::new(Point3::new(0.5, -1.0, 1.7), -down), 0.001, 100.0)
+            .unwrap();
+        assert!((rec.p.y - 0.25).abs() < TOLERANCE);
+        assert!(!rec.front_face);
+
+        // beside it, over it and short of it
+        assert!(ramp
+            .hit(&Ray::new(Point3::new(2.5, 5.0, 1.0), down), 0.001, 100.0)
+            .is_none());
+        assert!(ramp
+            .hit(
+                &Ray::new(Point3::new(-1.0, 1.5, 1.0), Vec3::X),
+                0.001,
+                100.0
+            )
+            .is_none());
+        assert!(ramp
+            .hit(&Ray::new(Point3::new(1.5, 5.0, 0.3), down), 0.001, 4.0)
+            .is_none());
+    }
+
+    #[test]
+    fn matches_brute_force() {
+        let terrain = terrain();
+        for _ in 0..2000 {
+            let origin = Point3::new(0.0, 0.25, 0.0) + 3.0 * utils::rand_vec3_unit();
+            let target = Point3::new(
+                utils::gen_range(-1.2..1.2),
+                utils::gen_range(0.0..0.5),
+                utils::gen_range(-1.2..1.2),
+            );
+            assert_same_hit(&terrain, &Ray::new(origin, target - origin));
+        }
+    }
+
+    #[test]
+    fn grazing() {
+        // nearly level rays skimming the terrain, crossing many blocks of
+        // the quadtree close to their bounds
+        let terrain = terrain();
+        for _ in 0..2000 {
+            let origin = Point3::new(
+                -1.5,
+                utils::gen_range(0.0..0.5),
+                utils::gen_range(-1.0..1.0),
+            );
+            let direction = Vec3::new(
+                1.0,
+                utils::gen_range(-1e-3..1e-3),
+                utils::gen_range(-0.5..0.5),
+            );
+            assert_same_hit(&terrain, &Ray::new(origin, direction));
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "at least 2x2 samples")]
+    fn single_sample() {
+        Heightfield::from_noise(
+            &Perlin::default(),
+            1,
+            4.0,
+            Point3::ZERO,
+            Vec3::ONE,
+            material(),
+        );
+    }
+}
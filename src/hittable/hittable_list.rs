@@ -2,6 +2,10 @@ use std::sync::Arc;
 
 use super::{Aabb, BVHNode};
 use crate::hittable::{HitRecord, Hittable};
+use crate::light::DirectionCone;
+use crate::types::Point3;
+use crate::utils;
+use crate::Vec3;
 
 pub struct HittableList {
     objects: Vec<Arc<dyn Hittable>>,
@@ -59,4 +63,34 @@ impl Hittable for HittableList {
         }
         Some(bounding_box)
     }
+
+    /// Objects are picked uniformly, e.g. the triangles of a mesh.
+    fn pdf_value(&self, origin: Point3, direction: Vec3) -> crate::Float {
+        if self.objects.is_empty() {
+            return 0.0;
+        }
+        let sum: crate::Float = self
+            .objects
+            .iter()
+            .map(|object| object.pdf_value(origin, direction))
+            .sum();
+        sum / self.objects.len() as crate::Float
+    }
+
+    fn random(&self, origin: Point3) -> Vec3 {
+        if self.objects.is_empty() {
+            return Vec3::X;
+        }
+        self.objects[utils::gen_range(0..self.objects.len())].random(origin)
+    }
+
+    fn normal_bounds(&self) -> DirectionCone {
+        let mut objects = self.objects.iter();
+        match objects.next() {
+            Some(first) => objects.fold(first.normal_bounds(), |normals, object| {
+                normals.union(&object.normal_bounds())
+            }),
+            None => DirectionCone::entire_sphere(),
+        }
+    }
 }
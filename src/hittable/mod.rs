@@ -1,24 +1,42 @@
 mod aabb;
 mod aarect;
+mod alpha_mask;
 mod r#box;
 mod bvh;
 mod constant_medium;
+mod csg;
+mod heightfield;
 mod hittable_list;
 mod moving_sphere;
+mod plane;
+mod quad;
+mod sdf_shape;
 mod sphere;
+mod torus;
+mod triangle;
 
 pub use aabb::Aabb;
 pub use aarect::{XYRect, XZRect, YZRect};
+pub use alpha_mask::{AlphaMask, AlphaMode};
 pub use bvh::BVHNode;
 pub use constant_medium::ConstantMedium;
+pub use csg::{Csg, CsgOperation};
+pub use heightfield::Heightfield;
 pub use hittable_list::HittableList;
 pub use moving_sphere::MovingSphere;
+pub use plane::Plane;
+pub use quad::Quad;
 pub use r#box::Box;
+pub use sdf_shape::SdfShape;
 pub use sphere::Sphere;
+pub use torus::Torus;
+pub use triangle::Triangle;
 
 use std::sync::Arc;
 
+use crate::light::DirectionCone;
 use crate::material::Material;
+use crate::onb::Onb;
 use crate::Point3;
 use crate::Ray;
 use crate::Vec3;
@@ -31,7 +49,16 @@ pub struct HitRecord {
     pub t: crate::Float,
     pub u: crate::Float,
     pub v: crate::Float,
+    /// Derivatives of the position along u and v, the surface tangents used
+    /// by normal and bump maps. Zero where there is no parameterization.
+    pub dpdu: Vec3,
+    pub dpdv: Vec3,
     pub front_face: bool,
+    /// `light_id` of the area light the surface belongs to
+    pub light: Option<usize>,
+    /// Width along u and v of the area a camera pixel covers, set by
+    /// `set_footprint`, for filtering textures
+    pub uv_footprint: (crate::Float, crate::Float),
 }
 
 impl HitRecord {
@@ -44,6 +71,10 @@ impl HitRecord {
             material: material.clone(),
             u: 0.0,
             v: 0.0,
+            dpdu: Vec3::ZERO,
+            dpdv: Vec3::ZERO,
+            light: None,
+            uv_footprint: (0.0, 0.0),
         }
     }
     pub fn set_face_normal(&mut self, r: &Ray, outward_normal: Vec3) {
@@ -54,11 +85,139 @@ impl HitRecord {
             -outward_normal
         };
     }
+
+    /// Finds `uv_footprint` from the cone of `r`: the circle it covers at
+    /// the hit, stretched along the surface at grazing angles, mapped to
+    /// texture space through `dpdu` and `dpdv`.
+    pub fn set_footprint(&mut self, r: &Ray) {
+        let width = r.width_at(self.t);
+        let direction = r.direction().normalize();
+        let cos_theta = direction.dot(self.normal).abs().max(FOOTPRINT_MIN_COSINE);
+        let along = match (direction - direction.dot(self.normal) * self.normal).try_normalize() {
+            Some(along) => along,
+            None => Onb::new_from_w(self.normal).u,
+        };
+        let across = self.normal.cross(along);
+
+        // least squares of dpdu * du + dpdv * dv = axis
+        let a00 = self.dpdu.length_squared();
+        let a01 = self.dpdu.dot(self.dpdv);
+        let a11 = self.dpdv.length_squared();
+        let determinant = a00 * a11 - a01 * a01;
+        if width == 0.0 || determinant.abs() < crate::Float::EPSILON {
+            self.uv_footprint = (0.0, 0.0);
+            return;
+        }
+        let solve = |axis: Vec3| {
+            let (b0, b1) = (self.dpdu.dot(axis), self.dpdv.dot(axis));
+            (
+                (a11 * b0 - a01 * b1) / determinant,
+                (a00 * b1 - a01 * b0) / determinant,
+            )
+        };
+        let (du_0, dv_0) = solve(along * width / cos_theta);
+        let (du_1, dv_1) = solve(across * width);
+        self.uv_footprint = (du_0.abs().max(du_1.abs()), dv_0.abs().max(dv_1.abs()));
+    }
 }
 
+/// Grazing angles stretch the footprint at most this many times.
+const FOOTPRINT_MIN_COSINE: crate::Float = 0.01;
+
+/// Gap left between consecutive crossings in `Hittable::hit_all`.
+const HIT_ALL_EPSILON: crate::Float = 0.0001;
+
 pub trait Hittable: Sync + Send {
     fn hit(&self, r: &Ray, t_min: crate::Float, t_max: crate::Float) -> Option<HitRecord>;
+
+    /// Every surface crossing in `[t_min, t_max]`, ordered by `t`.
+    fn hit_all(&self, r: &Ray, t_min: crate::Float, t_max: crate::Float) -> Vec<HitRecord> {
+        let mut hits = vec![];
+        let mut t_min = t_min;
+        while let Some(rec) = self.hit(r, t_min, t_max) {
+            t_min = rec.t + HIT_ALL_EPSILON;
+            hits.push(rec);
+        }
+        hits
+    }
+
+    /// `None` for unbounded objects such as `Plane`.
     fn bounding_box(&self, time_0: crate::Float, time_1: crate::Float) -> Option<Aabb>;
+
+    /// Density over solid angle of `random` picking `direction` from
+    /// `origin`, zero for objects which can't be sampled.
+    fn pdf_value(&self, origin: Point3, direction: Vec3) -> crate::Float {
+        0.0
+    }
+
+    /// Direction from `origin` towards a random point of the object, for
+    /// sampling it as a light.
+    fn random(&self, origin: Point3) -> Vec3 {
+        Vec3::X
+    }
+
+    /// Directions the front faces of the object face, for area lights to
+    /// be bounded. Any direction unless the object is flat.
+    fn normal_bounds(&self) -> DirectionCone {
+        DirectionCone::entire_sphere()
+    }
+}
+
+/// Density over solid angle of sampling `direction` from `origin` on a flat
+/// object with points picked uniformly over its `area`.
+fn area_pdf(
+    object: &dyn Hittable,
+    area: crate::Float,
+    origin: Point3,
+    direction: Vec3,
+) -> crate::Float {
+    let r = Ray::new(origin, direction);
+    match object.hit(&r, 0.001, crate::Float::INFINITY) {
+        Some(rec) => {
+            let distance_squared = rec.t * rec.t * direction.length_squared();
+            let cosine = direction.dot(rec.normal).abs() / direction.length();
+            distance_squared / (cosine * area)
+        }
+        None => 0.0,
+    }
+}
+
+/// Swaps the inside and outside of a surface, e.g. to turn a one-sided
+/// light around.
+pub struct FlipFace {
+    hittable: Arc<dyn Hittable>,
+}
+
+impl FlipFace {
+    pub fn new(hittable: Arc<dyn Hittable>) -> Self {
+        Self { hittable }
+    }
+}
+
+impl Hittable for FlipFace {
+    fn hit(&self, r: &Ray, t_min: crate::Float, t_max: crate::Float) -> Option<HitRecord> {
+        self.hittable.hit(r, t_min, t_max).map(|mut rec| {
+            rec.front_face = !rec.front_face;
+            rec
+        })
+    }
+
+    fn bounding_box(&self, time_0: crate::Float, time_1: crate::Float) -> Option<Aabb> {
+        self.hittable.bounding_box(time_0, time_1)
+    }
+
+    fn pdf_value(&self, origin: Point3, direction: Vec3) -> crate::Float {
+        self.hittable.pdf_value(origin, direction)
+    }
+
+    fn random(&self, origin: Point3) -> Vec3 {
+        self.hittable.random(origin)
+    }
+
+    fn normal_bounds(&self) -> DirectionCone {
+        let normals = self.hittable.normal_bounds();
+        DirectionCone::new(-normals.w, normals.cos_theta)
+    }
 }
 
 pub struct Translate {
@@ -90,6 +249,18 @@ impl Hittable for Translate {
             .bounding_box(time_0, time_1)
             .map(|bb| Aabb::new(bb.min() + self.offset, bb.max() + self.offset))
     }
+
+    fn pdf_value(&self, origin: Point3, direction: Vec3) -> crate::Float {
+        self.hittable.pdf_value(origin - self.offset, direction)
+    }
+
+    fn random(&self, origin: Point3) -> Vec3 {
+        self.hittable.random(origin - self.offset)
+    }
+
+    fn normal_bounds(&self) -> DirectionCone {
+        self.hittable.normal_bounds()
+    }
 }
 
 pub struct RotateY {
@@ -142,28 +313,36 @@ impl RotateY {
     }
 }
 
+impl RotateY {
+    /// From world space to the space of the object.
+    fn to_object(&self, v: Vec3) -> Vec3 {
+        Vec3::new(
+            self.cos_theta * v.x - self.sin_theta * v.z,
+            v.y,
+            self.sin_theta * v.x + self.cos_theta * v.z,
+        )
+    }
+
+    fn to_world(&self, v: Vec3) -> Vec3 {
+        Vec3::new(
+            self.cos_theta * v.x + self.sin_theta * v.z,
+            v.y,
+            -self.sin_theta * v.x + self.cos_theta * v.z,
+        )
+    }
+}
+
 impl Hittable for RotateY {
     fn hit(&self, r: &Ray, t_min: crate::Float, t_max: crate::Float) -> Option<HitRecord> {
-        let origin = Point3::new(
-            self.cos_theta * r.origin().x - self.sin_theta * r.origin().z,
-            r.origin().y,
-            self.sin_theta * r.origin().x + self.cos_theta * r.origin().z,
-        );
-        let direction = Vec3::new(
-            self.cos_theta * r.direction().x - self.sin_theta * r.direction().z,
-            r.direction().y,
-            self.sin_theta * r.direction().x + self.cos_theta * r.direction().z,
-        );
+        let origin = self.to_object(r.origin());
+        let direction = self.to_object(r.direction());
         let rotated_r = Ray::new_with_time(origin, direction, r.time());
         self.hittable.hit(&rotated_r, t_min, t_max).map(|mut rec| {
-            let mut p = rec.p;
-            let mut normal = rec.normal;
-            p.x = self.cos_theta * rec.p.x + self.sin_theta * rec.p.z;
-            p.z = -self.sin_theta * rec.p.x + self.cos_theta * rec.p.z;
-            normal.x = self.cos_theta * rec.normal.x + self.sin_theta * rec.normal.z;
-            normal.z = -self.sin_theta * rec.normal.x + self.cos_theta * rec.normal.z;
-            rec.p = p;
-            rec.set_face_normal(&rotated_r, normal);
+            let rotate = |v: Vec3| self.to_world(v);
+            rec.p = rotate(rec.p);
+            rec.set_face_normal(&rotated_r, rotate(rec.normal));
+            rec.dpdu = rotate(rec.dpdu);
+            rec.dpdv = rotate(rec.dpdv);
 
             rec
         })
@@ -172,4 +351,18 @@ impl Hittable for RotateY {
     fn bounding_box(&self, _time_0: crate::Float, _time_1: crate::Float) -> Option<Aabb> {
         self.bounding_box.clone()
     }
+
+    fn pdf_value(&self, origin: Point3, direction: Vec3) -> crate::Float {
+        self.hittable
+            .pdf_value(self.to_object(origin), self.to_object(direction))
+    }
+
+    fn random(&self, origin: Point3) -> Vec3 {
+        self.to_world(self.hittable.random(self.to_object(origin)))
+    }
+
+    fn normal_bounds(&self) -> DirectionCone {
+        let normals = self.hittable.normal_bounds();
+        DirectionCone::new(self.to_world(normals.w), normals.cos_theta)
+    }
 }
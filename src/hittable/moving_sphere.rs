@@ -1,6 +1,6 @@
 use std::sync::Arc;
 
-use super::Aabb;
+use super::{Aabb, Sphere};
 use crate::hittable::HitRecord;
 use crate::material::Material;
 use crate::types::Point3;
@@ -47,6 +47,8 @@ impl Hittable for MovingSphere {
 
         let mut rec = HitRecord::new(&p, &outward_normal, t, &self.material);
         rec.set_face_normal(r, outward_normal);
+        (rec.u, rec.v) = Sphere::get_uv(outward_normal);
+        (rec.dpdu, rec.dpdv) = Sphere::get_tangents(outward_normal, self.radius);
 
         return Some(rec);
     }
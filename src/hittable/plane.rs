@@ -0,0 +1,122 @@
+use std::sync::Arc;
+
+use super::Aabb;
+use crate::hittable::{HitRecord, Hittable};
+use crate::material::Material;
+use crate::types::Point3;
+use crate::Vec3;
+
+const PARALLEL_EPSILON: crate::Float = 1e-8;
+
+/// Infinite plane, it has no bounding box.
+///
+/// u and v are coordinates in world units along two axes of the plane, for a
+/// horizontal plane they run along x and z.
+pub struct Plane {
+    point: Point3,
+    normal: Vec3,
+    u_axis: Vec3,
+    v_axis: Vec3,
+    material: Arc<dyn Material>,
+}
+
+impl Plane {
+    pub fn new(point: Point3, normal: Vec3, material: Arc<dyn Material>) -> Self {
+        let normal = normal.normalize();
+        let helper = if normal.x.abs() > 0.9 {
+            Vec3::new(0.0, 0.0, 1.0)
+        } else {
+            Vec3::new(1.0, 0.0, 0.0)
+        };
+        let u_axis = (helper - helper.dot(normal) * normal).normalize();
+        let v_axis = u_axis.cross(normal);
+        Self {
+            point,
+            normal,
+            u_axis,
+            v_axis,
+            material,
+        }
+    }
+}
+
+impl Hittable for Plane {
+    fn hit(
+        &self,
+        r: &crate::ray::Ray,
+        t_min: crate::Float,
+        t_max: crate::Float,
+    ) -> Option<HitRecord> {
+        let denominator = r.direction().dot(self.normal);
+        if denominator.abs() < PARALLEL_EPSILON {
+            return None;
+        }
+        let t = (self.point - r.origin()).dot(self.normal) / denominator;
+        if t < t_min || t_max < t {
+            return None;
+        }
+
+        let p = r.at(t);
+        let mut rec = HitRecord::new(&p, &self.normal, t, &self.material);
+        rec.set_face_normal(r, self.normal);
+        let local = p - self.point;
+        rec.u = local.dot(self.u_axis);
+        rec.v = local.dot(self.v_axis);
+        rec.dpdu = self.u_axis;
+        rec.dpdv = self.v_axis;
+        Some(rec)
+    }
+
+    fn bounding_box(&self, _time_0: crate::Float, _time_1: crate::Float) -> Option<Aabb> {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::Color;
+    use crate::material::Lambertian;
+    use crate::ray::Ray;
+
+    const TOLERANCE: crate::Float = 1e3 * crate::Float::EPSILON;
+
+    fn ground() -> Plane {
+        let material = Arc::new(Lambertian::new_with_color(Color::splat(0.5)));
+        Plane::new(
+            Point3::new(0.0, -1.0, 0.0),
+            Vec3::new(0.0, 2.0, 0.0),
+            material,
+        )
+    }
+
+    #[test]
+    fn hit_and_miss() {
+        let plane = ground();
+        assert!(plane.bounding_box(0.0, 1.0).is_none());
+
+        // from above, far from the point it was given
+        let r = Ray::new(Point3::new(100.0, 1.0, -50.0), Vec3::new(0.0, -1.0, 0.0));
+        let rec = plane.hit(&r, 0.001, crate::Float::INFINITY).unwrap();
+        assert!((rec.t - 2.0).abs() < TOLERANCE);
+        assert!((rec.p - Point3::new(100.0, -1.0, -50.0)).length() < TOLERANCE);
+        assert!(rec.front_face);
+        assert_eq!(rec.normal, Vec3::Y);
+        assert!((rec.u.abs() - 100.0).abs() < TOLERANCE && (rec.v.abs() - 50.0).abs() < TOLERANCE);
+        assert!(rec.dpdu.dot(Vec3::Y).abs() < TOLERANCE && rec.dpdv.dot(Vec3::Y).abs() < TOLERANCE);
+
+        // from below
+        let r = Ray::new(Point3::new(0.0, -3.0, 0.0), Vec3::new(0.3, 1.0, 0.0));
+        let rec = plane.hit(&r, 0.001, crate::Float::INFINITY).unwrap();
+        assert!(!rec.front_face);
+        assert_eq!(rec.normal, -Vec3::Y);
+
+        // away, parallel and beyond t_max
+        let r = Ray::new(Point3::new(0.0, 1.0, 0.0), Vec3::new(0.0, 1.0, 0.0));
+        assert!(plane.hit(&r, 0.001, crate::Float::INFINITY).is_none());
+        let r = Ray::new(Point3::new(0.0, 1.0, 0.0), Vec3::new(1.0, 0.0, 0.0));
+        assert!(plane.hit(&r, 0.001, crate::Float::INFINITY).is_none());
+        let r = Ray::new(Point3::new(0.0, 1.0, 0.0), Vec3::new(0.0, -1.0, 0.0));
+        assert!(plane.hit(&r, 0.001, 1.5).is_none());
+    }
+}
@@ -0,0 +1,100 @@
+use std::sync::Arc;
+
+use super::{area_pdf, Aabb};
+use crate::hittable::{HitRecord, Hittable};
+use crate::light::DirectionCone;
+use crate::material::Material;
+use crate::ray::Ray;
+use crate::types::Point3;
+use crate::utils;
+use crate::Vec3;
+
+const PARALLEL_EPSILON: crate::Float = 1e-8;
+const AABB_EPSILON: crate::Float = 0.0001;
+
+/// Parallelogram of any orientation, from a corner and its two edges. The
+/// front face is the one `u` cross `v` points out of.
+pub struct Quad {
+    corner: Point3,
+    u: Vec3,
+    v: Vec3,
+    /// `u` cross `v` over its squared length, to find the coordinates of
+    /// points along the edges
+    w: Vec3,
+    normal: Vec3,
+    area: crate::Float,
+    material: Arc<dyn Material>,
+}
+
+impl Quad {
+    /// * `corner` - Where u and v are 0
+    /// * `u`, `v` - Edges from the corner, the texture coordinates run along them
+    pub fn new(corner: Point3, u: Vec3, v: Vec3, material: Arc<dyn Material>) -> Self {
+        let n = u.cross(v);
+        Self {
+            corner,
+            u,
+            v,
+            w: n / n.length_squared(),
+            normal: n.normalize(),
+            area: n.length(),
+            material,
+        }
+    }
+}
+
+impl Hittable for Quad {
+    fn hit(&self, r: &Ray, t_min: crate::Float, t_max: crate::Float) -> Option<HitRecord> {
+        let denominator = self.normal.dot(r.direction());
+        if denominator.abs() < PARALLEL_EPSILON {
+            return None;
+        }
+        let t = self.normal.dot(self.corner - r.origin()) / denominator;
+        if t < t_min || t_max < t {
+            return None;
+        }
+
+        let p = r.at(t);
+        let planar = p - self.corner;
+        let alpha = self.w.dot(planar.cross(self.v));
+        let beta = self.w.dot(self.u.cross(planar));
+        if !(0.0..=1.0).contains(&alpha) || !(0.0..=1.0).contains(&beta) {
+            return None;
+        }
+
+        let mut rec = HitRecord::new(&p, &self.normal, t, &self.material);
+        rec.set_face_normal(r, self.normal);
+        rec.u = alpha;
+        rec.v = beta;
+        rec.dpdu = self.u;
+        rec.dpdv = self.v;
+        Some(rec)
+    }
+
+    fn bounding_box(&self, _time_0: crate::Float, _time_1: crate::Float) -> Option<Aabb> {
+        let corners = [
+            self.corner,
+            self.corner + self.u,
+            self.corner + self.v,
+            self.corner + self.u + self.v,
+        ];
+        let min = corners.iter().fold(corners[0], |a, b| a.min(*b));
+        let max = corners.iter().fold(corners[0], |a, b| a.max(*b));
+        Some(Aabb::new(
+            min - Vec3::splat(AABB_EPSILON),
+            max + Vec3::splat(AABB_EPSILON),
+        ))
+    }
+
+    fn pdf_value(&self, origin: Point3, direction: Vec3) -> crate::Float {
+        area_pdf(self, self.area, origin, direction)
+    }
+
+    fn random(&self, origin: Point3) -> Vec3 {
+        self.corner + utils::gen_float() * self.u + utils::gen_float() * self.v - origin
+    }
+
+    fn normal_bounds(&self) -> DirectionCone {
+        DirectionCone::new(self.normal, 1.0)
+    }
+}
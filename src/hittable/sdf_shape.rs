@@ -0,0 +1,158 @@
+use std::sync::Arc;
+
+use super::{Aabb, Sphere};
+use crate::hittable::{HitRecord, Hittable};
+use crate::material::Material;
+use crate::sdf::Sdf;
+use crate::types::{Point3, PI};
+use crate::Vec3;
+
+const MAX_STEPS: usize = 512;
+const BISECTION_STEPS: usize = 16;
+/// Surface tolerance relative to the bounding box diagonal.
+const RELATIVE_EPSILON: crate::Float = 1e-5;
+
+/// Sphere traced signed distance field, clipped to a user supplied bounding box.
+pub struct SdfShape {
+    sdf: Arc<dyn Sdf>,
+    bounding_box: Aabb,
+    material: Arc<dyn Material>,
+    step_scale: crate::Float,
+    epsilon: crate::Float,
+}
+
+impl SdfShape {
+    pub fn new(sdf: Arc<dyn Sdf>, bounding_box: Aabb, material: Arc<dyn Material>) -> Self {
+        Self::new_with_step_scale(sdf, bounding_box, material, 1.0)
+    }
+
+    /// * `step_scale` - Fraction of the distance bound advanced each step, lower
+    ///   it for fields that are not exact distances (twist, displacement)
+    pub fn new_with_step_scale(
+        sdf: Arc<dyn Sdf>,
+        bounding_box: Aabb,
+        material: Arc<dyn Material>,
+        step_scale: crate::Float,
+    ) -> Self {
+        let epsilon = (bounding_box.max() - bounding_box.min()).length() * RELATIVE_EPSILON;
+        Self {
+            sdf,
+            bounding_box,
+            material,
+            step_scale,
+            epsilon,
+        }
+    }
+
+    /// Central differences, one epsilon apart.
+    fn gradient(&self, p: Point3) -> Vec3 {
+        let mut gradient = Vec3::default();
+        for axis in 0..3 {
+            let mut offset = Vec3::default();
+            offset[axis] = self.epsilon;
+            gradient[axis] = self.sdf.distance(p + offset) - self.sdf.distance(p - offset);
+        }
+        gradient
+    }
+
+    fn hit_record(&self, r: &crate::ray::Ray, t: crate::Float) -> HitRecord {
+        let p = r.at(t);
+        let outward_normal = self.gradient(p).normalize_or_zero();
+        let mut rec = HitRecord::new(&p, &outward_normal, t, &self.material);
+        rec.set_face_normal(r, outward_normal);
+        (rec.u, rec.v) = self.get_uv(p);
+        (rec.dpdu, rec.dpdv) = self.get_tangents(p, outward_normal);
+        rec
+    }
+
+    /// Spherical mapping around the bounding box center.
+    fn get_uv(&self, p: Point3) -> (crate::Float, crate::Float) {
+        let center = 0.5 * (self.bounding_box.min() + self.bounding_box.max());
+        let d = (p - center).normalize_or_zero();
+        let theta = (-d.y).clamp(-1.0, 1.0).acos();
+        let phi = (-d.z).atan2(d.x) + PI;
+
+        (phi / (2.0 * PI), theta / PI)
+    }
+
+    /// Tangents of the spherical mapping, projected onto the surface.
+    fn get_tangents(&self, p: Point3, normal: Vec3) -> (Vec3, Vec3) {
+        let center = 0.5 * (self.bounding_box.min() + self.bounding_box.max());
+        let offset = p - center;
+        let (dpdu, dpdv) = Sphere::get_tangents(offset.normalize_or_zero(), offset.length());
+        (
+            dpdu - dpdu.dot(normal) * normal,
+            dpdv - dpdv.dot(normal) * normal,
+        )
+    }
+}
+
+impl Hittable for SdfShape {
+    fn hit(
+        &self,
+        r: &crate::ray::Ray,
+        t_min: crate::Float,
+        t_max: crate::Float,
+    ) -> Option<HitRecord> {
+        let (t_start, t_end) = self.bounding_box.hit_interval(r, t_min, t_max)?;
+        let inv_length = 1.0 / r.direction().length();
+
+        let mut t = t_start;
+        let mut distance = self.sdf.distance(r.at(t));
+        if distance.abs() < self.epsilon
+            && t_start > t_min
+            && self.gradient(r.at(t)).dot(r.direction()) < 0.0
+        {
+            // surface touching the bounding box, entered right away
+            return Some(self.hit_record(r, t));
+        }
+        // A ray leaving the surface starts within epsilon of it, step off
+        // before deciding which side it is on.
+        while distance.abs() < self.epsilon {
+            t += self.epsilon * inv_length;
+            if t > t_end {
+                return None;
+            }
+            distance = self.sdf.distance(r.at(t));
+        }
+        let inside = distance < 0.0;
+
+        let mut t_previous = t;
+        let mut steps = 0;
+        loop {
+            if (distance < 0.0) != inside {
+                // overshot the surface, refine between the last two samples
+                let (mut near, mut far) = (t_previous, t);
+                for _ in 0..BISECTION_STEPS {
+                    let mid = 0.5 * (near + far);
+                    if (self.sdf.distance(r.at(mid)) < 0.0) == inside {
+                        near = mid;
+                    } else {
+                        far = mid;
+                    }
+                }
+                t = far;
+                break;
+            }
+            if distance.abs() < self.epsilon {
+                break;
+            }
+            steps += 1;
+            if steps > MAX_STEPS {
+                return None;
+            }
+            t_previous = t;
+            t += (distance.abs() * self.step_scale).max(0.5 * self.epsilon) * inv_length;
+            if t > t_end {
+                return None;
+            }
+            distance = self.sdf.distance(r.at(t));
+        }
+
+        Some(self.hit_record(r, t))
+    }
+
+    fn bounding_box(&self, _time_0: crate::Float, _time_1: crate::Float) -> Option<Aabb> {
+        Some(self.bounding_box.clone())
+    }
+}
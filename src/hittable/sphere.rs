@@ -1,8 +1,11 @@
 use std::sync::Arc;
 
-use crate::hittable::HitRecord;
+use crate::hittable::{HitRecord, Hittable};
 use crate::material::Material;
+use crate::onb::Onb;
+use crate::ray::Ray;
 use crate::types::Point3;
+use crate::utils;
 use crate::Vec3;
 
 use crate::types::PI;
@@ -24,12 +27,21 @@ impl Sphere {
         }
     }
 
-    fn get_uv(p: Point3) -> (crate::Float, crate::Float) {
-        let theta = (-p.y).acos();
+    pub(super) fn get_uv(p: Point3) -> (crate::Float, crate::Float) {
+        let theta = (-p.y).clamp(-1.0, 1.0).acos();
         let phi = (-p.z).atan2(p.x) + PI;
 
         (phi / (2.0 * PI), theta / PI)
     }
+
+    /// dpdu and dpdv of the mapping in `get_uv`, for the unit direction `p`.
+    pub(super) fn get_tangents(p: Point3, radius: crate::Float) -> (Vec3, Vec3) {
+        let alpha = (-p.z).atan2(p.x);
+        let sin_theta = (1.0 - p.y * p.y).max(0.0).sqrt();
+        let dpdu = 2.0 * PI * radius * Vec3::new(p.z, 0.0, -p.x);
+        let dpdv = PI * radius * Vec3::new(-p.y * alpha.cos(), sin_theta, p.y * alpha.sin());
+        (dpdu, dpdv)
+    }
 }
 
 impl crate::hittable::Hittable for Sphere {
@@ -61,7 +73,8 @@ impl crate::hittable::Hittable for Sphere {
 
         let mut rec = HitRecord::new(&p, &outward_normal, t, &self.material);
         rec.set_face_normal(r, outward_normal);
-        (rec.u, rec.v) = Self::get_uv(rec.normal);
+        (rec.u, rec.v) = Self::get_uv(outward_normal);
+        (rec.dpdu, rec.dpdv) = Self::get_tangents(outward_normal, self.radius);
 
         return Some(rec);
     }
@@ -72,4 +85,35 @@ impl crate::hittable::Hittable for Sphere {
             self.center + Vec3::splat(self.radius),
         ))
     }
+
+    /// Uniform over the cone of directions the sphere covers from outside,
+    /// over all directions from inside.
+    fn pdf_value(&self, origin: Point3, direction: Vec3) -> crate::Float {
+        let r = Ray::new(origin, direction);
+        if self.hit(&r, 0.001, crate::Float::INFINITY).is_none() {
+            return 0.0;
+        }
+        let distance_squared = (self.center - origin).length_squared();
+        let radius_squared = self.radius * self.radius;
+        if distance_squared <= radius_squared {
+            return 1.0 / (4.0 * PI);
+        }
+        let cos_theta_max = (1.0 - radius_squared / distance_squared).sqrt();
+        1.0 / (2.0 * PI * (1.0 - cos_theta_max))
+    }
+
+    fn random(&self, origin: Point3) -> Vec3 {
+        let direction = self.center - origin;
+        let distance_squared = direction.length_squared();
+        let radius_squared = self.radius * self.radius;
+        if distance_squared <= radius_squared {
+            return utils::rand_vec3_unit();
+        }
+        let cos_theta_max = (1.0 - radius_squared / distance_squared).sqrt();
+        let z = 1.0 + utils::gen_float() * (cos_theta_max - 1.0);
+        let phi = 2.0 * PI * utils::gen_float();
+        let sin_theta = (1.0 - z * z).max(0.0).sqrt();
+        let uvw = Onb::new_from_w(direction / distance_squared.sqrt());
+        uvw.to_world(Vec3::new(phi.cos() * sin_theta, phi.sin() * sin_theta, z))
+    }
 }
@@ -0,0 +1,217 @@
+use std::sync::Arc;
+
+use super::Aabb;
+use crate::hittable::{HitRecord, Hittable};
+use crate::material::Material;
+use crate::polynomial;
+use crate::types::{Point3, PI};
+use crate::Vec3;
+
+/// Torus lying in the xz plane, with the y axis through its hole.
+pub struct Torus {
+    center: Point3,
+    major_radius: crate::Float,
+    minor_radius: crate::Float,
+    material: Arc<dyn Material>,
+}
+
+impl Torus {
+    /// * `major_radius` - Distance from the center to the middle of the tube
+    /// * `minor_radius` - Radius of the tube
+    pub fn new(
+        center: Point3,
+        major_radius: crate::Float,
+        minor_radius: crate::Float,
+        material: Arc<dyn Material>,
+    ) -> Self {
+        Self {
+            center,
+            major_radius,
+            minor_radius,
+            material,
+        }
+    }
+
+    /// p is relative to the torus center
+    fn get_uv(&self, p: Point3) -> (crate::Float, crate::Float) {
+        let phi = (-p.z).atan2(p.x) + PI;
+        let ring_distance = (p.x * p.x + p.z * p.z).sqrt() - self.major_radius;
+        let theta = p.y.atan2(ring_distance) + PI;
+
+        (phi / (2.0 * PI), theta / (2.0 * PI))
+    }
+
+    /// dpdu and dpdv of the mapping in `get_uv`, p is relative to the torus center
+    fn get_tangents(&self, p: Point3) -> (Vec3, Vec3) {
+        let ring_direction = Vec3::new(p.x, 0.0, p.z).normalize_or_zero();
+        let ring_distance = (p.x * p.x + p.z * p.z).sqrt() - self.major_radius;
+        let dpdu = 2.0 * PI * Vec3::new(p.z, 0.0, -p.x);
+        let dpdv = 2.0 * PI * (ring_distance * Vec3::new(0.0, 1.0, 0.0) - p.y * ring_direction);
+        (dpdu, dpdv)
+    }
+
+    /// p is relative to the torus center
+    fn outward_normal(&self, p: Point3) -> Vec3 {
+        let ring_direction = Vec3::new(p.x, 0.0, p.z).normalize_or_zero();
+        (p - self.major_radius * ring_direction).normalize()
+    }
+}
+
+impl Hittable for Torus {
+    fn hit(
+        &self,
+        r: &crate::ray::Ray,
+        t_min: crate::Float,
+        t_max: crate::Float,
+    ) -> Option<HitRecord> {
+        let direction_length = r.direction().length();
+        let d = r.direction() / direction_length;
+        let o = r.origin() - self.center;
+
+        // Move the ray origin onto the bounding sphere first, the quartic
+        // coefficients blow up with the distance to the torus otherwise.
+        let bound = self.major_radius + self.minor_radius;
+        let half_b = o.dot(d);
+        let discriminant = half_b * half_b - (o.length_squared() - bound * bound);
+        if discriminant < 0.0 {
+            return None;
+        }
+        let t_shift = (-half_b - discriminant.sqrt()).max(0.0);
+        if t_shift / direction_length > t_max {
+            return None;
+        }
+        let o = o + t_shift * d;
+
+        let major_sqrd = self.major_radius * self.major_radius;
+        let minor_sqrd = self.minor_radius * self.minor_radius;
+        let four_major_sqrd = 4.0 * major_sqrd;
+        let e = o.length_squared() - major_sqrd - minor_sqrd;
+        let f = o.dot(d);
+
+        let coefficients = [
+            1.0,
+            4.0 * f,
+            2.0 * e + 4.0 * f * f + four_major_sqrd * d.y * d.y,
+            4.0 * f * e + 2.0 * four_major_sqrd * o.y * d.y,
+            e * e - four_major_sqrd * (minor_sqrd - o.y * o.y),
+        ];
+
+        let t = polynomial::solve_quartic(coefficients)
+            .into_iter()
+            .map(|root| (root + t_shift) / direction_length)
+            .find(|t| t_min <= *t && *t <= t_max)?;
+
+        let p = r.at(t);
+        let local_p = p - self.center;
+        let outward_normal = self.outward_normal(local_p);
+
+        let mut rec = HitRecord::new(&p, &outward_normal, t, &self.material);
+        rec.set_face_normal(r, outward_normal);
+        (rec.u, rec.v) = self.get_uv(local_p);
+        (rec.dpdu, rec.dpdv) = self.get_tangents(local_p);
+
+        Some(rec)
+    }
+
+    fn bounding_box(&self, _time_0: crate::Float, _time_1: crate::Float) -> Option<Aabb> {
+        let extent = Vec3::new(
+            self.major_radius + self.minor_radius,
+            self.minor_radius,
+            self.major_radius + self.minor_radius,
+        );
+        Some(Aabb::new(self.center - extent, self.center + extent))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material::Lambertian;
+    use crate::ray::Ray;
+    use crate::Color;
+
+    const MAJOR: crate::Float = 1.0;
+    const MINOR: crate::Float = 0.25;
+    /// Error allowed in distances around the size of the torus.
+    const TOLERANCE: crate::Float = 1e4 * crate::Float::EPSILON;
+
+    fn torus() -> Torus {
+        let material = Arc::new(Lambertian::new_with_color(Color::splat(0.5)));
+        Torus::new(Point3::ZERO, MAJOR, MINOR, material)
+    }
+
+    /// Distance from the surface of the torus, negative inside.
+    fn surface_distance(p: Point3) -> crate::Float {
+        let ring_distance = (p.x * p.x + p.z * p.z).sqrt() - MAJOR;
+        (ring_distance * ring_distance + p.y * p.y).sqrt() - MINOR
+    }
+
+    fn hit(origin: Point3, direction: Vec3) -> Option<HitRecord> {
+        torus().hit(&Ray::new(origin, direction), 0.001, crate::Float::INFINITY)
+    }
+
+    #[test]
+    fn head_on() {
+        let rec = hit(Point3::new(-10.0, 0.0, 0.0), Vec3::X).unwrap();
+        assert!((rec.p.x + MAJOR + MINOR).abs() < TOLERANCE);
+        assert!((rec.normal - -Vec3::X).length() < TOLERANCE);
+        assert!(rec.front_face);
+    }
+
+    #[test]
+    fn through_the_hole() {
+        assert!(hit(Point3::new(0.0, -10.0, 0.0), Vec3::Y).is_none());
+    }
+
+    #[test]
+    fn grazing() {
+        // just under the top of the tube
+        for offset in [1e-2, 1e-3, 1e-4] {
+            let rec = hit(Point3::new(-10.0, MINOR - offset, 0.0), Vec3::X).unwrap();
+            assert!(
+                surface_distance(rec.p).abs() < TOLERANCE,
+                "offset {}",
+                offset
+            );
+            // half the chord through the tube
+            let chord = (2.0 * MINOR * offset).sqrt();
+            assert!((rec.p.x + MAJOR + chord).abs() < chord / 2.0);
+        }
+        // just over it
+        assert!(hit(Point3::new(-10.0, MINOR + 1e-4, 0.0), Vec3::X).is_none());
+    }
+
+    #[test]
+    fn tangent() {
+        // touching the outer equator
+        let rec = hit(Point3::new(-10.0, 0.0, MAJOR + MINOR), Vec3::X)
+            .expect("tangent to the outer equator");
+        assert!(surface_distance(rec.p).abs() < TOLERANCE);
+        assert!(rec.p.x.abs() < TOLERANCE.sqrt());
+        // touching the inner equator from inside the hole
+        let rec = hit(Point3::new(-10.0, 0.0, MAJOR - MINOR), Vec3::X)
+            .expect("tangent to the inner equator");
+        assert!(surface_distance(rec.p).abs() < TOLERANCE);
+    }
+
+    #[test]
+    fn far_away() {
+        for distance in [1e2, 1e4] {
+            let origin = Point3::new(-distance, 0.1, distance);
+            let target = Point3::new(-MAJOR, 0.1, 0.0);
+            let rec = hit(origin, target - origin).unwrap();
+            assert!(
+                surface_distance(rec.p).abs() < TOLERANCE * distance,
+                "distance {}",
+                distance
+            );
+        }
+    }
+
+    #[test]
+    fn from_inside_the_tube() {
+        let rec = hit(Point3::new(MAJOR, 0.0, 0.0), Vec3::X).unwrap();
+        assert!((rec.p.x - MAJOR - MINOR).abs() < TOLERANCE);
+        assert!(!rec.front_face);
+    }
+}
@@ -0,0 +1,130 @@
+use std::sync::Arc;
+
+use super::{area_pdf, Aabb};
+use crate::hittable::{HitRecord, Hittable};
+use crate::light::DirectionCone;
+use crate::material::Material;
+use crate::onb::Onb;
+use crate::ray::Ray;
+use crate::types::Point3;
+use crate::utils;
+use crate::Vec3;
+
+const TRIANGLE_EPSILON: crate::Float = 1e-9;
+const AABB_EPSILON: crate::Float = 0.0001;
+
+/// Single flat triangle, the front face is the one the corners wind
+/// counterclockwise around. Meshes are lists of them.
+pub struct Triangle {
+    vertices: [Point3; 3],
+    uvs: [(crate::Float, crate::Float); 3],
+    material: Arc<dyn Material>,
+}
+
+impl Triangle {
+    pub fn new(a: Point3, b: Point3, c: Point3, material: Arc<dyn Material>) -> Self {
+        Self {
+            vertices: [a, b, c],
+            uvs: [(0.0, 0.0), (1.0, 0.0), (0.0, 1.0)],
+            material,
+        }
+    }
+
+    /// Texture coordinates of the corners, by default (0, 0), (1, 0) and
+    /// (0, 1).
+    pub fn with_uvs(self, uvs: [(crate::Float, crate::Float); 3]) -> Self {
+        Self { uvs, ..self }
+    }
+
+    fn area(&self) -> crate::Float {
+        let [a, b, c] = self.vertices;
+        (b - a).cross(c - a).length() / 2.0
+    }
+
+    /// dpdu and dpdv from the texture coordinates of the corners.
+    fn tangents(&self, normal: Vec3) -> (Vec3, Vec3) {
+        let [p0, p1, p2] = self.vertices;
+        let [uv0, uv1, uv2] = self.uvs;
+        let (du02, dv02) = (uv0.0 - uv2.0, uv0.1 - uv2.1);
+        let (du12, dv12) = (uv1.0 - uv2.0, uv1.1 - uv2.1);
+        let (dp02, dp12) = (p0 - p2, p1 - p2);
+        let determinant = du02 * dv12 - dv02 * du12;
+        if determinant.abs() < TRIANGLE_EPSILON {
+            // no usable parameterization, any tangents will do
+            let frame = Onb::new_from_w(normal);
+            return (frame.u, frame.v);
+        }
+        let inv_determinant = 1.0 / determinant;
+        (
+            (dv12 * dp02 - dv02 * dp12) * inv_determinant,
+            (du02 * dp12 - du12 * dp02) * inv_determinant,
+        )
+    }
+}
+
+impl Hittable for Triangle {
+    /// Möller-Trumbore.
+    fn hit(&self, r: &Ray, t_min: crate::Float, t_max: crate::Float) -> Option<HitRecord> {
+        let [a, b, c] = self.vertices;
+        let edge_1 = b - a;
+        let edge_2 = c - a;
+        let p_vec = r.direction().cross(edge_2);
+        let determinant = edge_1.dot(p_vec);
+        if determinant.abs() < TRIANGLE_EPSILON {
+            return None;
+        }
+        let inv_determinant = 1.0 / determinant;
+        let t_vec = r.origin() - a;
+        let beta = t_vec.dot(p_vec) * inv_determinant;
+        if !(0.0..=1.0).contains(&beta) {
+            return None;
+        }
+        let q_vec = t_vec.cross(edge_1);
+        let gamma = r.direction().dot(q_vec) * inv_determinant;
+        if gamma < 0.0 || beta + gamma > 1.0 {
+            return None;
+        }
+        let t = edge_2.dot(q_vec) * inv_determinant;
+        if t < t_min || t_max < t {
+            return None;
+        }
+
+        let outward_normal = edge_1.cross(edge_2).normalize();
+        let alpha = 1.0 - beta - gamma;
+        let [uv0, uv1, uv2] = self.uvs;
+
+        let p = r.at(t);
+        let mut rec = HitRecord::new(&p, &outward_normal, t, &self.material);
+        rec.set_face_normal(r, outward_normal);
+        rec.u = alpha * uv0.0 + beta * uv1.0 + gamma * uv2.0;
+        rec.v = alpha * uv0.1 + beta * uv1.1 + gamma * uv2.1;
+        (rec.dpdu, rec.dpdv) = self.tangents(outward_normal);
+        Some(rec)
+    }
+
+    fn bounding_box(&self, _time_0: crate::Float, _time_1: crate::Float) -> Option<Aabb> {
+        let [a, b, c] = self.vertices;
+        Some(Aabb::new(
+            a.min(b).min(c) - Vec3::splat(AABB_EPSILON),
+            a.max(b).max(c) + Vec3::splat(AABB_EPSILON),
+        ))
+    }
+
+    fn pdf_value(&self, origin: Point3, direction: Vec3) -> crate::Float {
+        area_pdf(self, self.area(), origin, direction)
+    }
+
+    fn random(&self, origin: Point3) -> Vec3 {
+        let [a, b, c] = self.vertices;
+        // uniform over the area
+        let s = utils::gen_float().sqrt();
+        let beta = utils::gen_float() * s;
+        let gamma = 1.0 - s;
+        a + beta * (b - a) + gamma * (c - a) - origin
+    }
+
+    fn normal_bounds(&self) -> DirectionCone {
+        let [a, b, c] = self.vertices;
+        DirectionCone::new((b - a).cross(c - a), 1.0)
+    }
+}
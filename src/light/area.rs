@@ -0,0 +1,196 @@
+use std::sync::Arc;
+
+use crate::color;
+use crate::hittable::{Aabb, HitRecord, Hittable};
+use crate::ray::Ray;
+use crate::types::{Point3, PI};
+use crate::utils;
+use crate::Vec3;
+
+use super::{light_id, DirectionCone, Light, LightBounds, LightSample};
+
+/// Directions the power of a light is estimated from.
+const POWER_SAMPLES: usize = 256;
+
+/// An emissive object sampled as a light, it is also the object to add to
+/// the world so hits on it can be weighted against sampling it.
+///
+/// The emission is the one of the material of the object where a sampled
+/// direction hits it, textures and the options of `DiffuseLight` included.
+/// Where it shines is bounded by `Hittable::normal_bounds` and
+/// `Material::emission_spread`. The object must be bounded and support sampling through
+/// `Hittable::random` and `Hittable::pdf_value`, e.g. a `Sphere`, `Quad`,
+/// `Triangle`, rectangle or a list of them.
+pub struct AreaLight {
+    shape: Arc<dyn Hittable>,
+    bounds: Aabb,
+    /// In luminance
+    power: crate::Float,
+    normals: DirectionCone,
+    cos_theta_e: crate::Float,
+    two_sided: bool,
+}
+
+impl AreaLight {
+    /// Panics for objects without a bounding box.
+    pub fn new(shape: Arc<dyn Hittable>) -> Self {
+        let bounds = shape
+            .bounding_box(0.0, 1.0)
+            .expect("an area light must be bounded");
+        let (power, spread) = estimate_power(shape.as_ref(), &bounds);
+        let (two_sided, cos_theta_e) = spread.unwrap_or((true, 0.0));
+        Self {
+            normals: shape.normal_bounds(),
+            shape,
+            bounds,
+            power,
+            cos_theta_e,
+            two_sided,
+        }
+    }
+}
+
+/// Intensity of the light, seen from random directions around it at a few
+/// times its size, over the whole sphere of directions. With the widest
+/// `Material::emission_spread` of the surfaces seen.
+fn estimate_power(
+    shape: &dyn Hittable,
+    bounds: &Aabb,
+) -> (crate::Float, Option<(bool, crate::Float)>) {
+    let center = (bounds.min() + bounds.max()) / 2.0;
+    let radius = (bounds.max() - bounds.min()).length() / 2.0;
+    let mut sum = 0.0;
+    let mut spread: Option<(bool, crate::Float)> = None;
+    for _ in 0..POWER_SAMPLES {
+        let origin = center + 4.0 * radius * utils::rand_vec3_unit();
+        let direction = shape.random(origin);
+        let pdf = shape.pdf_value(origin, direction);
+        if pdf <= 0.0 {
+            continue;
+        }
+        let r = Ray::new(origin, direction);
+        if let Some(rec) = shape.hit(&r, 0.001, crate::Float::INFINITY) {
+            let radiance = color::luminance(rec.material.emitted(&r, &rec));
+            sum += radiance / pdf * (origin - center).length_squared();
+
+            let (two_sided, cos_theta_e) = rec.material.emission_spread();
+            spread = Some(match spread {
+                Some((a, b)) => (a || two_sided, b.min(cos_theta_e)),
+                None => (two_sided, cos_theta_e),
+            });
+        }
+    }
+    (4.0 * PI * sum / POWER_SAMPLES as crate::Float, spread)
+}
+
+impl Hittable for AreaLight {
+    fn hit(&self, r: &Ray, t_min: crate::Float, t_max: crate::Float) -> Option<HitRecord> {
+        self.shape.hit(r, t_min, t_max).map(|mut rec| {
+            rec.light = Some(light_id(self));
+            rec
+        })
+    }
+
+    fn bounding_box(&self, time_0: crate::Float, time_1: crate::Float) -> Option<Aabb> {
+        self.shape.bounding_box(time_0, time_1)
+    }
+
+    fn pdf_value(&self, origin: Point3, direction: Vec3) -> crate::Float {
+        self.shape.pdf_value(origin, direction)
+    }
+
+    fn random(&self, origin: Point3) -> Vec3 {
+        self.shape.random(origin)
+    }
+}
+
+impl Light for AreaLight {
+    fn sample(&self, p: Point3) -> Option<LightSample> {
+        let direction = self.shape.random(p).try_normalize()?;
+        let pdf = self.shape.pdf_value(p, direction);
+        if pdf <= 0.0 {
+            return None;
+        }
+        let r = Ray::new(p, direction);
+        let rec = self.shape.hit(&r, 0.001, crate::Float::INFINITY)?;
+        let radiance = rec.material.emitted(&r, &rec);
+        if radiance == color::Color::splat(0.0) {
+            return None;
+        }
+        Some(LightSample {
+            direction,
+            distance: rec.t,
+            radiance,
+            pdf,
+        })
+    }
+
+    fn pdf(&self, p: Point3, direction: Vec3) -> crate::Float {
+        self.shape.pdf_value(p, direction)
+    }
+
+    fn is_delta(&self) -> bool {
+        false
+    }
+
+    fn bounds(&self) -> Option<LightBounds> {
+        Some(LightBounds {
+            bounds: self.bounds.clone(),
+            phi: self.power,
+            normals: self.normals,
+            cos_theta_e: self.cos_theta_e,
+            two_sided: self.two_sided,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hittable::{FlipFace, Quad, Sphere};
+    use crate::material::DiffuseLight;
+    use crate::Color;
+
+    const TOLERANCE: crate::Float = 1e3 * crate::Float::EPSILON;
+
+    fn quad(material: DiffuseLight) -> Arc<dyn Hittable> {
+        Arc::new(Quad::new(
+            Point3::new(-1.0, 2.0, -1.0),
+            Vec3::new(2.0, 0.0, 0.0),
+            Vec3::new(0.0, 0.0, 2.0),
+            Arc::new(material),
+        ))
+    }
+
+    #[test]
+    fn bounds_from_shape_and_material() {
+        // u cross v points down
+        let material = DiffuseLight::new_with_color(Color::splat(1.0))
+            .with_two_sided(false)
+            .with_spread(60.0);
+        let bounds = AreaLight::new(quad(material)).bounds().unwrap();
+        assert!((bounds.normals.w - -Vec3::Y).length() < TOLERANCE);
+        assert!((bounds.normals.cos_theta - 1.0).abs() < TOLERANCE);
+        assert!((bounds.cos_theta_e - (30.0 as crate::Float).to_radians().cos()).abs() < TOLERANCE);
+        assert!(!bounds.two_sided);
+        assert!(bounds.importance(Point3::ZERO, Vec3::Y) > 0.0);
+        assert_eq!(bounds.importance(Point3::new(0.0, 4.0, 0.0), -Vec3::Y), 0.0);
+
+        let material = DiffuseLight::new_with_color(Color::splat(1.0)).with_two_sided(false);
+        let flipped = AreaLight::new(Arc::new(FlipFace::new(quad(material))));
+        let bounds = flipped.bounds().unwrap();
+        assert!((bounds.normals.w - Vec3::Y).length() < TOLERANCE);
+        assert_eq!(bounds.cos_theta_e, 0.0);
+        assert!(bounds.importance(Point3::new(0.0, 4.0, 0.0), -Vec3::Y) > 0.0);
+        assert_eq!(bounds.importance(Point3::ZERO, Vec3::Y), 0.0);
+    }
+
+    #[test]
+    fn curved_bounds() {
+        let material = Arc::new(DiffuseLight::new_with_color(Color::splat(1.0)));
+        let sphere = AreaLight::new(Arc::new(Sphere::new(Point3::ZERO, 1.0, material)));
+        let bounds = sphere.bounds().unwrap();
+        assert_eq!(bounds.normals.cos_theta, -1.0);
+        assert!(bounds.two_sided);
+    }
+}
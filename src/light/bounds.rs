@@ -0,0 +1,191 @@
+use crate::hittable::Aabb;
+use crate::types::{Point3, PI};
+use crate::Vec3;
+
+/// Directions within an angle of an axis.
+#[derive(Clone, Copy)]
+pub struct DirectionCone {
+    pub w: Vec3,
+    pub cos_theta: crate::Float,
+}
+
+impl DirectionCone {
+    pub fn new(w: Vec3, cos_theta: crate::Float) -> Self {
+        Self {
+            w: w.normalize(),
+            cos_theta,
+        }
+    }
+
+    pub fn entire_sphere() -> Self {
+        Self::new(Vec3::Z, -1.0)
+    }
+
+    /// Smallest cone holding both.
+    pub fn union(&self, other: &DirectionCone) -> DirectionCone {
+        let theta_a = self.cos_theta.clamp(-1.0, 1.0).acos();
+        let theta_b = other.cos_theta.clamp(-1.0, 1.0).acos();
+        let theta_d = self.w.dot(other.w).clamp(-1.0, 1.0).acos();
+        if (theta_d + theta_b).min(PI) <= theta_a {
+            return *self;
+        }
+        if (theta_d + theta_a).min(PI) <= theta_b {
+            return *other;
+        }
+
+        let theta_o = (theta_a + theta_d + theta_b) / 2.0;
+        if theta_o >= PI {
+            return Self::entire_sphere();
+        }
+        // turn the axis of self towards other
+        let theta_r = theta_o - theta_a;
+        let axis = match self.w.cross(other.w).try_normalize() {
+            Some(axis) => axis,
+            None => return Self::entire_sphere(),
+        };
+        let (sin, cos) = theta_r.sin_cos();
+        let w = self.w * cos + axis.cross(self.w) * sin + axis * axis.dot(self.w) * (1.0 - cos);
+        Self::new(w, theta_o.cos())
+    }
+}
+
+/// Where a light or a group of lights is, where it shines and how much,
+/// to estimate how much it lights a point (Conty Estevez and Kulla,
+/// Importance Sampling of Many Lights with Adaptive Tree Splitting).
+#[derive(Clone)]
+pub struct LightBounds {
+    pub bounds: Aabb,
+    /// Power, in luminance
+    pub phi: crate::Float,
+    /// Normals of the emitting surfaces
+    pub normals: DirectionCone,
+    /// Cosine of the angle beyond the normals light leaves at, pi / 2 for
+    /// diffuse emitters
+    pub cos_theta_e: crate::Float,
+    pub two_sided: bool,
+}
+
+/// cos (a - b), or 1 if a is less than b.
+fn cos_sub_clamped(
+    sin_a: crate::Float,
+    cos_a: crate::Float,
+    sin_b: crate::Float,
+    cos_b: crate::Float,
+) -> crate::Float {
+    if cos_a > cos_b {
+        1.0
+    } else {
+        cos_a * cos_b + sin_a * sin_b
+    }
+}
+
+/// sin (a - b), or 0 if a is less than b.
+fn sin_sub_clamped(
+    sin_a: crate::Float,
+    cos_a: crate::Float,
+    sin_b: crate::Float,
+    cos_b: crate::Float,
+) -> crate::Float {
+    if cos_a > cos_b {
+        0.0
+    } else {
+        sin_a * cos_b - cos_a * sin_b
+    }
+}
+
+fn sin_from_cos(cos: crate::Float) -> crate::Float {
+    (1.0 - cos * cos).max(0.0).sqrt()
+}
+
+impl LightBounds {
+    pub fn centroid(&self) -> Point3 {
+        (self.bounds.min() + self.bounds.max()) / 2.0
+    }
+
+    pub fn union(&self, other: &LightBounds) -> LightBounds {
+        if self.phi == 0.0 {
+            return other.clone();
+        }
+        if other.phi == 0.0 {
+            return self.clone();
+        }
+        LightBounds {
+            bounds: self.bounds.surrounding_box(&other.bounds),
+            phi: self.phi + other.phi,
+            normals: self.normals.union(&other.normals),
+            cos_theta_e: self.cos_theta_e.min(other.cos_theta_e),
+            two_sided: self.two_sided || other.two_sided,
+        }
+    }
+
+    /// Cosine of the half angle the bounds cover seen from `p`.
+    fn cos_theta_bounds(&self, p: Point3) -> crate::Float {
+        let (min, max) = (self.bounds.min(), self.bounds.max());
+        if p.cmpge(min).all() && p.cmple(max).all() {
+            return -1.0;
+        }
+        let center = self.centroid();
+        let radius_2 = (max - center).length_squared();
+        let distance_2 = (p - center).length_squared();
+        if distance_2 < radius_2 {
+            return -1.0;
+        }
+        (1.0 - radius_2 / distance_2).max(0.0).sqrt()
+    }
+
+    /// Conservative estimate of the light reaching `p` on a surface with
+    /// normal `n`, zero `n` for points in a volume.
+    pub fn importance(&self, p: Point3, n: Vec3) -> crate::Float {
+        let center = self.centroid();
+        let diagonal = (self.bounds.max() - self.bounds.min()).length();
+        let distance_2 = (p - center).length_squared().max(diagonal / 2.0);
+
+        let wi = (p - center).try_normalize().unwrap_or(Vec3::Z);
+        let mut cos_theta_w = self.normals.w.dot(wi);
+        if self.two_sided {
+            cos_theta_w = cos_theta_w.abs();
+        }
+        let sin_theta_w = sin_from_cos(cos_theta_w);
+
+        // angle between the normals and p, less the spread of the normals
+        // and of the bounds
+        let cos_theta_b = self.cos_theta_bounds(p);
+        let sin_theta_b = sin_from_cos(cos_theta_b);
+        let cos_theta_o = self.normals.cos_theta;
+        let sin_theta_o = sin_from_cos(cos_theta_o);
+        let cos_theta_x = cos_sub_clamped(sin_theta_w, cos_theta_w, sin_theta_o, cos_theta_o);
+        let sin_theta_x = sin_sub_clamped(sin_theta_w, cos_theta_w, sin_theta_o, cos_theta_o);
+        let cos_theta_p = cos_sub_clamped(sin_theta_x, cos_theta_x, sin_theta_b, cos_theta_b);
+        if cos_theta_p <= self.cos_theta_e {
+            return 0.0;
+        }
+
+        let mut importance = self.phi * cos_theta_p / distance_2;
+        if n != Vec3::ZERO {
+            let cos_theta_i = wi.dot(n).abs();
+            let sin_theta_i = sin_from_cos(cos_theta_i);
+            importance *= cos_sub_clamped(sin_theta_i, cos_theta_i, sin_theta_b, cos_theta_b);
+        }
+        importance.max(0.0)
+    }
+
+    /// Of splitting lights into these bounds when building a tree.
+    pub(super) fn cost(&self, axis: usize) -> crate::Float {
+        let theta_o = self.normals.cos_theta.clamp(-1.0, 1.0).acos();
+        let theta_e = self.cos_theta_e.clamp(-1.0, 1.0).acos();
+        let theta_w = (theta_o + theta_e).min(PI);
+        let sin_theta_o = sin_from_cos(self.normals.cos_theta);
+        let m_omega = 2.0 * PI * (1.0 - self.normals.cos_theta)
+            + PI / 2.0
+                * (2.0 * theta_w * sin_theta_o
+                    - (theta_o - 2.0 * theta_w).cos()
+                    - 2.0 * theta_o * sin_theta_o
+                    + self.normals.cos_theta);
+
+        let diagonal = self.bounds.max() - self.bounds.min();
+        let k_r = diagonal.max_element() / diagonal[axis];
+        let area =
+            2.0 * (diagonal.x * diagonal.y + diagonal.y * diagonal.z + diagonal.z * diagonal.x);
+        self.phi * m_omega * k_r * area
+    }
+}
@@ -0,0 +1,388 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::hittable::Aabb;
+use crate::types::Point3;
+use crate::utils;
+use crate::Vec3;
+
+use super::bounds::LightBounds;
+use super::{light_id, Light};
+
+const BUCKETS: usize = 12;
+
+enum LightNode {
+    Leaf {
+        bounds: LightBounds,
+        light: usize,
+    },
+    /// The first child follows, `second` is the index of the other one
+    Interior {
+        bounds: LightBounds,
+        second: usize,
+    },
+}
+
+impl LightNode {
+    fn bounds(&self) -> &LightBounds {
+        match self {
+            LightNode::Leaf { bounds, .. } | LightNode::Interior { bounds, .. } => bounds,
+        }
+    }
+}
+
+/// Picks a light with a probability following how much it may light a
+/// point, walking down a tree of lights grouped by position and direction.
+/// Lights infinitely far away are picked apart from the tree.
+pub struct LightBvh {
+    lights: Vec<Arc<dyn Light>>,
+    nodes: Vec<LightNode>,
+    infinite: Vec<usize>,
+    /// Per light in the tree, the turns down to its leaf, bit i for depth i
+    trails: Vec<Option<u64>>,
+    /// Index of each light by its `light_id`
+    indices: HashMap<usize, usize>,
+}
+
+impl LightBvh {
+    pub fn new(lights: &[Arc<dyn Light>]) -> Self {
+        let mut bvh = Self {
+            lights: lights.to_vec(),
+            nodes: Vec::new(),
+            infinite: Vec::new(),
+            trails: vec![None; lights.len()],
+            indices: lights
+                .iter()
+                .enumerate()
+                .map(|(i, light)| (light_id(light.as_ref()), i))
+                .collect(),
+        };
+
+        let mut bounded = Vec::new();
+        for (i, light) in lights.iter().enumerate() {
+            match light.bounds() {
+                Some(bounds) if bounds.phi > 0.0 => bounded.push((i, bounds)),
+                Some(_) => {}
+                None => bvh.infinite.push(i),
+            }
+        }
+        if !bounded.is_empty() {
+            bvh.build(&mut bounded, 0, 0);
+        }
+        bvh
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty() && self.infinite.is_empty()
+    }
+
+    fn build(
+        &mut self,
+        lights: &mut [(usize, LightBounds)],
+        trail: u64,
+        depth: u32,
+    ) -> LightBounds {
+        if lights.len() == 1 {
+            let (light, bounds) = lights[0].clone();
+            self.trails[light] = Some(trail);
+            self.nodes.push(LightNode::Leaf {
+                bounds: bounds.clone(),
+                light,
+            });
+            return bounds;
+        }
+
+        let mid = split(lights, depth);
+        let node = self.nodes.len();
+        self.nodes.push(LightNode::Interior {
+            bounds: lights[0].1.clone(),
+            second: 0,
+        });
+        let (left, right) = lights.split_at_mut(mid);
+        let first_bounds = self.build(left, trail, depth + 1);
+        let second = self.nodes.len();
+        let second_bounds = self.build(right, trail | (1 << depth), depth + 1);
+        let bounds = first_bounds.union(&second_bounds);
+        self.nodes[node] = LightNode::Interior {
+            bounds: bounds.clone(),
+            second,
+        };
+        bounds
+    }
+
+    fn infinite_probability(&self) -> crate::Float {
+        let infinite = self.infinite.len() as crate::Float;
+        let tree = if self.nodes.is_empty() { 0.0 } else { 1.0 };
+        if infinite + tree == 0.0 {
+            0.0
+        } else {
+            infinite / (infinite + tree)
+        }
+    }
+
+    /// A light for a point on a surface with normal `n`, zero `n` for points
+    /// in a volume, with the probability of picking it.
+    pub fn choose(&self, p: Point3, n: Vec3) -> Option<(&dyn Light, crate::Float)> {
+        let infinite_probability = self.infinite_probability();
+        if utils::gen_float() < infinite_probability {
+            let index = utils::gen_range(0..self.infinite.len());
+            let light = self.lights[self.infinite[index]].as_ref();
+            return Some((
+                light,
+                infinite_probability / self.infinite.len() as crate::Float,
+            ));
+        }
+        if self.nodes.is_empty() {
+            return None;
+        }
+
+        let mut pmf = 1.0 - infinite_probability;
+        let mut node = 0;
+        loop {
+            match &self.nodes[node] {
+                LightNode::Leaf { bounds, light } => {
+                    if node == 0 && bounds.importance(p, n) == 0.0 {
+                        return None;
+                    }
+                    return Some((self.lights[*light].as_ref(), pmf));
+                }
+                LightNode::Interior { second, .. } => {
+                    let first_importance = self.nodes[node + 1].bounds().importance(p, n);
+                    let second_importance = self.nodes[*second].bounds().importance(p, n);
+                    let total = first_importance + second_importance;
+                    if total == 0.0 {
+                        return None;
+                    }
+                    let first_probability = first_importance / total;
+                    if utils::gen_float() < first_probability {
+                        pmf *= first_probability;
+                        node += 1;
+                    } else {
+                        pmf *= 1.0 - first_probability;
+                        node = *second;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Probability of `choose` picking the light at `index` of the list the
+    /// tree was built from.
+    pub fn pmf(&self, p: Point3, n: Vec3, index: usize) -> crate::Float {
+        let infinite_probability = self.infinite_probability();
+        let mut trail = match self.trails.get(index) {
+            Some(Some(trail)) => *trail,
+            _ => {
+                return if self.infinite.contains(&index) {
+                    infinite_probability / self.infinite.len() as crate::Float
+                } else {
+                    0.0
+                };
+            }
+        };
+
+        let mut pmf = 1.0 - infinite_probability;
+        let mut node = 0;
+        while let LightNode::Interior { second, .. } = &self.nodes[node] {
+            let first_importance = self.nodes[node + 1].bounds().importance(p, n);
+            let second_importance = self.nodes[*second].bounds().importance(p, n);
+            let total = first_importance + second_importance;
+            if total == 0.0 {
+                return 0.0;
+            }
+            if trail & 1 == 0 {
+                pmf *= first_importance / total;
+                node += 1;
+            } else {
+                pmf *= second_importance / total;
+                node = *second;
+            }
+            trail >>= 1;
+        }
+        pmf
+    }
+
+    /// Density over solid angle of picking the light of `light_id` and then
+    /// `direction` from it, for weighting light found by hitting it.
+    pub fn pdf(&self, p: Point3, n: Vec3, light_id: usize, direction: Vec3) -> crate::Float {
+        match self.indices.get(&light_id) {
+            Some(&index) => self.pmf(p, n, index) * self.lights[index].pdf(p, direction),
+            None => 0.0,
+        }
+    }
+}
+
+/// Which of the buckets along `axis` of the centroid bounds a light is in.
+fn bucket(light: &LightBounds, axis: usize, centroids: &Aabb) -> usize {
+    let min = centroids.min()[axis];
+    let t = (light.centroid()[axis] - min) / (centroids.max()[axis] - min);
+    ((t * BUCKETS as crate::Float) as usize).min(BUCKETS - 1)
+}
+
+/// Levels below a node of `count` lights when halving them all the way.
+fn halving_depth(count: usize) -> u32 {
+    usize::BITS - (count - 1).leading_zeros()
+}
+
+/// Orders the lights and returns where to split them in two, along the
+/// bucket boundary of least cost.
+fn split(lights: &mut [(usize, LightBounds)], depth: u32) -> usize {
+    let centroids = lights
+        .iter()
+        .fold(None, |bounds: Option<Aabb>, (_, light)| {
+            let c = light.centroid();
+            let point = Aabb::new(c, c);
+            Some(match bounds {
+                Some(bounds) => bounds.surrounding_box(&point),
+                None => point,
+            })
+        });
+    let centroids = centroids.unwrap();
+    let extent = centroids.max() - centroids.min();
+
+    let mut best: Option<(crate::Float, usize, usize)> = None;
+    // the trail of a leaf has one bit per level, keep enough of them to
+    // halve the rest of the lights down to leaves
+    if depth + 1 + halving_depth(lights.len()) <= u64::BITS {
+        for axis in 0..3 {
+            if extent[axis] <= 0.0 {
+                continue;
+            }
+            let bucket = |light: &LightBounds| bucket(light, axis, &centroids);
+            let mut buckets: Vec<Option<LightBounds>> = vec![None; BUCKETS];
+            for (_, light) in lights.iter() {
+                let b = &mut buckets[bucket(light)];
+                *b = Some(match b {
+                    Some(bounds) => bounds.union(light),
+                    None => light.clone(),
+                });
+            }
+            let union = |buckets: &[Option<LightBounds>]| {
+                buckets
+                    .iter()
+                    .flatten()
+                    .fold(None, |sum: Option<LightBounds>, b| {
+                        Some(match sum {
+                            Some(sum) => sum.union(b),
+                            None => b.clone(),
+                        })
+                    })
+            };
+            for split in 1..BUCKETS {
+                let (below, above) = (union(&buckets[..split]), union(&buckets[split..]));
+                if let (Some(below), Some(above)) = (below, above) {
+                    let cost = below.cost(axis) + above.cost(axis);
+                    if cost > 0.0 && best.map_or(true, |(best, _, _)| cost < best) {
+                        best = Some((cost, axis, split));
+                    }
+                }
+            }
+        }
+    }
+
+    match best {
+        Some((_, axis, split)) => {
+            let bucket = |light: &LightBounds| bucket(light, axis, &centroids);
+            lights.sort_by_key(|(_, light)| bucket(light));
+            lights.partition_point(|(_, light)| bucket(light) < split)
+        }
+        None => {
+            // lights on top of each other or no useful cost, halve them
+            let axis = if extent.x >= extent.y && extent.x >= extent.z {
+                0
+            } else if extent.y >= extent.z {
+                1
+            } else {
+                2
+            };
+            lights.sort_by(|(_, a), (_, b)| {
+                a.centroid()[axis]
+                    .partial_cmp(&b.centroid()[axis])
+                    .unwrap_or(std::cmp::Ordering::Equal)
+            });
+            lights.len() / 2
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::light::{DirectionalLight, PointLight, SpotLight};
+    use crate::Color;
+
+    /// Rounding error allowed in probabilities.
+    const TOLERANCE: crate::Float = 1e3 * crate::Float::EPSILON;
+
+    fn point(position: Point3, intensity: crate::Float) -> Arc<dyn Light> {
+        Arc::new(PointLight::new(position, Color::splat(intensity)))
+    }
+
+    #[test]
+    fn pmf_matches_choose() {
+        const SAMPLES: usize = 400_000;
+        let mut lights = vec![
+            Arc::new(DirectionalLight::new(
+                Vec3::new(1.0, -1.0, 0.0),
+                Color::splat(1.0),
+            )) as Arc<dyn Light>,
+            Arc::new(SpotLight::new(
+                Point3::new(0.0, 3.0, 0.0),
+                -Vec3::Y,
+                Color::splat(20.0),
+                30.0,
+                40.0,
+            )),
+        ];
+        for i in 0..20 {
+            let i = i as crate::Float;
+            lights.push(point(
+                Point3::new(i.sin() * 5.0, 1.0 + i % 3.0, i.cos() * 4.0),
+                1.0 + i,
+            ));
+        }
+        let bvh = LightBvh::new(&lights);
+
+        for (p, n) in [
+            (Point3::new(0.5, 0.0, 0.5), Vec3::Y),
+            (Point3::new(-3.0, 2.0, 1.0), Vec3::ZERO),
+        ] {
+            let mut counts = HashMap::new();
+            for _ in 0..SAMPLES {
+                if let Some((light, pmf)) = bvh.choose(p, n) {
+                    let index = bvh.indices[&light_id(light)];
+                    assert!((pmf - bvh.pmf(p, n, index)).abs() < TOLERANCE);
+                    *counts.entry(index).or_insert(0) += 1;
+                }
+            }
+            let mut total = 0.0;
+            for index in 0..lights.len() {
+                let pmf = bvh.pmf(p, n, index);
+                total += pmf;
+                let frequency =
+                    *counts.get(&index).unwrap_or(&0) as crate::Float / SAMPLES as crate::Float;
+                assert!(
+                    (frequency - pmf).abs() < 0.005,
+                    "light {}: picked {}, pmf {}",
+                    index,
+                    frequency,
+                    pmf
+                );
+            }
+            assert!((total - 1.0).abs() < TOLERANCE, "{}", total);
+        }
+    }
+
+    #[test]
+    fn deep_tree() {
+        // the cheapest split takes the farthest few lights off the rest,
+        // which would go deeper than the 64 levels a trail has room for
+        let lights: Vec<_> = (0..250)
+            .map(|i| point(Point3::splat((0.5 as crate::Float).powi(i)), 1.0))
+            .collect();
+        let bvh = LightBvh::new(&lights);
+        let p = Point3::new(1.0, 1.0, 0.0);
+        let total: crate::Float = (0..lights.len()).map(|i| bvh.pmf(p, Vec3::Y, i)).sum();
+        assert!((total - 1.0).abs() < TOLERANCE, "{}", total);
+    }
+}
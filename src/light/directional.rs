@@ -0,0 +1,63 @@
+use crate::color::Color;
+use crate::types::Point3;
+use crate::Vec3;
+
+use super::{Light, LightBounds, LightSample};
+
+/// Light from infinitely far away along one direction, like the sun without
+/// its size.
+pub struct DirectionalLight {
+    /// Towards the light
+    direction: Vec3,
+    irradiance: Color,
+}
+
+impl DirectionalLight {
+    /// * `direction` - The light travels along it
+    /// * `irradiance` - On a surface facing the light
+    pub fn new(direction: Vec3, irradiance: Color) -> Self {
+        Self {
+            direction: -direction.normalize(),
+            irradiance,
+        }
+    }
+}
+
+impl Light for DirectionalLight {
+    fn sample(&self, p: Point3) -> Option<LightSample> {
+        Some(LightSample {
+            direction: self.direction,
+            distance: crate::Float::INFINITY,
+            radiance: self.irradiance,
+            pdf: 1.0,
+        })
+    }
+
+    fn is_delta(&self) -> bool {
+        true
+    }
+
+    fn bounds(&self) -> Option<LightBounds> {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn same_everywhere() {
+        let light = DirectionalLight::new(Vec3::new(0.0, -2.0, 0.0), Color::new(1.0, 2.0, 3.0));
+        assert!(light.is_delta());
+        assert!(light.bounds().is_none());
+        for p in [Point3::splat(0.0), Point3::new(100.0, -50.0, 7.0)] {
+            let sample = light.sample(p).unwrap();
+            assert_eq!(sample.direction, Vec3::Y);
+            assert_eq!(sample.distance, crate::Float::INFINITY);
+            assert_eq!(sample.radiance, Color::new(1.0, 2.0, 3.0));
+            assert_eq!(sample.pdf, 1.0);
+            assert_eq!(light.pdf(p, Vec3::Y), 0.0);
+        }
+    }
+}
@@ -0,0 +1,365 @@
+use std::path::Path;
+
+use crate::onb::Onb;
+use crate::Vec3;
+
+/// Luminous intensity of a luminaire in every direction, measured by its
+/// manufacturer, from an IES LM-63 photometric file.
+///
+/// Only type C photometry is supported, the one of architectural
+/// luminaires: vertical angles from the nadir, horizontal angles around it.
+pub struct IesProfile {
+    /// In degrees, increasing
+    vertical_angles: Vec<crate::Float>,
+    /// In degrees, increasing
+    horizontal_angles: Vec<crate::Float>,
+    /// In candela, all vertical angles for each horizontal angle
+    candela: Vec<crate::Float>,
+}
+
+impl IesProfile {
+    /// Panics if the file can't be read or parsed.
+    pub fn new<P>(p: P) -> Self
+    where
+        P: AsRef<Path>,
+    {
+        let text = std::fs::read_to_string(p).unwrap();
+        Self::parse(&text).unwrap_or_else(|e| panic!("invalid IES file: {}", e))
+    }
+
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut lines = text.lines();
+        // keywords up to the tilt line
+        let tilt = loop {
+            let line = lines.next().ok_or("missing TILT line")?.trim();
+            if let Some(tilt) = line.strip_prefix("TILT=") {
+                break tilt.trim().to_string();
+            }
+        };
+
+        let rest: Vec<&str> = lines.collect();
+        let mut numbers = rest
+            .iter()
+            .flat_map(|line| line.split(|c: char| c.is_whitespace() || c == ','))
+            .filter(|token| !token.is_empty())
+            .map(|token| {
+                token
+                    .parse::<crate::Float>()
+                    .map_err(|_| format!("not a number: {}", token))
+            });
+        let mut next = || numbers.next().unwrap_or(Err("file ends early".into()));
+
+        if tilt == "INCLUDE" {
+            // lamp tilt multipliers, which need the mounting angle to apply
+            let _geometry = next()?;
+            let count = next()? as usize;
+            for _ in 0..2 * count {
+                next()?;
+            }
+        }
+
+        let _lamps = next()?;
+        let _lumens_per_lamp = next()?;
+        let multiplier = next()?;
+        let vertical_count = next()? as usize;
+        let horizontal_count = next()? as usize;
+        let photometric_type = next()? as u32;
+        // units and size of the luminous opening
+        for _ in 0..4 {
+            next()?;
+        }
+        let ballast_factor = next()?;
+        let _ballast_lamp_factor = next()?;
+        let _input_watts = next()?;
+
+        if photometric_type != 1 {
+            return Err(format!(
+                "photometric type {} is not supported, only type C",
+                photometric_type
+            ));
+        }
+        if vertical_count == 0 || horizontal_count == 0 {
+            return Err("no angles".into());
+        }
+
+        let mut read = |count: usize| (0..count).map(|_| next()).collect::<Result<Vec<_>, _>>();
+        let vertical_angles = read(vertical_count)?;
+        let horizontal_angles = read(horizontal_count)?;
+        let scale = multiplier * ballast_factor;
+        let candela = read(vertical_count * horizontal_count)?
+            .into_iter()
+            .map(|c| (c * scale).max(0.0))
+            .collect();
+
+        let increasing = |angles: &[crate::Float]| angles.windows(2).all(|w| w[0] < w[1]);
+        if !increasing(&vertical_angles) || !increasing(&horizontal_angles) {
+            return Err("angles are not increasing".into());
+        }
+
+        Ok(Self {
+            vertical_angles,
+            horizontal_angles,
+            candela,
+        })
+    }
+
+    /// Brightest value of the profile, in candela.
+    pub fn max_candela(&self) -> crate::Float {
+        self.candela.iter().copied().fold(0.0, crate::Float::max)
+    }
+
+    /// Folds a horizontal angle in [0, 360) onto the range measured, by the
+    /// symmetry the range implies.
+    fn horizontal(&self, angle: crate::Float) -> crate::Float {
+        let first = self.horizontal_angles[0];
+        let last = *self.horizontal_angles.last().unwrap();
+        if first == 0.0 && last == 90.0 {
+            // symmetric in each quadrant
+            let angle = angle % 180.0;
+            if angle > 90.0 {
+                180.0 - angle
+            } else {
+                angle
+            }
+        } else if first == 0.0 && last == 180.0 {
+            // symmetric about the 0 to 180 plane
+            if angle > 180.0 {
+                360.0 - angle
+            } else {
+                angle
+            }
+        } else if first == 90.0 && last == 270.0 {
+            // symmetric about the 90 to 270 plane
+            if angle < 90.0 {
+                180.0 - angle
+            } else if angle > 270.0 {
+                540.0 - angle
+            } else {
+                angle
+            }
+        } else {
+            angle
+        }
+    }
+
+    /// In candela towards a direction of the luminaire's frame, `w` of which
+    /// is the nadir and `u` the 0 degree horizontal angle.
+    pub fn candela(&self, frame: &Onb, direction: Vec3) -> crate::Float {
+        let d = frame.to_local(direction.normalize());
+        let vertical = d.z.clamp(-1.0, 1.0).acos().to_degrees();
+        let horizontal = d.y.atan2(d.x).to_degrees().rem_euclid(360.0);
+
+        let (v0, v1, tv) = match bracket(&self.vertical_angles, vertical) {
+            Some(bracket) => bracket,
+            None => return 0.0,
+        };
+        let (h0, h1, th) = if self.horizontal_angles.len() == 1 {
+            // rotationally symmetric
+            (0, 0, 0.0)
+        } else {
+            let horizontal = self.horizontal(horizontal);
+            match bracket(&self.horizontal_angles, horizontal) {
+                Some(bracket) => bracket,
+                None => {
+                    // in the gap of a full turn measured without both ends
+                    let last = self.horizontal_angles.len() - 1;
+                    let first_angle = self.horizontal_angles[0] + 360.0;
+                    let last_angle = self.horizontal_angles[last];
+                    let horizontal = if horizontal < last_angle {
+                        horizontal + 360.0
+                    } else {
+                        horizontal
+                    };
+                    let t = (horizontal - last_angle) / (first_angle - last_angle);
+                    (last, 0, t.clamp(0.0, 1.0))
+                }
+            }
+        };
+
+        let value = |h: usize, v: usize| self.candela[h * self.vertical_angles.len() + v];
+        let at_h0 = value(h0, v0) * (1.0 - tv) + value(h0, v1) * tv;
+        let at_h1 = value(h1, v0) * (1.0 - tv) + value(h1, v1) * tv;
+        at_h0 * (1.0 - th) + at_h1 * th
+    }
+}
+
+/// Frame of a luminaire pointing its nadir along `nadir`, turned by
+/// `rotation` degrees about it.
+pub fn luminaire_frame(nadir: Vec3, rotation: crate::Float) -> Onb {
+    let frame = Onb::new_from_w(nadir.normalize());
+    let (sin, cos) = rotation.to_radians().sin_cos();
+    Onb {
+        u: cos * frame.u + sin * frame.v,
+        v: -sin * frame.u + cos * frame.v,
+        w: frame.w,
+    }
+}
+
+/// Indices of the angles around `angle` and how far it is between them, or
+/// None outside the measured range.
+fn bracket(angles: &[crate::Float], angle: crate::Float) -> Option<(usize, usize, crate::Float)> {
+    let first = angles[0];
+    let last = *angles.last().unwrap();
+    if angles.len() == 1 {
+        return if (angle - first).abs() < 1e-6 {
+            Some((0, 0, 0.0))
+        } else {
+            None
+        };
+    }
+    if angle < first || angle > last {
+        return None;
+    }
+    let upper = angles
+        .partition_point(|a| *a <= angle)
+        .clamp(1, angles.len() - 1);
+    let lower = upper - 1;
+    let t = (angle - angles[lower]) / (angles[upper] - angles[lower]);
+    Some((lower, upper, t))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TOLERANCE: crate::Float = 1e-3;
+
+    /// An LM-63 file with one lamp and no tilt.
+    fn file(
+        vertical: &[crate::Float],
+        horizontal: &[crate::Float],
+        candela: &[crate::Float],
+    ) -> String {
+        let join = |values: &[crate::Float]| {
+            values
+                .iter()
+                .map(|v| v.to_string())
+                .collect::<Vec<_>>()
+                .join(" ")
+        };
+        format!(
+            "IESNA:LM-63-2002\n[TEST] made up\nTILT=NONE\n\
+             1 1000 1 {} {} 1 2 0.1 0.1 0\n1 1 50\n{}\n{}\n{}\n",
+            vertical.len(),
+            horizontal.len(),
+            join(vertical),
+            join(horizontal),
+            join(candela)
+        )
+    }
+
+    /// Vertical angles of 0, 45 and 90 degrees, the candela at each
+    /// horizontal angle being the angle itself.
+    fn by_horizontal(horizontal: &[crate::Float]) -> IesProfile {
+        let candela: Vec<_> = horizontal.iter().flat_map(|&h| [h, h, h]).collect();
+        IesProfile::parse(&file(&[0.0, 45.0, 90.0], horizontal, &candela)).unwrap()
+    }
+
+    fn frame() -> Onb {
+        Onb {
+            u: Vec3::X,
+            v: Vec3::Y,
+            w: Vec3::Z,
+        }
+    }
+
+    fn candela(
+        profile: &IesProfile,
+        vertical: crate::Float,
+        horizontal: crate::Float,
+    ) -> crate::Float {
+        let (v, h) = (vertical.to_radians(), horizontal.to_radians());
+        let direction = Vec3::new(v.sin() * h.cos(), v.sin() * h.sin(), v.cos());
+        profile.candela(&frame(), direction)
+    }
+
+    #[test]
+    fn parse() {
+        let text = file(&[0.0, 90.0], &[0.0], &[100.0, 50.0]).replace("1 1000 1", "1 1000 2");
+        let profile = IesProfile::parse(&text).unwrap();
+        // multiplier and ballast factor
+        assert!((profile.max_candela() - 200.0).abs() < TOLERANCE);
+
+        let commas = text.replace("100 50", "100,50");
+        assert!(IesProfile::parse(&commas).is_ok());
+
+        // lamp to luminaire geometry, two tilt angles and their multipliers
+        let tilt = text.replace("TILT=NONE\n", "TILT=INCLUDE\n1\n2\n0 90\n1 0.5\n");
+        let tilted = IesProfile::parse(&tilt).unwrap();
+        assert!((tilted.max_candela() - 200.0).abs() < TOLERANCE);
+    }
+
+    #[test]
+    fn parse_errors() {
+        let text = file(&[0.0, 90.0], &[0.0], &[100.0, 50.0]);
+        assert!(IesProfile::parse("IESNA:LM-63-2002\n").is_err());
+        assert!(IesProfile::parse(&text.replace("100 50", "100")).is_err());
+        assert!(IesProfile::parse(&text.replace("100 50", "100 x")).is_err());
+        // type B photometry
+        assert!(IesProfile::parse(&text.replace(" 1 2 0.1", " 2 2 0.1")).is_err());
+        let decreasing = file(&[90.0, 0.0], &[0.0], &[100.0, 50.0]);
+        assert!(IesProfile::parse(&decreasing).is_err());
+    }
+
+    #[test]
+    fn vertical_interpolation() {
+        let profile = IesProfile::parse(&file(&[0.0, 90.0], &[0.0], &[100.0, 50.0])).unwrap();
+        assert!((candela(&profile, 0.0, 0.0) - 100.0).abs() < TOLERANCE);
+        assert!((candela(&profile, 45.0, 0.0) - 75.0).abs() < TOLERANCE);
+        // rotationally symmetric
+        assert!((candela(&profile, 45.0, 123.0) - 75.0).abs() < TOLERANCE);
+        // above the measured range
+        assert_eq!(candela(&profile, 135.0, 0.0), 0.0);
+    }
+
+    #[test]
+    fn quadrant_symmetry() {
+        let profile = by_horizontal(&[0.0, 45.0, 90.0]);
+        for (horizontal, folded) in [(30.0, 30.0), (135.0, 45.0), (200.0, 20.0), (300.0, 60.0)] {
+            let value = candela(&profile, 45.0, horizontal);
+            assert!(
+                (value - folded).abs() < TOLERANCE,
+                "{}: {}",
+                horizontal,
+                value
+            );
+        }
+    }
+
+    #[test]
+    fn bilateral_symmetry() {
+        let profile = by_horizontal(&[0.0, 90.0, 180.0]);
+        for (horizontal, folded) in [(45.0, 45.0), (270.0, 90.0), (350.0, 10.0)] {
+            let value = candela(&profile, 45.0, horizontal);
+            assert!(
+                (value - folded).abs() < TOLERANCE,
+                "{}: {}",
+                horizontal,
+                value
+            );
+        }
+
+        let profile = by_horizontal(&[90.0, 180.0, 270.0]);
+        for (horizontal, folded) in [(180.0, 180.0), (45.0, 135.0), (300.0, 240.0)] {
+            let value = candela(&profile, 45.0, horizontal);
+            assert!(
+                (value - folded).abs() < TOLERANCE,
+                "{}: {}",
+                horizontal,
+                value
+            );
+        }
+    }
+
+    #[test]
+    fn full_turn() {
+        let profile = by_horizontal(&[0.0, 90.0, 180.0, 270.0, 360.0]);
+        let value = candela(&profile, 45.0, 315.0);
+        assert!((value - 315.0).abs() < TOLERANCE);
+
+        // without the closing angle, the gap wraps around to 0
+        let profile = by_horizontal(&[0.0, 90.0, 180.0, 270.0]);
+        let value = candela(&profile, 45.0, 315.0);
+        assert!((value - 135.0).abs() < TOLERANCE, "{}", value);
+    }
+}
@@ -0,0 +1,82 @@
+//! Lights sampled explicitly from the surfaces they light, rather than found
+//! by rays bouncing into them.
+
+mod area;
+mod bounds;
+mod bvh;
+mod directional;
+mod ies;
+mod point;
+mod spot;
+
+pub use area::AreaLight;
+pub use bounds::{DirectionCone, LightBounds};
+pub use bvh::LightBvh;
+pub use directional::DirectionalLight;
+pub use ies::IesProfile;
+pub use point::PointLight;
+pub use spot::SpotLight;
+
+use std::sync::Arc;
+
+use crate::color::Color;
+use crate::types::Point3;
+use crate::Vec3;
+
+/// Light arriving at a point from a light.
+pub struct LightSample {
+    /// Normalized, towards the light
+    pub direction: Vec3,
+    /// To the light along `direction`, infinite for distant lights
+    pub distance: crate::Float,
+    /// Radiance arriving along `direction`, for lights of no size the
+    /// irradiance on a surface facing the light
+    pub radiance: Color,
+    /// Density over solid angle of picking `direction`, 1 for lights of no
+    /// size which can only be found by sampling them
+    pub pdf: crate::Float,
+}
+
+pub trait Light: Sync + Send {
+    fn sample(&self, p: Point3) -> Option<LightSample>;
+
+    /// Whether the light is a point or a direction, which rays can't hit.
+    fn is_delta(&self) -> bool;
+
+    /// Density over solid angle of `sample` picking `direction` from `p`,
+    /// zero for lights rays can't hit.
+    fn pdf(&self, p: Point3, direction: Vec3) -> crate::Float {
+        0.0
+    }
+
+    /// Where the light is and where it shines, None for lights infinitely
+    /// far away.
+    fn bounds(&self) -> Option<LightBounds>;
+}
+
+/// Identifies a light across hits, see `HitRecord::light`.
+pub fn light_id(light: &dyn Light) -> usize {
+    light as *const dyn Light as *const () as usize
+}
+
+pub struct LightList {
+    lights: Vec<Arc<dyn Light>>,
+}
+
+impl LightList {
+    pub fn new() -> Self {
+        Self { lights: vec![] }
+    }
+
+    pub fn add(&mut self, light: Arc<dyn Light>) {
+        self.lights.push(light);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lights.is_empty()
+    }
+
+    pub fn build_bvh(&self) -> LightBvh {
+        LightBvh::new(&self.lights)
+    }
+}
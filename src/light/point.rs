@@ -0,0 +1,114 @@
+use std::sync::Arc;
+
+use crate::color::{self, Color};
+use crate::hittable::Aabb;
+use crate::onb::Onb;
+use crate::types::{Point3, PI};
+use crate::Vec3;
+
+use super::ies::{self, IesProfile};
+use super::{DirectionCone, Light, LightBounds, LightSample};
+
+/// Light from a single point, equally in all directions unless it follows
+/// the profile of a luminaire.
+pub struct PointLight {
+    position: Point3,
+    intensity: Color,
+    profile: Option<(Arc<IesProfile>, Onb)>,
+}
+
+impl PointLight {
+    /// * `intensity` - Power per solid angle, a surface facing the light at
+    ///   distance d receives intensity / d^2
+    pub fn new(position: Point3, intensity: Color) -> Self {
+        Self {
+            position,
+            intensity,
+            profile: None,
+        }
+    }
+
+    /// Shines like a measured luminaire, `intensity` then scales the candela
+    /// of the profile to the units of the scene.
+    ///
+    /// * `nadir` - Where the luminaire points, usually down
+    /// * `rotation` - About the nadir, in degrees
+    pub fn with_profile(
+        self,
+        profile: Arc<IesProfile>,
+        nadir: Vec3,
+        rotation: crate::Float,
+    ) -> Self {
+        Self {
+            profile: Some((profile, ies::luminaire_frame(nadir, rotation))),
+            ..self
+        }
+    }
+}
+
+impl Light for PointLight {
+    fn sample(&self, p: Point3) -> Option<LightSample> {
+        let to_light = self.position - p;
+        let distance_squared = to_light.length_squared();
+        if distance_squared == 0.0 {
+            return None;
+        }
+        let distance = distance_squared.sqrt();
+        let direction = to_light / distance;
+        let candela = match &self.profile {
+            Some((profile, frame)) => profile.candela(frame, -direction),
+            None => 1.0,
+        };
+        if candela == 0.0 {
+            return None;
+        }
+        Some(LightSample {
+            direction,
+            distance,
+            radiance: self.intensity * candela / distance_squared,
+            pdf: 1.0,
+        })
+    }
+
+    fn is_delta(&self) -> bool {
+        true
+    }
+
+    fn bounds(&self) -> Option<LightBounds> {
+        let candela = match &self.profile {
+            Some((profile, _)) => profile.max_candela(),
+            None => 1.0,
+        };
+        Some(LightBounds {
+            bounds: Aabb::new(self.position, self.position),
+            phi: 4.0 * PI * color::luminance(self.intensity) * candela,
+            normals: DirectionCone::entire_sphere(),
+            cos_theta_e: 0.0,
+            two_sided: false,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TOLERANCE: crate::Float = 1e3 * crate::Float::EPSILON;
+
+    #[test]
+    fn inverse_square() {
+        let light = PointLight::new(Point3::new(1.0, 2.0, 3.0), Color::new(4.0, 2.0, 1.0));
+        assert!(light.is_delta());
+        for distance in [0.5, 1.0, 2.0, 10.0] {
+            let p = Point3::new(1.0, 2.0 - distance, 3.0);
+            let sample = light.sample(p).unwrap();
+            assert!((sample.direction - Vec3::Y).length() < TOLERANCE);
+            assert!((sample.distance - distance).abs() < TOLERANCE * distance);
+            let expected = Color::new(4.0, 2.0, 1.0) / (distance * distance);
+            assert!((sample.radiance - expected).length() < TOLERANCE * expected.length());
+            assert_eq!(sample.pdf, 1.0);
+            assert_eq!(light.pdf(p, sample.direction), 0.0);
+        }
+        assert!(light.sample(Point3::new(1.0, 2.0, 3.0)).is_none());
+    }
+}
@@ -0,0 +1,184 @@
+use std::sync::Arc;
+
+use crate::color::{self, Color};
+use crate::hittable::Aabb;
+use crate::onb::Onb;
+use crate::types::{Point3, PI};
+use crate::Vec3;
+
+use super::ies::{self, IesProfile};
+use super::{DirectionCone, Light, LightBounds, LightSample};
+
+/// Point light shining in a cone, full inside `falloff_start` and fading
+/// out to nothing at the edge of the cone.
+pub struct SpotLight {
+    position: Point3,
+    direction: Vec3,
+    intensity: Color,
+    cos_total_width: crate::Float,
+    cos_falloff_start: crate::Float,
+    profile: Option<(Arc<IesProfile>, Onb)>,
+}
+
+impl SpotLight {
+    /// * `direction` - The cone points along it
+    /// * `intensity` - Power per solid angle on the axis, see `PointLight`
+    /// * `cone_angle` - Half angle of the cone in degrees
+    /// * `falloff_start` - Half angle in degrees where the light starts to
+    ///   fade, at most `cone_angle`
+    pub fn new(
+        position: Point3,
+        direction: Vec3,
+        intensity: Color,
+        cone_angle: crate::Float,
+        falloff_start: crate::Float,
+    ) -> Self {
+        let cone_angle = cone_angle.clamp(0.0, 180.0);
+        Self {
+            position,
+            direction: direction.normalize(),
+            intensity,
+            cos_total_width: cone_angle.to_radians().cos(),
+            cos_falloff_start: falloff_start.clamp(0.0, cone_angle).to_radians().cos(),
+            profile: None,
+        }
+    }
+
+    /// Shines like a measured luminaire pointing its nadir along the cone,
+    /// `intensity` then scales the candela of the profile to the units of
+    /// the scene.
+    ///
+    /// * `rotation` - About the axis of the cone, in degrees
+    pub fn with_profile(self, profile: Arc<IesProfile>, rotation: crate::Float) -> Self {
+        let frame = ies::luminaire_frame(self.direction, rotation);
+        Self {
+            profile: Some((profile, frame)),
+            ..self
+        }
+    }
+
+    /// Of the intensity leaving along a direction at angle theta from the
+    /// axis, smooth from the falloff start to the edge of the cone.
+    fn falloff(&self, cos_theta: crate::Float) -> crate::Float {
+        if cos_theta <= self.cos_total_width {
+            return 0.0;
+        }
+        if cos_theta >= self.cos_falloff_start {
+            return 1.0;
+        }
+        let t =
+            (cos_theta - self.cos_total_width) / (self.cos_falloff_start - self.cos_total_width);
+        t * t * (3.0 - 2.0 * t)
+    }
+}
+
+impl Light for SpotLight {
+    fn sample(&self, p: Point3) -> Option<LightSample> {
+        let to_light = self.position - p;
+        let distance_squared = to_light.length_squared();
+        if distance_squared == 0.0 {
+            return None;
+        }
+        let distance = distance_squared.sqrt();
+        let direction = to_light / distance;
+        let mut falloff = self.falloff(-direction.dot(self.direction));
+        if let Some((profile, frame)) = &self.profile {
+            falloff *= profile.candela(frame, -direction);
+        }
+        if falloff == 0.0 {
+            return None;
+        }
+        Some(LightSample {
+            direction,
+            distance,
+            radiance: self.intensity * falloff / distance_squared,
+            pdf: 1.0,
+        })
+    }
+
+    fn is_delta(&self) -> bool {
+        true
+    }
+
+    fn bounds(&self) -> Option<LightBounds> {
+        let candela = match &self.profile {
+            Some((profile, _)) => profile.max_candela(),
+            None => 1.0,
+        };
+        // full power inside the falloff start, about half of it in the fade
+        let solid_angle = 2.0
+            * PI
+            * ((1.0 - self.cos_falloff_start)
+                + (self.cos_falloff_start - self.cos_total_width) / 2.0);
+        let theta_falloff = self.cos_falloff_start.acos();
+        let theta_total = self.cos_total_width.acos();
+        Some(LightBounds {
+            bounds: Aabb::new(self.position, self.position),
+            phi: color::luminance(self.intensity) * candela * solid_angle,
+            normals: DirectionCone::new(self.direction, self.cos_falloff_start),
+            cos_theta_e: (theta_total - theta_falloff).cos(),
+            two_sided: false,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TOLERANCE: crate::Float = 1e3 * crate::Float::EPSILON;
+
+    /// Pointing down from y = 1, full within 20 degrees and out at 40.
+    fn spot() -> SpotLight {
+        SpotLight::new(
+            Point3::new(0.0, 1.0, 0.0),
+            -Vec3::Y,
+            Color::splat(3.0),
+            40.0,
+            20.0,
+        )
+    }
+
+    /// Radiance on the ground at angle `degrees` from the axis.
+    fn at_angle(light: &SpotLight, degrees: crate::Float) -> crate::Float {
+        let p = Point3::new(degrees.to_radians().tan(), 0.0, 0.0);
+        light.sample(p).map_or(0.0, |sample| {
+            sample.radiance.x * sample.distance * sample.distance / 3.0
+        })
+    }
+
+    #[test]
+    fn smooth_falloff() {
+        let light = spot();
+        assert!(light.is_delta());
+        assert!((at_angle(&light, 0.0) - 1.0).abs() < TOLERANCE);
+        assert!((at_angle(&light, 19.0) - 1.0).abs() < TOLERANCE);
+        assert_eq!(at_angle(&light, 41.0), 0.0);
+        assert!(light.sample(Point3::new(0.0, 2.0, 0.0)).is_none());
+
+        // smoothstep over the cosines between the two angles
+        let (start, end) = (
+            (20.0 as crate::Float).to_radians().cos(),
+            (40.0 as crate::Float).to_radians().cos(),
+        );
+        let mut previous = 1.0;
+        for degrees in [22.0, 25.0, 30.0, 35.0, 38.0] {
+            let t = ((degrees as crate::Float).to_radians().cos() - end) / (start - end);
+            let expected = t * t * (3.0 - 2.0 * t);
+            let falloff = at_angle(&light, degrees);
+            assert!(
+                (falloff - expected).abs() < TOLERANCE,
+                "{} != {}",
+                falloff,
+                expected
+            );
+            assert!(falloff < previous);
+            previous = falloff;
+        }
+
+        let p = Point3::new(0.0, -1.0, 0.0);
+        let sample = light.sample(p).unwrap();
+        assert_eq!(sample.pdf, 1.0);
+        assert_eq!(light.pdf(p, sample.direction), 0.0);
+    }
+}
@@ -11,12 +11,19 @@
 mod camera;
 mod cli;
 mod color;
+mod distribution;
+mod environment;
 mod hittable;
+mod light;
 mod material;
+mod onb;
 mod perlin;
+mod polynomial;
 mod prelude;
 mod ray;
 mod scene;
+mod sdf;
+mod spectrum;
 mod texture;
 mod types;
 mod utils;
@@ -31,23 +38,201 @@ use rayon::iter::{IntoParallelIterator, ParallelIterator};
 use types::{Float, Point3, Vec3};
 
 use camera::Camera;
-use hittable::Hittable;
+use environment::{Environment, ImageEnvironment, Sky};
+use hittable::{HitRecord, Hittable};
+use light::{LightBvh, LightList};
 
-#[instrument(level = "trace", skip(r, background, world))]
-fn ray_color(r: &Ray, background: Color, world: &dyn Hittable, depth: u32) -> Color {
+/// Most scattering events of a random walk through a subsurface interior
+/// before the path is dropped, they do not count towards the depth.
+const MAX_WALK_STEPS: u32 = 256;
+
+/// Light from the environment reaching `rec` directly, weighted against the
+/// same light found by `scatter` with multiple importance sampling.
+fn sample_environment(
+    r: &Ray,
+    rec: &HitRecord,
+    environment: &dyn Environment,
+    world: &dyn Hittable,
+) -> Color {
+    let (direction, radiance, light_pdf) = match environment.sample() {
+        Some(sample) => sample,
+        None => return Color::splat(0.0),
+    };
+    let (f, scatter_pdf) = match rec.material.eval(r, rec, direction) {
+        Some(eval) => eval,
+        None => return Color::splat(0.0),
+    };
+    if f == Color::splat(0.0) {
+        return Color::splat(0.0);
+    }
+    let shadow = Ray::new_with_time(rec.p, direction, r.time());
+    if world.hit(&shadow, 0.001, crate::Float::INFINITY).is_some() {
+        return Color::splat(0.0);
+    }
+    f * radiance * utils::power_heuristic(light_pdf, scatter_pdf) / light_pdf
+}
+
+/// Light from one of the `lights` reaching `rec`, unless something is in
+/// the way. Light from area lights is weighted against the same light found
+/// by `scatter` with multiple importance sampling.
+fn sample_lights(r: &Ray, rec: &HitRecord, lights: &LightBvh, world: &dyn Hittable) -> Color {
+    let (light, choice_pdf) = match lights.choose(rec.p, rec.normal) {
+        Some(choice) => choice,
+        None => return Color::splat(0.0),
+    };
+    let sample = match light.sample(rec.p) {
+        Some(sample) => sample,
+        None => return Color::splat(0.0),
+    };
+    let (f, scatter_pdf) = match rec.material.eval(r, rec, sample.direction) {
+        Some(eval) => eval,
+        None => return Color::splat(0.0),
+    };
+    if f == Color::splat(0.0) {
+        return Color::splat(0.0);
+    }
+    let shadow = Ray::new_with_time(rec.p, sample.direction, r.time());
+    // stop short of the light itself
+    let t_max = sample.distance * (1.0 - 1e-4);
+    if world.hit(&shadow, 0.001, t_max).is_some() {
+        return Color::splat(0.0);
+    }
+    let light_pdf = sample.pdf * choice_pdf;
+    let weight = if light.is_delta() {
+        1.0
+    } else {
+        utils::power_heuristic(light_pdf, scatter_pdf)
+    };
+    f * sample.radiance * weight / light_pdf
+}
+
+/// The surface a ray scattered from, when it also sampled lights directly.
+#[derive(Clone, Copy, Debug)]
+struct Bounce {
+    p: Point3,
+    normal: Vec3,
+    /// Density of the surface scattering along the ray
+    pdf: crate::Float,
+}
+
+/// * `bounce` - The previous surface, when it also sampled the environment
+///   and lights directly
+#[instrument(level = "trace", skip(r, environment, lights, world))]
+fn ray_color(
+    r: &Ray,
+    environment: &dyn Environment,
+    lights: &LightBvh,
+    world: &dyn Hittable,
+    depth: u32,
+    bounce: Option<Bounce>,
+) -> Color {
     if depth == 0 {
         return Color::splat(0.0);
     }
-    if let Some(rec) = world.hit(r, 0.001, crate::Float::INFINITY) {
-        let emitted = rec.material.emitted(rec.u, rec.v, rec.p);
-        if let Some(scatter) = rec.material.scatter(r, &rec) {
-            return emitted
-                + scatter.attenuation * ray_color(&scatter.ray, background, world, depth - 1);
+
+    // walk through a scattering interior up to the next surface
+    let mut r = *r;
+    let mut bounce = bounce;
+    let mut transmittance = Color::splat(1.0);
+    let mut steps = 0;
+    let hit = loop {
+        let hit = world.hit(&r, 0.001, crate::Float::INFINITY);
+        let distance = hit
+            .as_ref()
+            .map_or(crate::Float::INFINITY, |rec| rec.t * r.direction().length());
+        match r.interiors.sample_medium(distance) {
+            material::MediumEvent::Pass { weight } => {
+                transmittance *= weight;
+                break hit;
+            }
+            material::MediumEvent::Scatter { distance, weight } => {
+                steps += 1;
+                if steps > MAX_WALK_STEPS {
+                    return Color::splat(0.0);
+                }
+                transmittance *= weight;
+                bounce = None;
+                let p = r.origin() + distance * r.direction().normalize();
+                let mut scattered = Ray::new_with_time(p, utils::rand_vec3_unit(), r.time());
+                scattered.interiors = r.interiors;
+                scattered.wavelength = r.wavelength;
+                r = scattered;
+            }
+        }
+    };
+    let r = &r;
+
+    if let Some(mut rec) = hit {
+        rec.set_footprint(r);
+        if r.from_camera && !rec.material.visible_to_camera() {
+            let mut passed = r.with_cone_of(r, rec.t);
+            passed.orig = rec.p;
+            return transmittance * ray_color(&passed, environment, lights, world, depth, bounce);
+        }
+
+        let id = material::material_id(rec.material.as_ref());
+        let interior = rec.material.interior();
+        let crossed = |interiors: &material::InteriorStack, interior: material::Interior| {
+            if rec.front_face {
+                interiors.entered(id, interior)
+            } else {
+                interiors.exited(id)
+            }
+        };
+
+        if let Some(interior) = interior {
+            if !r.interiors.is_true_intersection(id, &interior) {
+                // surface hidden inside a higher priority dielectric, go straight through
+                let mut passed =
+                    Ray::new_with_time(rec.p, r.direction(), r.time()).with_cone_of(r, rec.t);
+                passed.interiors = crossed(&r.interiors, interior);
+                passed.wavelength = r.wavelength;
+                passed.from_camera = r.from_camera;
+                return transmittance
+                    * ray_color(&passed, environment, lights, world, depth - 1, bounce);
+            }
+        }
+
+        let mut emitted = rec.material.emitted(r, &rec);
+        if let (Some(light), Some(bounce)) = (rec.light, bounce) {
+            let light_pdf = lights.pdf(bounce.p, bounce.normal, light, r.direction());
+            emitted *= utils::power_heuristic(bounce.pdf, light_pdf);
+        }
+        // sampled whether or not scatter finds a direction, paths it drops
+        // still see the lights
+        let direct =
+            sample_environment(r, &rec, environment, world) + sample_lights(r, &rec, lights, world);
+        if let Some(mut scatter) = rec.material.scatter(r, &rec) {
+            scatter.ray.interiors = match interior {
+                Some(interior) if scatter.ray.direction().dot(rec.normal) < 0.0 => {
+                    crossed(&r.interiors, interior)
+                }
+                _ => r.interiors,
+            };
+            scatter.ray.wavelength = scatter.ray.wavelength.or(r.wavelength);
+            scatter.ray = scatter.ray.with_cone_of(r, rec.t);
+            let bounce = rec
+                .material
+                .eval(r, &rec, scatter.ray.direction())
+                .map(|(_, pdf)| Bounce {
+                    p: rec.p,
+                    normal: rec.normal,
+                    pdf,
+                });
+            return transmittance
+                * (emitted
+                    + direct
+                    + scatter.attenuation
+                        * ray_color(&scatter.ray, environment, lights, world, depth - 1, bounce));
         } else {
-            return emitted;
+            return transmittance * (emitted + direct);
         }
     } else {
-        return background;
+        let direction = r.direction();
+        let weight = bounce.map_or(1.0, |bounce| {
+            utils::power_heuristic(bounce.pdf, environment.pdf(direction))
+        });
+        return transmittance * weight * environment.radiance(direction);
     }
 }
 
@@ -98,55 +283,60 @@ fn main() {
 
     // World
     let hittable_list;
+    let mut lights = LightList::new();
     let lookfrom;
     let lookat;
     let mut vfov = 40.0;
     let mut aperture = 0.0;
-    let mut background = Color::splat(0.0);
+    let mut background: Box<dyn Environment> = Box::new(Color::splat(0.0));
 
     match scene {
         1 => {
             hittable_list = scene::random_scene();
-            background = Color::new(0.7, 0.8, 1.0);
+            background = Box::new(Sky::new(Vec3::new(1.0, 0.8, 0.6), 3.0));
             lookfrom = Point3::new(13.0, 2.0, 3.0);
             lookat = Point3::splat(0.0);
             vfov = 20.0;
         }
         2 => {
             hittable_list = scene::two_spheres();
-            background = Color::new(0.7, 0.8, 1.0);
+            background = Box::new(Color::new(0.7, 0.8, 1.0));
             lookfrom = Point3::new(13.0, 2.0, 3.0);
             lookat = Point3::splat(0.0);
             vfov = 20.0;
         }
         3 => {
             hittable_list = scene::two_perlin_spheres();
-            background = Color::new(0.7, 0.8, 1.0);
+            background = Box::new(Color::new(0.7, 0.8, 1.0));
             lookfrom = Point3::new(13.0, 2.0, 3.0);
             lookat = Point3::splat(0.0);
             vfov = 20.0;
         }
         4 => {
             hittable_list = scene::earth();
-            background = Color::new(0.7, 0.8, 1.0);
+            background = Box::new(Color::new(0.7, 0.8, 1.0));
             lookfrom = Point3::new(13.0, 2.0, 3.0);
             lookat = Point3::splat(0.0);
             vfov = 20.0;
         }
         5 => {
-            hittable_list = scene::simple_light();
+            let (objects, scene_lights) = scene::simple_light();
+            hittable_list = objects;
+            lights = scene_lights;
             samples_per_pixel = 400;
-            background = Color::splat(0.0);
+            background = Box::new(Color::splat(0.0));
             lookfrom = Point3::new(26.0, 3.0, 6.0);
             lookat = Point3::new(0.0, 2.0, 0.0);
             vfov = 20.0;
         }
         6 => {
-            hittable_list = scene::cornell_box();
+            let (objects, scene_lights) = scene::cornell_box();
+            hittable_list = objects;
+            lights = scene_lights;
             aspect_ratio = 1.0;
             image_width = 600;
             samples_per_pixel = 400;
-            background = Color::splat(0.0);
+            background = Box::new(Color::splat(0.0));
             lookfrom = Point3::new(278.0, 278.0, -800.0);
             lookat = Point3::new(278.0, 278.0, 0.0);
             vfov = 40.0;
@@ -156,17 +346,37 @@ fn main() {
             aspect_ratio = 1.0;
             image_width = 600;
             samples_per_pixel = 200;
-            background = Color::splat(0.0);
+            background = Box::new(Color::splat(0.0));
             lookfrom = Point3::new(278.0, 278.0, -800.0);
             lookat = Point3::new(278.0, 278.0, 0.0);
             vfov = 40.0;
         }
+        9 => {
+            let (objects, scene_lights) = scene::delta_lights();
+            hittable_list = objects;
+            lights = scene_lights;
+            samples_per_pixel = 100;
+            lookfrom = Point3::new(26.0, 3.0, 6.0);
+            lookat = Point3::new(0.0, 2.0, 0.0);
+            vfov = 20.0;
+        }
+        10 => {
+            let (objects, scene_lights) = scene::area_lights();
+            hittable_list = objects;
+            lights = scene_lights;
+            samples_per_pixel = 100;
+            lookfrom = Point3::new(26.0, 3.0, 6.0);
+            lookat = Point3::new(0.0, 2.0, 0.0);
+            vfov = 20.0;
+        }
         8 | _ => {
-            hittable_list = scene::final_scene();
+            let (objects, scene_lights) = scene::final_scene();
+            hittable_list = objects;
+            lights = scene_lights;
             aspect_ratio = 1.0;
             image_width = 800;
             samples_per_pixel = 10000;
-            background = Color::splat(0.0);
+            background = Box::new(Color::splat(0.0));
             lookfrom = Point3::new(478.0, 278.0, -600.0);
             lookat = Point3::new(278.0, 278.0, 0.0);
             vfov = 40.0;
@@ -185,12 +395,28 @@ fn main() {
 
     let image_height = (image_width as crate::Float / aspect_ratio) as u32;
     let bvh = hittable_list.build_bvh(0.0, 1.0);
+    let lights = lights.build_bvh();
 
     let world: Box<dyn Hittable> = match use_bvh {
         true => Box::new(bvh),
         false => Box::new(hittable_list),
     };
 
+    let environment: Box<dyn Environment> = match matches.get_one::<String>("environment") {
+        Some(path) => Box::new(ImageEnvironment::new_with_transform(
+            path,
+            matches
+                .get_one::<crate::Float>("environment rotation")
+                .copied()
+                .unwrap_or(0.0),
+            matches
+                .get_one::<crate::Float>("environment intensity")
+                .copied()
+                .unwrap_or(1.0),
+        )),
+        None => background,
+    };
+
     // Camera
     let vup = Vec3::new(0.0, 1.0, 0.0);
     let dist_to_focus = 10.0;
@@ -204,7 +430,8 @@ fn main() {
         dist_to_focus,
         0.0,
         1.0,
-    );
+    )
+    .with_image_height(image_height);
 
     // Render
     let start_time = std::time::Instant::now();
@@ -232,7 +459,14 @@ fn main() {
                     let v = (y as crate::Float + utils::gen_float())
                         / (image_height - 1) as crate::Float;
                     let r = cam.get_ray(u, v);
-                    pixel_color += ray_color(&r, background, world.as_ref(), max_depth);
+                    pixel_color += ray_color(
+                        &r,
+                        environment.as_ref(),
+                        &lights,
+                        world.as_ref(),
+                        max_depth,
+                        None,
+                    );
                 }
                 tx.send(((x, y), pixel_color)).unwrap();
             });
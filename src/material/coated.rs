@@ -0,0 +1,206 @@
+use std::sync::Arc;
+
+use crate::color::Color;
+use crate::hittable::HitRecord;
+use crate::onb::Onb;
+use crate::ray::Ray;
+use crate::types::PI;
+use crate::Vec3;
+
+use super::fresnel;
+use super::microfacet::TrowbridgeReitz;
+use super::rough_dielectric;
+use super::{Material, Scatter};
+
+/// Most bounces between the coat and the base before the path is dropped.
+const MAX_BOUNCES: usize = 16;
+
+/// Varnish or lacquer, a dielectric layer on top of an opaque base material.
+///
+/// Light is followed through the layer as a random walk: refracted into the
+/// coat, absorbed on the way down, scattered by the base, absorbed on the way
+/// up, and then either leaves or is reflected back down by the coat. The
+/// layer is thin compared to the surface, so the walk stays at the hit point.
+/// As with `Conductor`, rough coats lose some light to multiple scattering
+/// between microfacets, which is not modelled. Light the base transmits is
+/// lost, so a coated surface never has an interior, even over glass.
+pub struct CoatedMaterial {
+    base: Arc<dyn Material>,
+    ir: crate::Float,
+    distribution: TrowbridgeReitz,
+    thickness: crate::Float,
+    absorption: Color,
+}
+
+impl CoatedMaterial {
+    /// * `ir` - Index of refraction of the coat
+    /// * `roughness` - Perceptual roughness of the coat, 0 gives a mirror finish
+    pub fn new(base: Arc<dyn Material>, ir: crate::Float, roughness: crate::Float) -> Self {
+        Self::new_with_absorption(base, ir, roughness, 0.0, Color::splat(0.0))
+    }
+
+    /// Tinted coat absorbing `absorption` per unit distance over `thickness`.
+    pub fn new_with_absorption(
+        base: Arc<dyn Material>,
+        ir: crate::Float,
+        roughness: crate::Float,
+        thickness: crate::Float,
+        absorption: Color,
+    ) -> Self {
+        Self {
+            base,
+            ir,
+            distribution: TrowbridgeReitz::from_roughness(roughness, roughness),
+            thickness,
+            absorption,
+        }
+    }
+
+    /// Transmittance of the coat for a direction inside it.
+    fn transmittance(&self, w: Vec3) -> Color {
+        if self.thickness == 0.0 {
+            return Color::splat(1.0);
+        }
+        let distance = self.thickness / w.z.abs().max(1e-4);
+        (-self.absorption * distance).exp()
+    }
+}
+
+/// Mirror a local direction to the frame seen from below the coat.
+fn flip(w: Vec3) -> Vec3 {
+    Vec3::new(w.x, w.y, -w.z)
+}
+
+impl Material for CoatedMaterial {
+    fn scatter(&self, r: &Ray, rec: &HitRecord) -> Option<Scatter> {
+        let frame = Onb::new_from_w(rec.normal);
+        let wo = frame.to_local(-r.direction().normalize());
+
+        let (mut w, mut throughput) = rough_dielectric::sample(&self.distribution, self.ir, wo)?;
+        if w.z > 0.0 {
+            // reflected by the coat
+            return Some(Scatter {
+                attenuation: Color::splat(throughput),
+                ray: Ray::new_with_time(rec.p, frame.to_world(w), r.time()),
+            });
+        }
+        let mut throughput = Color::splat(throughput);
+
+        for _ in 0..MAX_BOUNCES {
+            // down through the coat onto the base
+            throughput *= self.transmittance(w);
+            let mut incoming = Ray::new_with_time(rec.p, frame.to_world(w), r.time());
+            incoming.wavelength = r.wavelength;
+            let scatter = self.base.scatter(&incoming, rec)?;
+            let wb = frame.to_local(scatter.ray.direction().normalize());
+            if wb.z <= 0.0 {
+                return None;
+            }
+            throughput *= scatter.attenuation * self.transmittance(wb);
+
+            // up to the coat, seen from inside
+            let (wi, weight) =
+                rough_dielectric::sample(&self.distribution, 1.0 / self.ir, flip(-wb))?;
+            throughput *= weight;
+            w = flip(wi);
+            if w.z > 0.0 {
+                return Some(Scatter {
+                    attenuation: throughput,
+                    ray: Ray::new_with_time(rec.p, frame.to_world(w), r.time()),
+                });
+            }
+        }
+        None
+    }
+
+    fn emitted(&self, r: &Ray, rec: &HitRecord) -> Color {
+        self.base.emitted(r, rec)
+    }
+
+    fn visible_to_camera(&self) -> bool {
+        self.base.visible_to_camera()
+    }
+
+    /// f is estimated by one random walk as in `scatter`, with the way out
+    /// towards `direction` evaluated rather than sampled. The density is
+    /// approximated by the coat reflection and a cosine lobe for the rest.
+    fn eval(&self, r: &Ray, rec: &HitRecord, direction: Vec3) -> Option<(Color, crate::Float)> {
+        if self.distribution.effectively_smooth() {
+            // the mirror finish is only found by scatter
+            return None;
+        }
+        let frame = Onb::new_from_w(rec.normal);
+        let wo = frame.to_local(-r.direction().normalize());
+        let wi = frame.to_local(direction.normalize());
+        if wo.z <= 0.0 || wi.z <= 0.0 {
+            return Some((Color::splat(0.0), 0.0));
+        }
+
+        let (reflected, reflected_pdf) =
+            rough_dielectric::eval(&self.distribution, self.ir, wo, wi);
+        let mut f = Color::splat(reflected);
+        let pdf = reflected_pdf + (1.0 - fresnel::dielectric(wo.z, self.ir)) * wi.z / PI;
+
+        let (mut w, throughput) = match rough_dielectric::sample(&self.distribution, self.ir, wo) {
+            Some((w, throughput)) if w.z < 0.0 => (w, throughput),
+            _ => return Some((f, pdf)),
+        };
+        let mut throughput = Color::splat(throughput);
+        for _ in 0..MAX_BOUNCES {
+            throughput *= self.transmittance(w);
+            let mut incoming = Ray::new_with_time(rec.p, frame.to_world(w), r.time());
+            incoming.wavelength = r.wavelength;
+            let scatter = match self.base.scatter(&incoming, rec) {
+                Some(scatter) => scatter,
+                None => break,
+            };
+            let wb = frame.to_local(scatter.ray.direction().normalize());
+            if wb.z <= 0.0 {
+                break;
+            }
+            throughput *= scatter.attenuation * self.transmittance(wb);
+
+            // out through the coat towards wi
+            let (exit, _) =
+                rough_dielectric::eval(&self.distribution, 1.0 / self.ir, flip(-wb), flip(wi));
+            f += throughput * exit;
+
+            // or back down, paths leaving elsewhere are done
+            match rough_dielectric::sample(&self.distribution, 1.0 / self.ir, flip(-wb)) {
+                Some((next, weight)) if next.z > 0.0 => {
+                    throughput *= weight;
+                    w = flip(next);
+                }
+                _ => break,
+            }
+        }
+        Some((f, pdf))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material::tests::assert_eval_reflects_as_scatter;
+    use crate::material::{Dielectric, Lambertian};
+
+    #[test]
+    fn eval_reflects_as_scatter() {
+        let base = Arc::new(Lambertian::new_with_color(Color::new(0.8, 0.2, 0.1)));
+        let varnish = Arc::new(CoatedMaterial::new_with_absorption(
+            base,
+            1.5,
+            0.5,
+            0.2,
+            Color::new(0.0, 0.5, 1.0),
+        ));
+        assert_eval_reflects_as_scatter(varnish.clone(), Vec3::new(0.0, 1.0, 0.3));
+        assert_eval_reflects_as_scatter(varnish, Vec3::new(1.0, 0.3, 0.0));
+    }
+
+    #[test]
+    fn opaque_over_glass() {
+        let coated = CoatedMaterial::new(Arc::new(Dielectric::new(1.5)), 1.5, 0.0);
+        assert!(coated.interior().is_none());
+    }
+}
@@ -0,0 +1,198 @@
+use crate::color::Color;
+use crate::hittable::HitRecord;
+use crate::onb::Onb;
+use crate::ray::{self, Ray};
+use crate::Vec3;
+use crate::{spectrum, utils};
+
+use super::fresnel;
+use super::microfacet::TrowbridgeReitz;
+use super::{Material, Scatter, ThinFilm};
+
+#[derive(Clone, Copy)]
+enum ConductorFresnel {
+    /// complex index of refraction `eta + i k`, per channel
+    Complex { eta: Color, k: Color },
+    /// reflectance at normal incidence
+    Schlick(Color),
+}
+
+/// Rough metal, GGX distribution with visible normal sampling.
+///
+/// Only single scattering is modelled, light reflected below the surface
+/// by a microfacet is lost, so very rough conductors get a little darker.
+pub struct Conductor {
+    fresnel: ConductorFresnel,
+    distribution: TrowbridgeReitz,
+    thin_film: Option<ThinFilm>,
+}
+
+impl Conductor {
+    /// * `roughness_x`, `roughness_y` - Perceptual roughness along `dpdu` and
+    ///   across it
+    pub fn new(eta: Color, k: Color, roughness_x: crate::Float, roughness_y: crate::Float) -> Self {
+        Self {
+            fresnel: ConductorFresnel::Complex { eta, k },
+            distribution: TrowbridgeReitz::from_roughness(roughness_x, roughness_y),
+            thin_film: None,
+        }
+    }
+
+    /// Artist friendly variant taking the color at normal incidence.
+    pub fn new_with_color(base_color: Color, roughness: crate::Float) -> Self {
+        Self {
+            fresnel: ConductorFresnel::Schlick(base_color),
+            distribution: TrowbridgeReitz::from_roughness(roughness, roughness),
+            thin_film: None,
+        }
+    }
+
+    pub fn gold(roughness: crate::Float) -> Self {
+        Self::new(
+            Color::new(0.143, 0.374, 1.442),
+            Color::new(3.983, 2.385, 1.603),
+            roughness,
+            roughness,
+        )
+    }
+
+    pub fn copper(roughness: crate::Float) -> Self {
+        Self::new(
+            Color::new(0.200, 0.924, 1.102),
+            Color::new(3.912, 2.452, 2.142),
+            roughness,
+            roughness,
+        )
+    }
+
+    pub fn aluminium(roughness: crate::Float) -> Self {
+        Self::new(
+            Color::new(1.657, 0.880, 0.521),
+            Color::new(9.224, 6.270, 4.837),
+            roughness,
+            roughness,
+        )
+    }
+
+    pub fn silver(roughness: crate::Float) -> Self {
+        Self::new(
+            Color::new(0.155, 0.117, 0.138),
+            Color::new(4.828, 3.122, 2.147),
+            roughness,
+            roughness,
+        )
+    }
+
+    /// Adds an oxide or lacquer film, e.g. the colors of tempered steel.
+    pub fn with_thin_film(self, thin_film: ThinFilm) -> Self {
+        Self {
+            thin_film: Some(thin_film),
+            ..self
+        }
+    }
+
+    /// Complex index of refraction at a wavelength. For the Schlick form it
+    /// is the real index giving the same reflectance at normal incidence.
+    fn ior_at(&self, wavelength: crate::Float) -> (crate::Float, crate::Float) {
+        match self.fresnel {
+            ConductorFresnel::Complex { eta, k } => (
+                spectrum::rgb_at_wavelength(eta, wavelength),
+                spectrum::rgb_at_wavelength(k, wavelength),
+            ),
+            ConductorFresnel::Schlick(f0) => {
+                let f0 = spectrum::rgb_at_wavelength(f0, wavelength)
+                    .clamp(0.0, 0.999)
+                    .sqrt();
+                ((1.0 + f0) / (1.0 - f0), 0.0)
+            }
+        }
+    }
+
+    fn fresnel(&self, cos_theta: crate::Float, r: &Ray, rec: &HitRecord) -> Color {
+        if let Some(thin_film) = &self.thin_film {
+            return thin_film.reflectance(rec, cos_theta, r.wavelength, |wavelength| {
+                let (eta, k) = self.ior_at(wavelength);
+                (r.interiors.ir(Some(wavelength)), eta, k)
+            });
+        }
+        match self.fresnel {
+            ConductorFresnel::Complex { eta, k } => fresnel::conductor(cos_theta, eta, k),
+            ConductorFresnel::Schlick(f0) => fresnel::schlick(cos_theta, f0),
+        }
+    }
+}
+
+impl Material for Conductor {
+    fn scatter(&self, r: &Ray, rec: &HitRecord) -> Option<Scatter> {
+        // roughness_x runs along dpdu
+        let frame = Onb::new_from_w_and_u(rec.normal, rec.dpdu);
+        let wo = frame.to_local(-r.direction().normalize());
+        if wo.z <= 0.0 {
+            return None;
+        }
+
+        let (wi, attenuation) = if self.distribution.effectively_smooth() {
+            let wi = Vec3::new(-wo.x, -wo.y, wo.z);
+            (wi, self.fresnel(wo.z, r, rec))
+        } else {
+            let wm = self
+                .distribution
+                .sample_visible_normal(wo, (utils::gen_float(), utils::gen_float()));
+            let wi = ray::reflect(-wo, wm);
+            if wi.z <= 0.0 {
+                return None;
+            }
+            // f cos / pdf with pdf = D_visible / (4 wo.wm)
+            let weight = self.distribution.g(wo, wi) / self.distribution.g1(wo);
+            (wi, self.fresnel(wo.dot(wm), r, rec) * weight)
+        };
+
+        Some(Scatter {
+            attenuation,
+            ray: Ray::new_with_time(rec.p, frame.to_world(wi), r.time()),
+        })
+    }
+
+    fn eval(&self, r: &Ray, rec: &HitRecord, direction: Vec3) -> Option<(Color, crate::Float)> {
+        if self.distribution.effectively_smooth() {
+            return None;
+        }
+        let frame = Onb::new_from_w_and_u(rec.normal, rec.dpdu);
+        let wo = frame.to_local(-r.direction().normalize());
+        let wi = frame.to_local(direction.normalize());
+        if wo.z <= 0.0 || wi.z <= 0.0 {
+            return Some((Color::splat(0.0), 0.0));
+        }
+        let wm = match (wo + wi).try_normalize() {
+            Some(wm) => wm,
+            None => return Some((Color::splat(0.0), 0.0)),
+        };
+        let cos_theta = wo.dot(wm);
+        let f = self.fresnel(cos_theta, r, rec)
+            * (self.distribution.d(wm) * self.distribution.g(wo, wi) / (4.0 * wo.z));
+        let pdf = self.distribution.d_visible(wo, wm) / (4.0 * cos_theta);
+        Some((f, pdf))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material::tests::assert_eval_matches_scatter;
+    use std::sync::Arc;
+
+    #[test]
+    fn eval_matches_scatter() {
+        let gold = Arc::new(Conductor::gold(0.3));
+        assert_eval_matches_scatter(gold, Vec3::new(0.3, 1.0, 0.2), true);
+
+        let anisotropic = Arc::new(Conductor::new(
+            Color::new(0.2, 0.9, 1.1),
+            Color::new(3.9, 2.5, 2.1),
+            0.3,
+            0.7,
+        ));
+        assert_eval_matches_scatter(anisotropic.clone(), Vec3::new(0.3, 1.0, 0.2), true);
+        assert_eval_matches_scatter(anisotropic, Vec3::new(-0.2, 0.4, 1.0), true);
+    }
+}
@@ -0,0 +1,186 @@
+use crate::color::Color;
+use crate::types::PI;
+
+#[derive(Clone, Copy)]
+struct Complex {
+    re: crate::Float,
+    im: crate::Float,
+}
+
+impl Complex {
+    fn new(re: crate::Float, im: crate::Float) -> Self {
+        Self { re, im }
+    }
+
+    fn add(self, other: Self) -> Self {
+        Self::new(self.re + other.re, self.im + other.im)
+    }
+
+    fn sub(self, other: Self) -> Self {
+        Self::new(self.re - other.re, self.im - other.im)
+    }
+
+    fn mul(self, other: Self) -> Self {
+        Self::new(
+            self.re * other.re - self.im * other.im,
+            self.re * other.im + self.im * other.re,
+        )
+    }
+
+    fn div(self, other: Self) -> Self {
+        let scale = 1.0 / other.norm();
+        Self::new(
+            scale * (self.re * other.re + self.im * other.im),
+            scale * (self.im * other.re - self.re * other.im),
+        )
+    }
+
+    fn scale(self, factor: crate::Float) -> Self {
+        Self::new(factor * self.re, factor * self.im)
+    }
+
+    fn exp(self) -> Self {
+        let magnitude = self.re.exp();
+        Self::new(magnitude * self.im.cos(), magnitude * self.im.sin())
+    }
+
+    /// squared magnitude
+    fn norm(self) -> crate::Float {
+        self.re * self.re + self.im * self.im
+    }
+
+    fn sqrt(self) -> Self {
+        let n = self.norm().sqrt();
+        if n == 0.0 {
+            return Self::new(0.0, 0.0);
+        }
+        let t1 = (0.5 * (n + self.re.abs())).sqrt();
+        let t2 = 0.5 * self.im / t1;
+        if self.re >= 0.0 {
+            Self::new(t1, t2)
+        } else {
+            Self::new(t2.abs(), t1.copysign(self.im))
+        }
+    }
+}
+
+/// Unpolarized reflectance of a conductor with complex index of refraction `eta + i k`.
+fn conductor_channel(cos_theta_i: crate::Float, eta: Complex) -> crate::Float {
+    let cos_theta_i = cos_theta_i.clamp(0.0, 1.0);
+    let sin_2_theta_i = 1.0 - cos_theta_i * cos_theta_i;
+    let sin_2_theta_t = Complex::new(sin_2_theta_i, 0.0).div(eta.mul(eta));
+    let cos_theta_t = Complex::new(1.0, 0.0).sub(sin_2_theta_t).sqrt();
+
+    let cos_i = Complex::new(cos_theta_i, 0.0);
+    let eta_cos_i = eta.mul(cos_i);
+    let eta_cos_t = eta.mul(cos_theta_t);
+    let r_parallel = eta_cos_i.sub(cos_theta_t).div(eta_cos_i.add(cos_theta_t));
+    let r_perpendicular = cos_i.sub(eta_cos_t).div(cos_i.add(eta_cos_t));
+    0.5 * (r_parallel.norm() + r_perpendicular.norm())
+}
+
+pub fn conductor(cos_theta_i: crate::Float, eta: Color, k: Color) -> Color {
+    Color::new(
+        conductor_channel(cos_theta_i, Complex::new(eta.x, k.x)),
+        conductor_channel(cos_theta_i, Complex::new(eta.y, k.y)),
+        conductor_channel(cos_theta_i, Complex::new(eta.z, k.z)),
+    )
+}
+
+/// Schlick's approximation from the reflectance at normal incidence.
+pub fn schlick(cos_theta_i: crate::Float, f0: Color) -> Color {
+    f0 + (Color::splat(1.0) - f0) * (1.0 - cos_theta_i.clamp(0.0, 1.0)).powi(5)
+}
+
+/// Unpolarized reflectance of a dielectric interface, `eta` is the index of
+/// refraction on the far side of the normal over the one on the near side.
+/// A negative `cos_theta_i` means the ray arrives from the far side.
+pub fn dielectric(cos_theta_i: crate::Float, eta: crate::Float) -> crate::Float {
+    let mut cos_theta_i = cos_theta_i.clamp(-1.0, 1.0);
+    let mut eta = eta;
+    if cos_theta_i < 0.0 {
+        eta = 1.0 / eta;
+        cos_theta_i = -cos_theta_i;
+    }
+
+    let sin_2_theta_i = 1.0 - cos_theta_i * cos_theta_i;
+    let sin_2_theta_t = sin_2_theta_i / (eta * eta);
+    if sin_2_theta_t >= 1.0 {
+        // total internal reflection
+        return 1.0;
+    }
+    let cos_theta_t = (1.0 - sin_2_theta_t).sqrt();
+
+    let r_parallel = (eta * cos_theta_i - cos_theta_t) / (eta * cos_theta_i + cos_theta_t);
+    let r_perpendicular = (cos_theta_i - eta * cos_theta_t) / (cos_theta_i + eta * cos_theta_t);
+    0.5 * (r_parallel * r_parallel + r_perpendicular * r_perpendicular)
+}
+
+/// Amplitude reflection coefficients, perpendicular and parallel, of the
+/// interface between media `a` and `b`.
+fn amplitudes(
+    eta_a: Complex,
+    cos_a: Complex,
+    eta_b: Complex,
+    cos_b: Complex,
+) -> (Complex, Complex) {
+    let perpendicular = eta_a
+        .mul(cos_a)
+        .sub(eta_b.mul(cos_b))
+        .div(eta_a.mul(cos_a).add(eta_b.mul(cos_b)));
+    let parallel = eta_b
+        .mul(cos_a)
+        .sub(eta_a.mul(cos_b))
+        .div(eta_b.mul(cos_a).add(eta_a.mul(cos_b)));
+    (perpendicular, parallel)
+}
+
+/// Cosine of the refracted angle, imaginary beyond total internal reflection.
+fn refracted_cos(sin_2_theta_i: crate::Float, eta_i: Complex, eta_t: Complex) -> Complex {
+    let ratio = eta_i.div(eta_t);
+    Complex::new(1.0, 0.0)
+        .sub(ratio.mul(ratio).scale(sin_2_theta_i))
+        .sqrt()
+}
+
+/// Unpolarized reflectance of a thin film on a substrate, with the
+/// interference of all internal reflections (Airy summation).
+///
+/// * `outside_ir` - Index of refraction on the side of the incident light
+/// * `thickness` - Of the film, in nanometres like `wavelength`
+/// * `substrate_eta`, `substrate_k` - Complex index of refraction below the
+///   film, `k` is 0 for dielectrics
+pub fn thin_film(
+    cos_theta_i: crate::Float,
+    outside_ir: crate::Float,
+    film_ir: crate::Float,
+    thickness: crate::Float,
+    substrate_eta: crate::Float,
+    substrate_k: crate::Float,
+    wavelength: crate::Float,
+) -> crate::Float {
+    let cos_theta_i = cos_theta_i.clamp(0.0, 1.0);
+    let sin_2_theta_i = 1.0 - cos_theta_i * cos_theta_i;
+    let eta_1 = Complex::new(outside_ir, 0.0);
+    let eta_2 = Complex::new(film_ir, 0.0);
+    let eta_3 = Complex::new(substrate_eta, substrate_k);
+    let cos_1 = Complex::new(cos_theta_i, 0.0);
+    let cos_2 = refracted_cos(sin_2_theta_i, eta_1, eta_2);
+    let cos_3 = refracted_cos(sin_2_theta_i, eta_1, eta_3);
+
+    let (r12_perpendicular, r12_parallel) = amplitudes(eta_1, cos_1, eta_2, cos_2);
+    let (r23_perpendicular, r23_parallel) = amplitudes(eta_2, cos_2, eta_3, cos_3);
+
+    // phase difference of a round trip through the film
+    let phase = Complex::new(0.0, 1.0)
+        .mul(eta_2.mul(cos_2))
+        .scale(4.0 * PI * thickness / wavelength)
+        .exp();
+    let airy = |r12: Complex, r23: Complex| {
+        let delayed = r23.mul(phase);
+        r12.add(delayed)
+            .div(Complex::new(1.0, 0.0).add(r12.mul(delayed)))
+            .norm()
+    };
+    0.5 * (airy(r12_perpendicular, r23_perpendicular) + airy(r12_parallel, r23_parallel))
+}
@@ -0,0 +1,189 @@
+use crate::color::Color;
+use crate::utils;
+
+use super::{Ior, Material};
+
+/// Deepest nesting of dielectrics tracked, further ones are ignored.
+const MAX_INTERIORS: usize = 8;
+
+/// What fills a closed dielectric surface.
+#[derive(Clone, Copy)]
+pub struct Interior {
+    pub ior: Ior,
+    /// Beer-Lambert absorption coefficient per unit distance
+    pub absorption: Color,
+    /// Coefficient of isotropic scattering per unit distance, for
+    /// subsurface scattering
+    pub scattering: Color,
+    /// Where dielectrics overlap, e.g. water poured in a glass, only the
+    /// surfaces of the highest priority interior are seen
+    pub priority: u32,
+}
+
+impl Interior {
+    /// Absorption letting `color` through after travelling `distance`.
+    pub fn absorption_for_color(color: Color, distance: crate::Float) -> Color {
+        -Color::new(color.x.ln(), color.y.ln(), color.z.ln()) / distance
+    }
+}
+
+/// Identifies the material of a surface across hits, as `Interior` values
+/// of distinct objects may be equal. A surface is the material its hits
+/// carry, `rec.material`, the outermost one when materials are wrapped.
+pub fn material_id(material: &dyn Material) -> usize {
+    material as *const dyn Material as *const () as usize
+}
+
+/// What happens to a ray travelling through an interior.
+pub enum MediumEvent {
+    /// Scattered after `distance`, in world units along the ray
+    Scatter {
+        distance: crate::Float,
+        weight: Color,
+    },
+    /// Reached the end of the segment
+    Pass { weight: Color },
+}
+
+#[derive(Clone, Copy)]
+struct Entry {
+    id: usize,
+    interior: Interior,
+}
+
+/// The dielectrics a ray is currently inside, in the order they were entered.
+#[derive(Clone, Copy, Default)]
+pub struct InteriorStack {
+    entries: [Option<Entry>; MAX_INTERIORS],
+    len: usize,
+}
+
+impl InteriorStack {
+    fn iter(&self) -> impl Iterator<Item = &Entry> {
+        self.entries[..self.len].iter().flatten()
+    }
+
+    /// Highest priority entry other than `excluded`, the latest one on ties.
+    fn top_excluding(&self, excluded: Option<usize>) -> Option<&Entry> {
+        self.iter().filter(|entry| Some(entry.id) != excluded).fold(
+            None,
+            |top: Option<&Entry>, entry| match top {
+                Some(top) if top.interior.priority > entry.interior.priority => Some(top),
+                _ => Some(entry),
+            },
+        )
+    }
+
+    /// The interior the ray travels through.
+    pub fn current(&self) -> Option<Interior> {
+        self.top_excluding(None).map(|entry| entry.interior)
+    }
+
+    /// Index of refraction the ray travels through, 1 outside any dielectric.
+    pub fn ir(&self, wavelength: Option<crate::Float>) -> crate::Float {
+        self.current()
+            .map_or(1.0, |interior| interior.ior.at(wavelength))
+    }
+
+    /// Index of refraction on the other side when leaving the surface `id`.
+    pub fn ir_outside(&self, id: usize, wavelength: Option<crate::Float>) -> crate::Float {
+        self.top_excluding(Some(id))
+            .map_or(1.0, |entry| entry.interior.ior.at(wavelength))
+    }
+
+    /// Whether a surface is actually there, or hidden inside a higher priority interior.
+    pub fn is_true_intersection(&self, id: usize, interior: &Interior) -> bool {
+        self.top_excluding(Some(id))
+            .map_or(true, |top| interior.priority >= top.interior.priority)
+    }
+
+    pub fn entered(&self, id: usize, interior: Interior) -> Self {
+        let mut stack = *self;
+        if stack.len < MAX_INTERIORS {
+            stack.entries[stack.len] = Some(Entry { id, interior });
+            stack.len += 1;
+        }
+        stack
+    }
+
+    pub fn exited(&self, id: usize) -> Self {
+        let mut stack = *self;
+        if let Some(index) = self.entries[..self.len]
+            .iter()
+            .rposition(|entry| entry.map_or(false, |entry| entry.id == id))
+        {
+            stack.entries.copy_within(index + 1..self.len, index);
+            stack.len -= 1;
+            stack.entries[stack.len] = None;
+        }
+        stack
+    }
+
+    /// Beer-Lambert attenuation over `distance` in the current interior.
+    pub fn transmittance(&self, distance: crate::Float) -> Color {
+        match self.current() {
+            Some(interior) => transmittance(interior.absorption, distance),
+            None => Color::splat(1.0),
+        }
+    }
+
+    /// Whether a ray scatters in the current interior before travelling
+    /// `distance`, with the weight for the attenuation up to that point.
+    ///
+    /// The distance is sampled from the extinction of one channel picked at
+    /// random, weighted against all three so colored media stay unbiased.
+    pub fn sample_medium(&self, distance: crate::Float) -> MediumEvent {
+        let interior = match self.current() {
+            Some(interior) if interior.scattering != Color::splat(0.0) => interior,
+            _ => {
+                return MediumEvent::Pass {
+                    weight: self.transmittance(distance),
+                }
+            }
+        };
+
+        let extinction = interior.absorption + interior.scattering;
+        let channel = utils::gen_range(0..3);
+        let sampled = if extinction[channel] > 0.0 {
+            -(1.0 - utils::gen_float()).ln() / extinction[channel]
+        } else {
+            crate::Float::INFINITY
+        };
+
+        if sampled < distance {
+            let transmittance = transmittance(extinction, sampled);
+            let pdf = (extinction * transmittance).dot(Color::splat(1.0 / 3.0));
+            MediumEvent::Scatter {
+                distance: sampled,
+                weight: interior.scattering * transmittance / pdf,
+            }
+        } else {
+            let transmittance = transmittance(extinction, distance);
+            let probability = transmittance.dot(Color::splat(1.0 / 3.0));
+            MediumEvent::Pass {
+                weight: if probability > 0.0 {
+                    transmittance / probability
+                } else {
+                    Color::splat(0.0)
+                },
+            }
+        }
+    }
+}
+
+/// Per channel exp(-coefficient * distance)
+fn transmittance(coefficient: Color, distance: crate::Float) -> Color {
+    // written out so a clear channel stays clear over an infinite distance
+    let channel = |coefficient: crate::Float| {
+        if coefficient == 0.0 {
+            1.0
+        } else {
+            (-coefficient * distance).exp()
+        }
+    };
+    Color::new(
+        channel(coefficient.x),
+        channel(coefficient.y),
+        channel(coefficient.z),
+    )
+}
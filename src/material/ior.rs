@@ -0,0 +1,112 @@
+use crate::spectrum;
+
+/// Index of refraction, optionally depending on the wavelength.
+#[derive(Clone, Copy)]
+pub enum Ior {
+    Constant(crate::Float),
+    /// n = a + b / lambda^2, with lambda in micrometres
+    Cauchy {
+        a: crate::Float,
+        b: crate::Float,
+    },
+    /// n^2 = 1 + sum b_i lambda^2 / (lambda^2 - c_i), with lambda in
+    /// micrometres, the form of glass catalog data
+    Sellmeier {
+        b: [crate::Float; 3],
+        c: [crate::Float; 3],
+    },
+}
+
+impl Ior {
+    pub fn cauchy(a: crate::Float, b: crate::Float) -> Self {
+        Self::Cauchy { a, b }
+    }
+
+    pub fn sellmeier(b: [crate::Float; 3], c: [crate::Float; 3]) -> Self {
+        Self::Sellmeier { b, c }
+    }
+
+    /// Schott N-BK7 crown glass, common optical glass with low dispersion
+    pub fn bk7() -> Self {
+        Self::sellmeier(
+            [1.039_612_12, 0.231_792_344, 1.010_469_45],
+            [0.006_000_698_67, 0.020_017_914_4, 103.560_653],
+        )
+    }
+
+    pub fn fused_silica() -> Self {
+        Self::sellmeier(
+            [0.696_166_3, 0.407_942_6, 0.897_479_4],
+            [0.004_679_148_2, 0.013_512_063, 97.934_002_5],
+        )
+    }
+
+    /// Schott SF11 flint glass, strong dispersion for prisms
+    pub fn dense_flint() -> Self {
+        Self::sellmeier(
+            [1.737_596_95, 0.313_747_346, 1.898_781_01],
+            [0.013_188_707, 0.062_306_814_2, 155.236_29],
+        )
+    }
+
+    pub fn is_dispersive(&self) -> bool {
+        !matches!(self, Self::Constant(_))
+    }
+
+    /// At `wavelength` in nanometres, or at the sodium D line for light
+    /// that has not been split.
+    pub fn at(&self, wavelength: Option<crate::Float>) -> crate::Float {
+        let micrometres = wavelength.unwrap_or(spectrum::WAVELENGTH_D_LINE) / 1000.0;
+        let lambda_2 = micrometres * micrometres;
+        match *self {
+            Self::Constant(ir) => ir,
+            Self::Cauchy { a, b } => a + b / lambda_2,
+            Self::Sellmeier { b, c } => (1.0
+                + b.iter()
+                    .zip(c.iter())
+                    .map(|(b, c)| b * lambda_2 / (lambda_2 - c))
+                    .sum::<crate::Float>())
+            .sqrt(),
+        }
+    }
+}
+
+impl From<crate::Float> for Ior {
+    fn from(ir: crate::Float) -> Self {
+        Self::Constant(ir)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normal_dispersion() {
+        for ior in [
+            Ior::cauchy(1.5, 0.0042),
+            Ior::bk7(),
+            Ior::fused_silica(),
+            Ior::dense_flint(),
+        ] {
+            assert!(ior.is_dispersive());
+            // falling from violet to red
+            let mut previous = crate::Float::INFINITY;
+            for wavelength in spectrum::wavelengths(50) {
+                let ir = ior.at(Some(wavelength));
+                assert!(ir > 1.0 && ir < previous);
+                previous = ir;
+            }
+            assert_eq!(ior.at(None), ior.at(Some(spectrum::WAVELENGTH_D_LINE)));
+        }
+
+        // catalog values at the d line
+        assert!((Ior::bk7().at(None) - 1.5168).abs() < 1e-3);
+        assert!((Ior::fused_silica().at(None) - 1.4585).abs() < 1e-3);
+        assert!((Ior::dense_flint().at(None) - 1.7847).abs() < 1e-3);
+
+        let constant = Ior::from(1.33);
+        assert!(!constant.is_dispersive());
+        assert_eq!(constant.at(Some(400.0)), constant.at(Some(700.0)));
+    }
+}
@@ -0,0 +1,383 @@
+use std::path::Path;
+
+use crate::color::{self, Color};
+use crate::distribution::Distribution2D;
+use crate::hittable::HitRecord;
+use crate::onb::Onb;
+use crate::ray::Ray;
+use crate::types::PI;
+use crate::utils;
+use crate::Vec3;
+
+use super::{Material, Scatter};
+
+const THETA_HALF_RESOLUTION: usize = 90;
+const THETA_DIFFERENCE_RESOLUTION: usize = 90;
+const PHI_DIFFERENCE_RESOLUTION: usize = 180;
+const SAMPLES_PER_CHANNEL: usize =
+    THETA_HALF_RESOLUTION * THETA_DIFFERENCE_RESOLUTION * PHI_DIFFERENCE_RESOLUTION;
+/// Factors from the stored values to reflectance per channel.
+const CHANNEL_SCALE: [crate::Float; 3] = [1.0 / 1500.0, 1.15 / 1500.0, 1.66 / 1500.0];
+
+/// Resolution of the sampling tables: bins of the outgoing elevation, and
+/// cells of the incoming elevation and azimuth relative to it.
+const THETA_OUT_BINS: usize = 16;
+const THETA_IN_CELLS: usize = 32;
+const PHI_IN_CELLS: usize = 64;
+/// Share of directions sampled around the normal, keeps the estimator
+/// unbiased where a coarse table cell misses a lobe.
+const COSINE_FRACTION: crate::Float = 0.1;
+
+/// Isotropic BRDF measured by Matusik et al., A Data-Driven Reflectance
+/// Model, from a `.binary` file of the MERL database.
+pub struct Merl {
+    /// Per channel, indexed by theta half, theta difference and phi difference
+    data: Vec<crate::Float>,
+    /// f * cos of the incoming direction by its luminance, per outgoing bin
+    sampling: Vec<Distribution2D>,
+}
+
+impl Merl {
+    /// Panics if the file can't be read or isn't a MERL BRDF.
+    pub fn new<P>(p: P) -> Self
+    where
+        P: AsRef<Path>,
+    {
+        let bytes = std::fs::read(p).unwrap();
+        assert!(bytes.len() >= 12, "MERL BRDF without a header");
+        let dims: Vec<usize> = bytes[..12]
+            .chunks_exact(4)
+            .map(|b| i32::from_le_bytes([b[0], b[1], b[2], b[3]]) as usize)
+            .collect();
+        assert_eq!(
+            dims,
+            [
+                THETA_HALF_RESOLUTION,
+                THETA_DIFFERENCE_RESOLUTION,
+                PHI_DIFFERENCE_RESOLUTION
+            ],
+            "unexpected MERL BRDF resolution"
+        );
+        assert_eq!(
+            bytes.len(),
+            12 + 3 * SAMPLES_PER_CHANNEL * 8,
+            "truncated MERL BRDF"
+        );
+
+        let data = bytes[12..]
+            .chunks_exact(8)
+            .enumerate()
+            .map(|(i, b)| {
+                let value = f64::from_le_bytes([b[0], b[1], b[2], b[3], b[4], b[5], b[6], b[7]]);
+                // missing measurements are stored negative
+                (value as crate::Float * CHANNEL_SCALE[i / SAMPLES_PER_CHANNEL]).max(0.0)
+            })
+            .collect();
+        Self::new_with_data(data)
+    }
+
+    fn new_with_data(data: Vec<crate::Float>) -> Self {
+        let mut merl = Self {
+            data,
+            sampling: Vec::new(),
+        };
+        merl.sampling = (0..THETA_OUT_BINS)
+            .map(|bin| merl.sampling_table(bin))
+            .collect();
+        merl
+    }
+
+    /// Luminance of f * cos theta * sin theta over the (phi, theta) cells of
+    /// the incoming direction, for the outgoing elevation at the bin centre.
+    fn sampling_table(&self, bin: usize) -> Distribution2D {
+        let theta_o = (bin as crate::Float + 0.5) / THETA_OUT_BINS as crate::Float * PI / 2.0;
+        let wo = Vec3::new(theta_o.sin(), 0.0, theta_o.cos());
+        let mut function = Vec::with_capacity(THETA_IN_CELLS * PHI_IN_CELLS);
+        for i in 0..THETA_IN_CELLS {
+            for j in 0..PHI_IN_CELLS {
+                let wi = cell_direction(
+                    (j as crate::Float + 0.5) / PHI_IN_CELLS as crate::Float,
+                    (i as crate::Float + 0.5) / THETA_IN_CELLS as crate::Float,
+                );
+                let f = color::luminance(self.brdf(wo, wi));
+                function.push(f * wi.z * (1.0 - wi.z * wi.z).sqrt());
+            }
+        }
+        Distribution2D::new(&function, PHI_IN_CELLS, THETA_IN_CELLS)
+    }
+
+    /// The measured BRDF, directions in the shading frame.
+    fn brdf(&self, wo: Vec3, wi: Vec3) -> Color {
+        if wo.z <= 0.0 || wi.z <= 0.0 {
+            return Color::splat(0.0);
+        }
+        let (theta_half, theta_difference, phi_difference) = half_difference(wo, wi);
+        let index = phi_difference_index(phi_difference)
+            + PHI_DIFFERENCE_RESOLUTION * theta_difference_index(theta_difference)
+            + PHI_DIFFERENCE_RESOLUTION
+                * THETA_DIFFERENCE_RESOLUTION
+                * theta_half_index(theta_half);
+        Color::new(
+            self.data[index],
+            self.data[index + SAMPLES_PER_CHANNEL],
+            self.data[index + 2 * SAMPLES_PER_CHANNEL],
+        )
+    }
+
+    /// The two sampling tables around the outgoing elevation with the weight
+    /// of the second, and the rotation from the tables' azimuth to the
+    /// shading frame.
+    fn tables(&self, wo: Vec3) -> (&Distribution2D, &Distribution2D, crate::Float, crate::Float) {
+        let theta_o = wo.z.clamp(0.0, 1.0).acos();
+        let x = (theta_o / (PI / 2.0) * THETA_OUT_BINS as crate::Float - 0.5)
+            .clamp(0.0, (THETA_OUT_BINS - 1) as crate::Float);
+        let bin = (x as usize).min(THETA_OUT_BINS - 2);
+        (
+            &self.sampling[bin],
+            &self.sampling[bin + 1],
+            x - bin as crate::Float,
+            wo.y.atan2(wo.x),
+        )
+    }
+
+    fn sample(&self, wo: Vec3) -> Vec3 {
+        if utils::gen_float() < COSINE_FRACTION {
+            return utils::rand_cosine_direction();
+        }
+        let (lower, upper, t, phi_o) = self.tables(wo);
+        let table = if utils::gen_float() < t { upper } else { lower };
+        let ((u, v), _) = table.sample_continuous((utils::gen_float(), utils::gen_float()));
+        rotate_z(cell_direction(u, v), phi_o)
+    }
+
+    /// Density over solid angle of sampling `wi`.
+    fn pdf(&self, wo: Vec3, wi: Vec3) -> crate::Float {
+        if wi.z <= 0.0 {
+            return 0.0;
+        }
+        let (lower, upper, t, phi_o) = self.tables(wo);
+        let phi = (wi.y.atan2(wi.x) - phi_o).rem_euclid(2.0 * PI);
+        let theta = wi.z.min(1.0).acos();
+        let sin_theta = (1.0 - wi.z * wi.z).max(0.0).sqrt();
+        let tabulated = if sin_theta > 0.0 {
+            let uv = (phi / (2.0 * PI), theta / (PI / 2.0));
+            ((1.0 - t) * lower.pdf(uv) + t * upper.pdf(uv)) / (PI * PI * sin_theta)
+        } else {
+            0.0
+        };
+        COSINE_FRACTION * wi.z / PI + (1.0 - COSINE_FRACTION) * tabulated
+    }
+}
+
+/// Incoming direction at (phi, theta) of a sampling table, both in [0, 1].
+fn cell_direction(u: crate::Float, v: crate::Float) -> Vec3 {
+    let phi = u * 2.0 * PI;
+    let theta = v * PI / 2.0;
+    Vec3::new(
+        theta.sin() * phi.cos(),
+        theta.sin() * phi.sin(),
+        theta.cos(),
+    )
+}
+
+fn rotate_z(v: Vec3, angle: crate::Float) -> Vec3 {
+    let (sin, cos) = angle.sin_cos();
+    Vec3::new(v.x * cos - v.y * sin, v.x * sin + v.y * cos, v.z)
+}
+
+fn rotate_y(v: Vec3, angle: crate::Float) -> Vec3 {
+    let (sin, cos) = angle.sin_cos();
+    Vec3::new(v.x * cos + v.z * sin, v.y, -v.x * sin + v.z * cos)
+}
+
+/// Rusinkiewicz's half and difference angles of a pair of directions:
+/// theta half, theta difference and phi difference.
+fn half_difference(wo: Vec3, wi: Vec3) -> (crate::Float, crate::Float, crate::Float) {
+    let half = (wo + wi).normalize();
+    let theta_half = half.z.clamp(-1.0, 1.0).acos();
+    let phi_half = half.y.atan2(half.x);
+    let difference = rotate_y(rotate_z(wi, -phi_half), -theta_half);
+    let theta_difference = difference.z.clamp(-1.0, 1.0).acos();
+    let phi_difference = difference.y.atan2(difference.x);
+    (theta_half, theta_difference, phi_difference)
+}
+
+/// The table is denser near the specular peak.
+fn theta_half_index(theta_half: crate::Float) -> usize {
+    if theta_half <= 0.0 {
+        return 0;
+    }
+    let index = (theta_half / (PI / 2.0)).sqrt() * THETA_HALF_RESOLUTION as crate::Float;
+    (index as usize).min(THETA_HALF_RESOLUTION - 1)
+}
+
+fn theta_difference_index(theta_difference: crate::Float) -> usize {
+    let index = theta_difference / (PI / 2.0) * THETA_DIFFERENCE_RESOLUTION as crate::Float;
+    (index.max(0.0) as usize).min(THETA_DIFFERENCE_RESOLUTION - 1)
+}
+
+/// Reciprocity makes phi difference and phi difference + pi equal, only
+/// half the circle is stored.
+fn phi_difference_index(phi_difference: crate::Float) -> usize {
+    let phi_difference = if phi_difference < 0.0 {
+        phi_difference + PI
+    } else {
+        phi_difference
+    };
+    let index = phi_difference / PI * PHI_DIFFERENCE_RESOLUTION as crate::Float;
+    (index.max(0.0) as usize).min(PHI_DIFFERENCE_RESOLUTION - 1)
+}
+
+impl Material for Merl {
+    fn scatter(&self, r: &Ray, rec: &HitRecord) -> Option<Scatter> {
+        let frame = Onb::new_from_w(rec.normal);
+        let wo = frame.to_local(-r.direction().normalize());
+        if wo.z <= 0.0 {
+            return None;
+        }
+
+        let direction = frame.to_world(self.sample(wo));
+        // the direction as `eval` sees it, rounding may move it across a
+        // cell of the tables
+        let wi = frame.to_local(direction.normalize());
+        let pdf = self.pdf(wo, wi);
+        if wi.z <= 0.0 || pdf <= 0.0 {
+            return None;
+        }
+
+        Some(Scatter {
+            attenuation: self.brdf(wo, wi) * wi.z / pdf,
+            ray: Ray::new_with_time(rec.p, direction, r.time()),
+        })
+    }
+
+    fn eval(&self, r: &Ray, rec: &HitRecord, direction: Vec3) -> Option<(Color, crate::Float)> {
+        let frame = Onb::new_from_w(rec.normal);
+        let wo = frame.to_local(-r.direction().normalize());
+        let wi = frame.to_local(direction.normalize());
+        Some((self.brdf(wo, wi) * wi.z.max(0.0), self.pdf(wo, wi)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material::tests::assert_eval_matches_scatter;
+    use crate::material::Lambertian;
+    use std::sync::Arc;
+
+    const TOLERANCE: crate::Float = 1e3 * crate::Float::EPSILON;
+
+    /// Every measurement of each channel set to `f`.
+    fn constant(f: Color) -> Merl {
+        let data = (0..3)
+            .flat_map(|channel| std::iter::repeat(f[channel]).take(SAMPLES_PER_CHANNEL))
+            .collect();
+        Merl::new_with_data(data)
+    }
+
+    fn direction(theta: crate::Float, phi: crate::Float) -> Vec3 {
+        Vec3::new(
+            theta.sin() * phi.cos(),
+            theta.sin() * phi.sin(),
+            theta.cos(),
+        )
+    }
+
+    #[test]
+    fn table_indices() {
+        for k in [0, 1, 10, 45, 89] {
+            let centre = (k as crate::Float + 0.5) / 90.0;
+            // theta half is stored by the square root of the angle
+            assert_eq!(theta_half_index(centre * centre * PI / 2.0), k);
+            assert_eq!(theta_difference_index(centre * PI / 2.0), k);
+        }
+        assert_eq!(theta_half_index(0.0), 0);
+        assert_eq!(theta_half_index(PI / 2.0), THETA_HALF_RESOLUTION - 1);
+        assert_eq!(
+            theta_difference_index(PI / 2.0),
+            THETA_DIFFERENCE_RESOLUTION - 1
+        );
+
+        for k in [0, 1, 90, 179] {
+            let phi = (k as crate::Float + 0.5) / 180.0 * PI;
+            assert_eq!(phi_difference_index(phi), k);
+            // the other half of the circle folds onto the same entries
+            assert_eq!(phi_difference_index(phi - PI), k);
+        }
+    }
+
+    #[test]
+    fn half_and_difference_angles() {
+        // angles from acos near 0 only have half the digits
+        let angle_tolerance = 10.0 * crate::Float::EPSILON.sqrt();
+        // mirror directions have the half vector on the normal
+        let theta = 0.6;
+        let (theta_half, theta_difference, _) =
+            half_difference(direction(theta, 0.4), direction(theta, 0.4 + PI));
+        assert!(theta_half.abs() < angle_tolerance);
+        assert!((theta_difference - theta).abs() < angle_tolerance);
+
+        // equal directions have no difference
+        let (theta_half, theta_difference, _) =
+            half_difference(direction(theta, 1.0), direction(theta, 1.0));
+        assert!((theta_half - theta).abs() < TOLERANCE);
+        assert!(theta_difference.abs() < angle_tolerance);
+    }
+
+    #[test]
+    fn reciprocity() {
+        // distinct values everywhere, so a wrong fold of phi difference
+        // reads another entry
+        let data = (0..3 * SAMPLES_PER_CHANNEL)
+            .map(|i| ((i * 7919) % 1000) as crate::Float / 1000.0)
+            .collect();
+        let merl = Merl::new_with_data(data);
+        for _ in 0..1000 {
+            let wo = utils::rand_cosine_direction();
+            let wi = utils::rand_cosine_direction();
+            assert_eq!(merl.brdf(wo, wi), merl.brdf(wi, wo));
+        }
+    }
+
+    #[test]
+    fn constant_albedo() {
+        let albedo = Color::new(0.8, 0.5, 0.2);
+        let merl = constant(albedo / PI);
+        let r = Ray::new(Vec3::new(0.3, 1.0, 0.0), Vec3::new(-0.3, -1.0, 0.0));
+        let material: Arc<dyn Material> = Arc::new(Lambertian::new_with_color(albedo));
+        let mut rec = HitRecord::new(&Vec3::ZERO, &Vec3::Y, 1.0, &material);
+        rec.front_face = true;
+
+        const SAMPLES: usize = 100_000;
+        let mut sum = Color::splat(0.0);
+        for _ in 0..SAMPLES {
+            sum += merl
+                .scatter(&r, &rec)
+                .map_or(Color::splat(0.0), |s| s.attenuation);
+        }
+        let mean = sum / SAMPLES as crate::Float;
+        assert!((mean - albedo).abs().max_element() < 0.01, "{}", mean);
+    }
+
+    #[test]
+    fn eval_matches_scatter() {
+        assert_eval_matches_scatter(
+            Arc::new(constant(Color::new(0.8, 0.5, 0.2) / PI)),
+            Vec3::new(0.3, 1.0, 0.2),
+            true,
+        );
+
+        // glossy, bright where the half vector is near the normal
+        let data = (0..3 * SAMPLES_PER_CHANNEL)
+            .map(|i| {
+                let theta_half = i % SAMPLES_PER_CHANNEL
+                    / (PHI_DIFFERENCE_RESOLUTION * THETA_DIFFERENCE_RESOLUTION);
+                0.05 + 20.0 * (-(theta_half as crate::Float) / 4.0).exp()
+            })
+            .collect();
+        let glossy = Arc::new(Merl::new_with_data(data));
+        assert_eval_matches_scatter(glossy.clone(), Vec3::new(0.3, 1.0, 0.2), true);
+        assert_eval_matches_scatter(glossy, Vec3::new(1.0, 0.2, 0.0), true);
+    }
+}
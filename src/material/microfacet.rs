@@ -0,0 +1,103 @@
+//! Trowbridge-Reitz (GGX) microfacet distribution.
+//!
+//! All directions are in the local shading frame, the macro surface normal is
+//! +z, and point away from the surface.
+
+use crate::types::PI;
+use crate::Vec3;
+
+/// Below this alpha the distribution is treated as a perfect mirror.
+const MIN_ALPHA: crate::Float = 1e-4;
+
+#[derive(Clone, Copy)]
+pub struct TrowbridgeReitz {
+    alpha_x: crate::Float,
+    alpha_y: crate::Float,
+}
+
+impl TrowbridgeReitz {
+    pub fn new(alpha_x: crate::Float, alpha_y: crate::Float) -> Self {
+        Self {
+            alpha_x: alpha_x.max(MIN_ALPHA),
+            alpha_y: alpha_y.max(MIN_ALPHA),
+        }
+    }
+
+    /// Perceptual roughness in [0, 1] along the tangent and bitangent, squared to get alpha.
+    pub fn from_roughness(roughness_x: crate::Float, roughness_y: crate::Float) -> Self {
+        Self::new(
+            roughness_x.clamp(0.0, 1.0).powi(2),
+            roughness_y.clamp(0.0, 1.0).powi(2),
+        )
+    }
+
+    /// Microfacet normal density
+    pub fn d(&self, wm: Vec3) -> crate::Float {
+        let x = wm.x / self.alpha_x;
+        let y = wm.y / self.alpha_y;
+        let denominator = x * x + y * y + wm.z * wm.z;
+        1.0 / (PI * self.alpha_x * self.alpha_y * denominator * denominator)
+    }
+
+    fn lambda(&self, w: Vec3) -> crate::Float {
+        if w.z == 0.0 {
+            return crate::Float::INFINITY;
+        }
+        let x = self.alpha_x * w.x;
+        let y = self.alpha_y * w.y;
+        let tan_2_alpha_2 = (x * x + y * y) / (w.z * w.z);
+        0.5 * ((1.0 + tan_2_alpha_2).sqrt() - 1.0)
+    }
+
+    /// Smith masking
+    pub fn g1(&self, w: Vec3) -> crate::Float {
+        1.0 / (1.0 + self.lambda(w))
+    }
+
+    /// Height correlated Smith masking-shadowing
+    pub fn g(&self, wo: Vec3, wi: Vec3) -> crate::Float {
+        1.0 / (1.0 + self.lambda(wo) + self.lambda(wi))
+    }
+
+    /// Density of visible normals seen from `w`
+    pub fn d_visible(&self, w: Vec3, wm: Vec3) -> crate::Float {
+        if w.z == 0.0 {
+            return 0.0;
+        }
+        self.g1(w) / w.z.abs() * self.d(wm) * w.dot(wm).max(0.0)
+    }
+
+    /// Sample a normal from the distribution of normals visible from `w`,
+    /// with `u` uniform in [0, 1)^2 (Heitz, Sampling the GGX Distribution of
+    /// Visible Normals).
+    pub fn sample_visible_normal(&self, w: Vec3, u: (crate::Float, crate::Float)) -> Vec3 {
+        // transform to the hemisphere configuration
+        let mut wh = Vec3::new(self.alpha_x * w.x, self.alpha_y * w.y, w.z).normalize();
+        if wh.z < 0.0 {
+            wh = -wh;
+        }
+        let length_sqrd = wh.x * wh.x + wh.y * wh.y;
+        let t1 = if length_sqrd > 0.0 {
+            Vec3::new(-wh.y, wh.x, 0.0) / length_sqrd.sqrt()
+        } else {
+            Vec3::new(1.0, 0.0, 0.0)
+        };
+        let t2 = wh.cross(t1);
+
+        // uniform disk, warped towards the visible half
+        let r = u.0.sqrt();
+        let phi = 2.0 * PI * u.1;
+        let p1 = r * phi.cos();
+        let mut p2 = r * phi.sin();
+        let s = 0.5 * (1.0 + wh.z);
+        p2 = (1.0 - s) * (1.0 - p1 * p1).max(0.0).sqrt() + s * p2;
+
+        let nh = p1 * t1 + p2 * t2 + (1.0 - p1 * p1 - p2 * p2).max(0.0).sqrt() * wh;
+        Vec3::new(self.alpha_x * nh.x, self.alpha_y * nh.y, nh.z.max(1e-6)).normalize()
+    }
+
+    /// Whether the surface is smooth enough to be handled as a delta distribution.
+    pub fn effectively_smooth(&self) -> bool {
+        self.alpha_x.max(self.alpha_y) < 1e-3
+    }
+}
@@ -0,0 +1,125 @@
+use std::sync::Arc;
+
+use crate::color::Color;
+use crate::hittable::HitRecord;
+use crate::ray::Ray;
+use crate::texture::{SolidColor, Texture};
+use crate::types::Point3;
+use crate::utils;
+use crate::Vec3;
+
+use super::{Interior, Material, Scatter};
+
+/// Blend of two materials, e.g. rust patches over paint.
+///
+/// Each scatter picks one of the materials at random with the texture as
+/// probability. The interior is the one of `a`, or else the one of `b`.
+pub struct MixMaterial {
+    a: Arc<dyn Material>,
+    b: Arc<dyn Material>,
+    weight: Arc<dyn Texture>,
+}
+
+impl MixMaterial {
+    /// * `weight` - Fraction of `b`, read from the red channel
+    pub fn new(a: Arc<dyn Material>, b: Arc<dyn Material>, weight: Arc<dyn Texture>) -> Self {
+        Self { a, b, weight }
+    }
+
+    pub fn new_with_weight(
+        a: Arc<dyn Material>,
+        b: Arc<dyn Material>,
+        weight: crate::Float,
+    ) -> Self {
+        Self::new(a, b, Arc::new(SolidColor::new(Color::splat(weight))))
+    }
+
+    fn weight(&self, u: crate::Float, v: crate::Float, p: Point3) -> crate::Float {
+        self.weight.scalar(u, v, p).clamp(0.0, 1.0)
+    }
+}
+
+impl Material for MixMaterial {
+    fn scatter(&self, r: &Ray, rec: &HitRecord) -> Option<Scatter> {
+        if utils::gen_float() < self.weight(rec.u, rec.v, rec.p) {
+            self.b.scatter(r, rec)
+        } else {
+            self.a.scatter(r, rec)
+        }
+    }
+
+    fn emitted(&self, r: &Ray, rec: &HitRecord) -> Color {
+        let weight = self.weight(rec.u, rec.v, rec.p);
+        self.a.emitted(r, rec).lerp(self.b.emitted(r, rec), weight)
+    }
+
+    /// Hidden when both materials are.
+    fn visible_to_camera(&self) -> bool {
+        self.a.visible_to_camera() || self.b.visible_to_camera()
+    }
+
+    fn interior(&self) -> Option<Interior> {
+        self.a.interior().or_else(|| self.b.interior())
+    }
+
+    fn eval(&self, r: &Ray, rec: &HitRecord, direction: Vec3) -> Option<(Color, crate::Float)> {
+        let weight = self.weight(rec.u, rec.v, rec.p);
+        // a material which can't be evaluated only counts when it is never picked
+        let eval = |material: &Arc<dyn Material>, weight: crate::Float| {
+            if weight == 0.0 {
+                Some((Color::splat(0.0), 0.0))
+            } else {
+                material.eval(r, rec, direction)
+            }
+        };
+        let (f_a, pdf_a) = eval(&self.a, 1.0 - weight)?;
+        let (f_b, pdf_b) = eval(&self.b, weight)?;
+        Some((f_a.lerp(f_b, weight), pdf_a + (pdf_b - pdf_a) * weight))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material::tests::{assert_eval_matches_scatter, assert_total_internal_reflection};
+    use crate::material::{Dielectric, Lambertian, OrenNayar};
+    use crate::Vec3;
+
+    #[test]
+    fn eval_matches_scatter() {
+        let mix = Arc::new(MixMaterial::new_with_weight(
+            Arc::new(Lambertian::new_with_color(Color::new(0.8, 0.2, 0.1))),
+            Arc::new(OrenNayar::new_with_color(Color::new(0.1, 0.8, 0.1), 0.5)),
+            0.3,
+        ));
+        assert_eval_matches_scatter(mix, Vec3::new(0.3, 1.0, 0.2), false);
+    }
+
+    #[test]
+    fn glass_refracts() {
+        let lambertian = Arc::new(Lambertian::new_with_color(Color::splat(0.5)));
+        let glass = Arc::new(Dielectric::new(1.5));
+        let only_glass = Arc::new(MixMaterial::new_with_weight(lambertian, glass.clone(), 1.0));
+        assert_total_internal_reflection(only_glass.clone());
+        assert_total_internal_reflection(Arc::new(MixMaterial::new_with_weight(
+            glass, only_glass, 0.5,
+        )));
+    }
+
+    #[test]
+    fn eval_needs_both() {
+        let lambertian = Arc::new(Lambertian::new_with_color(Color::splat(0.5)));
+        let glass = Arc::new(Dielectric::new(1.5));
+        let mix = MixMaterial::new_with_weight(lambertian.clone(), glass.clone(), 0.0);
+        let r = Ray::new(Vec3::Y, -Vec3::Y);
+        let rec = HitRecord::new(
+            &Vec3::ZERO,
+            &Vec3::Y,
+            1.0,
+            &(lambertian as Arc<dyn Material>),
+        );
+        assert!(mix.eval(&r, &rec, Vec3::Y).is_some());
+        let mix = MixMaterial::new_with_weight(Arc::new(mix), glass, 0.5);
+        assert!(mix.eval(&r, &rec, Vec3::Y).is_none());
+    }
+}
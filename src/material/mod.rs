@@ -0,0 +1,502 @@
+mod coated;
+mod conductor;
+mod fresnel;
+mod interior;
+mod ior;
+mod merl;
+mod microfacet;
+mod mix;
+mod normal_map;
+mod oren_nayar;
+mod principled;
+mod rough_dielectric;
+mod subsurface;
+mod thin_film;
+
+pub use coated::CoatedMaterial;
+pub use conductor::Conductor;
+pub use interior::{material_id, Interior, InteriorStack, MediumEvent};
+pub use ior::Ior;
+pub use merl::Merl;
+pub use mix::MixMaterial;
+pub use normal_map::{BumpMap, NormalMap};
+pub use oren_nayar::OrenNayar;
+pub use principled::Principled;
+pub use rough_dielectric::RoughDielectric;
+pub use subsurface::Subsurface;
+pub use thin_film::ThinFilm;
+
+use std::sync::Arc;
+
+use crate::color::Color;
+use crate::hittable::HitRecord;
+use crate::ray::{self, Ray};
+use crate::texture::{SolidColor, Texture};
+use crate::types::{Point3, PI};
+use crate::{spectrum, types, utils, Vec3};
+
+pub struct Scatter {
+    pub attenuation: Color,
+    pub ray: Ray,
+}
+
+pub trait Material: Sync + Send {
+    fn scatter(&self, r: &Ray, rec: &HitRecord) -> Option<Scatter>;
+
+    /// Light leaving the surface along `r` back towards its origin.
+    fn emitted(&self, r: &Ray, rec: &HitRecord) -> Color {
+        return Color::splat(0.0);
+    }
+
+    /// Whether rays from the camera see the surface rather than pass through.
+    fn visible_to_camera(&self) -> bool {
+        true
+    }
+
+    /// Whether `emitted` lights the back face too, and the cosine of the
+    /// widest angle to the normal light leaves at, for area lights to be
+    /// bounded. Both faces and the whole hemisphere by default.
+    fn emission_spread(&self) -> (bool, crate::Float) {
+        (true, 0.0)
+    }
+
+    /// What fills the surface, for dielectrics whose nesting is tracked along the ray.
+    fn interior(&self) -> Option<Interior> {
+        None
+    }
+
+    /// Light scattered along `r` back towards its origin per unit of light
+    /// arriving from `direction`, the BRDF times the cosine, with the density
+    /// of `scatter` picking that direction. For lights to be sampled from
+    /// the surface. None if it can't be evaluated, e.g. for smooth mirrors
+    /// and glass, lights are then only found by `scatter`.
+    ///
+    /// The density only weights light sampling against `scatter`, an
+    /// approximation makes that less effective but not wrong.
+    fn eval(&self, r: &Ray, rec: &HitRecord, direction: Vec3) -> Option<(Color, crate::Float)> {
+        None
+    }
+}
+
+pub struct Lambertian {
+    base_color: Arc<dyn Texture>,
+}
+
+impl Material for Lambertian {
+    fn scatter(&self, r: &Ray, rec: &HitRecord) -> Option<Scatter> {
+        let mut scatter_direction = rec.normal + utils::rand_vec3_unit();
+        if types::is_near_zero(scatter_direction) {
+            scatter_direction = rec.normal;
+        }
+        Some(Scatter {
+            attenuation: self.base_color.value_at(rec),
+            ray: Ray::new_with_time(rec.p, scatter_direction, r.time()),
+        })
+    }
+
+    fn eval(&self, r: &Ray, rec: &HitRecord, direction: Vec3) -> Option<(Color, crate::Float)> {
+        // the scattered directions are cosine distributed
+        let cosine = direction.normalize().dot(rec.normal).max(0.0);
+        Some((self.base_color.value_at(rec) * cosine / PI, cosine / PI))
+    }
+}
+
+impl Lambertian {
+    pub fn new(texture: Arc<dyn Texture>) -> Self {
+        Self {
+            base_color: texture,
+        }
+    }
+
+    pub fn new_with_color(color: Color) -> Self {
+        Self {
+            base_color: Arc::new(SolidColor::new(color)),
+        }
+    }
+}
+
+pub struct Dielectric {
+    pub ior: Ior,
+    absorption: Color,
+    priority: u32,
+    thin_film: Option<ThinFilm>,
+}
+
+impl Dielectric {
+    /// * `ior` - A number, or an `Ior` that varies with the wavelength for dispersion
+    pub fn new(ior: impl Into<Ior>) -> Self {
+        Self::new_with_absorption(ior, Color::splat(0.0), 0)
+    }
+
+    /// Colored glass or liquid, see `Interior` for `absorption` and `priority`.
+    pub fn new_with_absorption(ior: impl Into<Ior>, absorption: Color, priority: u32) -> Self {
+        Self {
+            ior: ior.into(),
+            absorption,
+            priority,
+            thin_film: None,
+        }
+    }
+
+    /// Adds a film on the surface, e.g. a soap bubble is a film on a
+    /// dielectric with an index of refraction of 1.
+    pub fn with_thin_film(self, thin_film: ThinFilm) -> Self {
+        Self {
+            thin_film: Some(thin_film),
+            ..self
+        }
+    }
+
+    fn reflectance(cosine: crate::Float, ref_idx: crate::Float) -> crate::Float {
+        // Use Schlick's approximation for reflectance.
+        let mut r0 = (1.0 - ref_idx) / (1.0 + ref_idx);
+        r0 = r0 * r0;
+        return r0 + (1.0 - r0) * (1.0 - cosine).powi(5);
+    }
+
+    /// Indices of refraction on the side of the ray and on the far side.
+    fn media(
+        &self,
+        r: &Ray,
+        rec: &HitRecord,
+        wavelength: Option<crate::Float>,
+    ) -> (crate::Float, crate::Float) {
+        if rec.front_face {
+            (r.interiors.ir(wavelength), self.ior.at(wavelength))
+        } else {
+            (
+                self.ior.at(wavelength),
+                r.interiors
+                    .ir_outside(material_id(rec.material.as_ref()), wavelength),
+            )
+        }
+    }
+}
+
+impl Material for Dielectric {
+    fn scatter(&self, r: &Ray, rec: &HitRecord) -> Option<Scatter> {
+        // dispersive glass splits white light into a single wavelength
+        let (wavelength, tint) = if self.ior.is_dispersive() {
+            let (wavelength, tint) = spectrum::hero_wavelength(r.wavelength);
+            (Some(wavelength), tint)
+        } else {
+            (r.wavelength, Color::splat(1.0))
+        };
+
+        let (ir_incident, ir_transmitted) = self.media(r, rec, wavelength);
+        let refraction_ratio = ir_incident / ir_transmitted;
+        let unit_direction = r.direction().normalize();
+        let cos_theta = -unit_direction.dot(rec.normal).min(1.0);
+        let sin_theta = (1.0 - cos_theta * cos_theta).sqrt();
+        let cannot_refract = refraction_ratio * sin_theta > 1.0;
+
+        let reflected = || ray::reflect(unit_direction, rec.normal);
+        let refracted = || ray::refract(unit_direction, rec.normal, refraction_ratio);
+        let (dir_out, attenuation) = match &self.thin_film {
+            _ if cannot_refract => (reflected(), Color::splat(1.0)),
+            None => {
+                if Self::reflectance(cos_theta, refraction_ratio) > utils::gen_float() {
+                    (reflected(), Color::splat(1.0))
+                } else {
+                    (refracted(), Color::splat(1.0))
+                }
+            }
+            Some(thin_film) => {
+                let reflectance = thin_film.reflectance(rec, cos_theta, wavelength, |wavelength| {
+                    let (ir_incident, ir_transmitted) = self.media(r, rec, Some(wavelength));
+                    (ir_incident, ir_transmitted, 0.0)
+                });
+                // reflect with the average reflectance, weighting each channel
+                let probability =
+                    ((reflectance.x + reflectance.y + reflectance.z) / 3.0).clamp(0.001, 0.999);
+                if utils::gen_float() < probability {
+                    (reflected(), reflectance / probability)
+                } else {
+                    (
+                        refracted(),
+                        (Color::splat(1.0) - reflectance) / (1.0 - probability),
+                    )
+                }
+            }
+        };
+
+        let mut ray = Ray::new_with_time(rec.p, dir_out, r.time());
+        ray.wavelength = wavelength;
+        Some(Scatter {
+            attenuation: tint * attenuation,
+            ray,
+        })
+    }
+
+    fn interior(&self) -> Option<Interior> {
+        Some(Interior {
+            ior: self.ior,
+            absorption: self.absorption,
+            scattering: Color::splat(0.0),
+            priority: self.priority,
+        })
+    }
+}
+
+/// Narrows the light leaving an emitter towards its normal, keeping its
+/// power, like the honeycomb grid of a softbox.
+#[derive(Clone, Copy)]
+struct Spread {
+    tan_half_angle: crate::Float,
+    normalization: crate::Float,
+}
+
+impl Spread {
+    /// * `angle` - Full angle of the cone light leaves in, in radians, below pi
+    fn new(angle: crate::Float) -> Self {
+        let half_angle = angle / 2.0;
+        let tan_half_angle = half_angle.tan();
+        // integral of the falloff times the cosine over the hemisphere, pi
+        // for a diffuse emitter
+        let sin_half_angle = half_angle.sin();
+        let integral = 2.0
+            * PI
+            * (tan_half_angle * sin_half_angle * sin_half_angle / 2.0 - half_angle / 2.0
+                + (2.0 * half_angle).sin() / 4.0);
+        Self {
+            tan_half_angle,
+            normalization: PI / integral,
+        }
+    }
+
+    /// Cosine of the angle to the normal past which nothing is emitted.
+    fn cos_half_angle(&self) -> crate::Float {
+        1.0 / (1.0 + self.tan_half_angle * self.tan_half_angle).sqrt()
+    }
+
+    fn falloff(&self, cos_theta: crate::Float) -> crate::Float {
+        if cos_theta <= 0.0 {
+            return 0.0;
+        }
+        let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+        let tan_theta = sin_theta / cos_theta;
+        ((self.tan_half_angle - tan_theta) * self.normalization).max(0.0)
+    }
+}
+
+pub struct DiffuseLight {
+    emit: Arc<dyn Texture>,
+    two_sided: bool,
+    spread: Option<Spread>,
+    visible_to_camera: bool,
+}
+
+impl DiffuseLight {
+    pub fn new(texture: Arc<dyn Texture>) -> Self {
+        Self {
+            emit: texture,
+            two_sided: true,
+            spread: None,
+            visible_to_camera: true,
+        }
+    }
+
+    pub fn new_with_color(color: Color) -> Self {
+        Self::new(Arc::new(SolidColor::new(color)))
+    }
+
+    /// Whether the back face, against the normal of the shape, emits too.
+    /// Lights emit from both faces unless told otherwise.
+    pub fn with_two_sided(self, two_sided: bool) -> Self {
+        Self { two_sided, ..self }
+    }
+
+    /// Light leaves within a cone of `degrees` around the normal, brighter
+    /// towards the normal. 180 is a diffuse emitter.
+    pub fn with_spread(self, degrees: crate::Float) -> Self {
+        let spread = if degrees >= 180.0 {
+            None
+        } else {
+            Some(Spread::new(degrees.max(1.0).to_radians()))
+        };
+        Self { spread, ..self }
+    }
+
+    /// A light hidden from the camera still lights the scene and shows in
+    /// reflections, camera rays go through it.
+    pub fn with_visible_to_camera(self, visible_to_camera: bool) -> Self {
+        Self {
+            visible_to_camera,
+            ..self
+        }
+    }
+}
+
+impl Material for DiffuseLight {
+    fn scatter(&self, _r: &Ray, _rec: &HitRecord) -> Option<Scatter> {
+        return None;
+    }
+
+    fn emitted(&self, r: &Ray, rec: &HitRecord) -> Color {
+        if !rec.front_face && !self.two_sided {
+            return Color::splat(0.0);
+        }
+        let falloff = match self.spread {
+            Some(spread) => {
+                // the normal faces the ray
+                let cos_theta = -r.direction().normalize().dot(rec.normal);
+                spread.falloff(cos_theta)
+            }
+            None => 1.0,
+        };
+        return self.emit.value_at(rec) * falloff;
+    }
+
+    fn visible_to_camera(&self) -> bool {
+        self.visible_to_camera
+    }
+
+    fn emission_spread(&self) -> (bool, crate::Float) {
+        let cos_theta_e = match self.spread {
+            Some(spread) => spread.cos_half_angle(),
+            None => 0.0,
+        };
+        (self.two_sided, cos_theta_e)
+    }
+}
+
+pub struct Isotropic {
+    albedo: Arc<dyn Texture>,
+}
+
+impl Isotropic {
+    pub fn new(albedo: Arc<dyn Texture>) -> Self {
+        Self { albedo }
+    }
+    pub fn new_with_color(color: Color) -> Self {
+        Self {
+            albedo: Arc::new(SolidColor::new(color)),
+        }
+    }
+}
+
+impl Material for Isotropic {
+    fn scatter(&self, r: &Ray, rec: &HitRecord) -> Option<Scatter> {
+        let scattered = Ray::new_with_time(rec.p, utils::rand_vec3_in_unit_sphere(), r.time());
+        let attenuation = self.albedo.value(rec.u, rec.v, rec.p);
+        Some(Scatter {
+            attenuation,
+            ray: scattered,
+        })
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::color;
+
+    const SAMPLES: usize = 500_000;
+
+    /// Checks `Material::eval` against `Material::scatter` at a hit on the
+    /// xz plane seen from `outgoing`, with u along x and v along z: the pdf
+    /// integrates to how often scatter succeeds and f |cos| to its mean
+    /// attenuation.
+    /// Where scatter picks directions with one sample MIS, so its
+    /// attenuation is f |cos| / pdf, `exact` also checks every sample.
+    pub(crate) fn assert_eval_matches_scatter(
+        material: Arc<dyn Material>,
+        outgoing: Vec3,
+        exact: bool,
+    ) {
+        let (r, rec) = hit(material.clone(), outgoing);
+        let mut scattered = 0;
+        for _ in 0..SAMPLES {
+            if let Some(scatter) = material.scatter(&r, &rec) {
+                scattered += 1;
+                if exact {
+                    let (f, pdf) = material.eval(&r, &rec, scatter.ray.direction()).unwrap();
+                    assert!(pdf > 0.0);
+                    let expected = f / pdf;
+                    assert!(
+                        (expected - scatter.attenuation).abs().max_element()
+                            < 1e-3 * expected.max_element().max(1.0),
+                        "{} != {}",
+                        expected,
+                        scatter.attenuation
+                    );
+                }
+            }
+        }
+
+        let pdf_integral = integrate(&material, &r, &rec, |_, pdf| pdf);
+        let scattered = scattered as crate::Float / SAMPLES as crate::Float;
+        assert!(
+            (pdf_integral - scattered).abs() < 0.03,
+            "seen from {}, pdf integrates to {}, scatter succeeds {}",
+            outgoing,
+            pdf_integral,
+            scattered
+        );
+        assert_eval_reflects_as_scatter(material, outgoing);
+    }
+
+    /// Checks that f |cos| of `Material::eval` integrates to the mean
+    /// attenuation of `Material::scatter`, as `assert_eval_matches_scatter`.
+    // the casts are needed when Float is f32
+    #[allow(clippy::unnecessary_cast)]
+    pub(crate) fn assert_eval_reflects_as_scatter(material: Arc<dyn Material>, outgoing: Vec3) {
+        let (r, rec) = hit(material.clone(), outgoing);
+        // summed in f64, f32 would lose the small terms
+        let mut attenuation = 0.0_f64;
+        for _ in 0..SAMPLES {
+            if let Some(scatter) = material.scatter(&r, &rec) {
+                attenuation += color::luminance(scatter.attenuation) as f64;
+            }
+        }
+        let expected = (attenuation / SAMPLES as f64) as crate::Float;
+        let reflected = integrate(&material, &r, &rec, |f, _| color::luminance(f));
+        assert!(
+            (reflected - expected).abs() < 0.03 * expected.max(0.1),
+            "seen from {}, f integrates to {}, scatter reflects {}",
+            outgoing,
+            reflected,
+            expected
+        );
+    }
+
+    /// Checks that `material`, glass of index 1.5 however it is wrapped,
+    /// reflects everything leaving it at 45 degrees, past the critical angle.
+    pub(crate) fn assert_total_internal_reflection(material: Arc<dyn Material>) {
+        let (mut r, rec) = hit(material.clone(), Vec3::new(1.0, -1.0, 0.0));
+        // entered through the same surface, as the integrator does
+        r.interiors = InteriorStack::default()
+            .entered(material_id(material.as_ref()), material.interior().unwrap());
+        for _ in 0..2000 {
+            if let Some(scatter) = material.scatter(&r, &rec) {
+                assert!(scatter.ray.direction().dot(rec.normal) > 0.0);
+            }
+        }
+    }
+
+    fn hit(material: Arc<dyn Material>, outgoing: Vec3) -> (Ray, HitRecord) {
+        let outgoing = outgoing.normalize();
+        let mut rec = HitRecord::new(&Point3::ZERO, &Vec3::Y, 1.0, &material);
+        rec.front_face = outgoing.y > 0.0;
+        rec.normal = if rec.front_face { Vec3::Y } else { -Vec3::Y };
+        rec.dpdu = Vec3::X;
+        rec.dpdv = Vec3::Z;
+        (Ray::new(outgoing, -outgoing), rec)
+    }
+
+    /// Integral of `value` of the evaluation over the sphere of directions.
+    #[allow(clippy::unnecessary_cast)]
+    fn integrate(
+        material: &Arc<dyn Material>,
+        r: &Ray,
+        rec: &HitRecord,
+        value: impl Fn(Color, crate::Float) -> crate::Float,
+    ) -> crate::Float {
+        let mut sum = 0.0_f64;
+        for _ in 0..SAMPLES {
+            let (f, pdf) = material.eval(r, rec, utils::rand_vec3_unit()).unwrap();
+            sum += value(f, pdf) as f64;
+        }
+        (4.0 * PI as f64 * sum / SAMPLES as f64) as crate::Float
+    }
+}
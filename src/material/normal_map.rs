@@ -0,0 +1,324 @@
+use std::sync::Arc;
+
+use crate::color::Color;
+use crate::hittable::HitRecord;
+use crate::ray::{self, Ray};
+use crate::texture::Texture;
+use crate::types::Point3;
+use crate::Vec3;
+
+use super::{Interior, Material, Scatter};
+
+/// Step in u and v for the finite differences of bump maps.
+const BUMP_DELTA: crate::Float = 0.0005;
+const BISECTION_STEPS: usize = 12;
+
+/// Tangent and bitangent around `rec.normal`, oriented along u and v.
+/// `None` where the primitive has no parameterization.
+fn tangent_frame(rec: &HitRecord) -> Option<(Vec3, Vec3)> {
+    let normal = rec.normal;
+    let tangent = (rec.dpdu - rec.dpdu.dot(normal) * normal).try_normalize()?;
+    let bitangent = normal.cross(tangent);
+    if bitangent.dot(rec.dpdv) < 0.0 {
+        Some((tangent, -bitangent))
+    } else {
+        Some((tangent, bitangent))
+    }
+}
+
+/// Bend a shading normal towards the geometric one until the mirror
+/// reflection of `wo` stays above the surface. Without this, grazing views
+/// of strongly perturbed normals leave nothing to reflect and show up as
+/// black fringes.
+fn ensure_valid_reflection(geometric_normal: Vec3, wo: Vec3, normal: Vec3) -> Vec3 {
+    let threshold = (0.9 * wo.dot(geometric_normal)).min(0.01);
+    let is_valid = |normal: Vec3| ray::reflect(-wo, normal).dot(geometric_normal) >= threshold;
+    if is_valid(normal) {
+        return normal;
+    }
+
+    // the geometric normal itself is always valid
+    let (mut invalid, mut valid) = (0.0, 1.0);
+    for _ in 0..BISECTION_STEPS {
+        let middle = 0.5 * (invalid + valid);
+        if is_valid(normal.lerp(geometric_normal, middle).normalize()) {
+            valid = middle;
+        } else {
+            invalid = middle;
+        }
+    }
+    normal.lerp(geometric_normal, valid).normalize()
+}
+
+/// Scatter `material` with `normal` as the shading normal. Directions that
+/// end up on the wrong side of the geometric surface, reflections going
+/// below it or refractions staying above it, are mirrored back so light
+/// neither leaks through nor is lost.
+fn scatter_with_normal(
+    material: &dyn Material,
+    r: &Ray,
+    rec: &HitRecord,
+    normal: Vec3,
+) -> Option<Scatter> {
+    let geometric_normal = rec.normal;
+    let wo = -r.direction().normalize();
+    let mut shaded = rec.clone();
+    shaded.normal = ensure_valid_reflection(geometric_normal, wo, normal);
+
+    let mut scatter = material.scatter(r, &shaded)?;
+    let direction = scatter.ray.direction();
+    let intended_side = direction.dot(shaded.normal) > 0.0;
+    let actual_side = direction.dot(geometric_normal) > 0.0;
+    if intended_side != actual_side {
+        let mirrored = direction - 2.0 * direction.dot(geometric_normal) * geometric_normal;
+        let mut ray = Ray::new_with_time(scatter.ray.origin(), mirrored, scatter.ray.time());
+        ray.interiors = scatter.ray.interiors;
+        ray.wavelength = scatter.ray.wavelength;
+        scatter.ray = ray;
+    }
+    Some(scatter)
+}
+
+/// Evaluates `material` with `normal` as the shading normal, counting the
+/// directions `scatter_with_normal` mirrors onto `direction`.
+fn eval_with_normal(
+    material: &dyn Material,
+    r: &Ray,
+    rec: &HitRecord,
+    normal: Vec3,
+    direction: Vec3,
+) -> Option<(Color, crate::Float)> {
+    let geometric_normal = rec.normal;
+    let wo = -r.direction().normalize();
+    let mut shaded = rec.clone();
+    shaded.normal = ensure_valid_reflection(geometric_normal, wo, normal);
+    let kept = |direction: Vec3| {
+        (direction.dot(shaded.normal) > 0.0) == (direction.dot(geometric_normal) > 0.0)
+    };
+
+    let mut f = Color::splat(0.0);
+    let mut pdf = 0.0;
+    if kept(direction) {
+        let (kept_f, kept_pdf) = material.eval(r, &shaded, direction)?;
+        f += kept_f;
+        pdf += kept_pdf;
+    }
+    let mirrored = direction - 2.0 * direction.dot(geometric_normal) * geometric_normal;
+    if !kept(mirrored) {
+        let (mirrored_f, mirrored_pdf) = material.eval(r, &shaded, mirrored)?;
+        f += mirrored_f;
+        pdf += mirrored_pdf;
+    }
+    Some((f, pdf))
+}
+
+/// Tangent space normal map over any material.
+///
+/// The map stores normals as RGB in [0, 1] with blue along the surface
+/// normal, red along u and green along v.
+pub struct NormalMap {
+    material: Arc<dyn Material>,
+    map: Arc<dyn Texture>,
+    strength: crate::Float,
+}
+
+impl NormalMap {
+    pub fn new(material: Arc<dyn Material>, map: Arc<dyn Texture>) -> Self {
+        Self::new_with_strength(material, map, 1.0)
+    }
+
+    /// * `strength` - Scales the tilt of the normals, 0 leaves the surface flat
+    pub fn new_with_strength(
+        material: Arc<dyn Material>,
+        map: Arc<dyn Texture>,
+        strength: crate::Float,
+    ) -> Self {
+        Self {
+            material,
+            map,
+            strength,
+        }
+    }
+}
+
+impl NormalMap {
+    /// None where the surface has no tangents to tilt the normal along.
+    fn shading_normal(&self, rec: &HitRecord) -> Option<Vec3> {
+        let (tangent, bitangent) = tangent_frame(rec)?;
+        let value = 2.0 * self.map.value_at(rec) - Color::splat(1.0);
+        let normal = (self.strength * value.x * tangent
+            + self.strength * value.y * bitangent
+            + value.z.max(0.0) * rec.normal)
+            .try_normalize()
+            .unwrap_or(rec.normal);
+        Some(normal)
+    }
+}
+
+impl Material for NormalMap {
+    fn scatter(&self, r: &Ray, rec: &HitRecord) -> Option<Scatter> {
+        match self.shading_normal(rec) {
+            Some(normal) => scatter_with_normal(self.material.as_ref(), r, rec, normal),
+            None => self.material.scatter(r, rec),
+        }
+    }
+
+    fn emitted(&self, r: &Ray, rec: &HitRecord) -> Color {
+        self.material.emitted(r, rec)
+    }
+
+    fn visible_to_camera(&self) -> bool {
+        self.material.visible_to_camera()
+    }
+
+    fn interior(&self) -> Option<Interior> {
+        self.material.interior()
+    }
+
+    fn eval(&self, r: &Ray, rec: &HitRecord, direction: Vec3) -> Option<(Color, crate::Float)> {
+        match self.shading_normal(rec) {
+            Some(normal) => eval_with_normal(self.material.as_ref(), r, rec, normal, direction),
+            None => self.material.eval(r, rec, direction),
+        }
+    }
+}
+
+/// Bump map over any material, the normal follows the slope of a height
+/// given by any texture, e.g. `NoiseTexture`.
+pub struct BumpMap {
+    material: Arc<dyn Material>,
+    height: Arc<dyn Texture>,
+    scale: crate::Float,
+}
+
+impl BumpMap {
+    /// * `height` - Displacement along the normal, read from the red channel
+    /// * `scale` - World units per unit of height
+    pub fn new(material: Arc<dyn Material>, height: Arc<dyn Texture>, scale: crate::Float) -> Self {
+        Self {
+            material,
+            height,
+            scale,
+        }
+    }
+
+    fn displacement(&self, u: crate::Float, v: crate::Float, p: Point3) -> crate::Float {
+        self.scale * self.height.scalar(u, v, p)
+    }
+
+    /// None where the surface has no parameterization to displace along.
+    fn shading_normal(&self, rec: &HitRecord) -> Option<Vec3> {
+        if tangent_frame(rec).is_none() || rec.dpdv.length_squared() == 0.0 {
+            return None;
+        }
+        let outward_normal = if rec.front_face {
+            rec.normal
+        } else {
+            -rec.normal
+        };
+
+        // derivatives of the displaced surface p + d(u, v) n
+        let displacement = self.displacement(rec.u, rec.v, rec.p);
+        let d_du = (self.displacement(rec.u + BUMP_DELTA, rec.v, rec.p + BUMP_DELTA * rec.dpdu)
+            - displacement)
+            / BUMP_DELTA;
+        let d_dv = (self.displacement(rec.u, rec.v + BUMP_DELTA, rec.p + BUMP_DELTA * rec.dpdv)
+            - displacement)
+            / BUMP_DELTA;
+        let dpdu = rec.dpdu + d_du * outward_normal;
+        let dpdv = rec.dpdv + d_dv * outward_normal;
+
+        let mut normal = dpdu.cross(dpdv).try_normalize()?;
+        // the parameterization may be left handed
+        if rec.dpdu.cross(rec.dpdv).dot(outward_normal) < 0.0 {
+            normal = -normal;
+        }
+        if !rec.front_face {
+            normal = -normal;
+        }
+        Some(normal)
+    }
+}
+
+impl Material for BumpMap {
+    fn scatter(&self, r: &Ray, rec: &HitRecord) -> Option<Scatter> {
+        match self.shading_normal(rec) {
+            Some(normal) => scatter_with_normal(self.material.as_ref(), r, rec, normal),
+            None => self.material.scatter(r, rec),
+        }
+    }
+
+    fn emitted(&self, r: &Ray, rec: &HitRecord) -> Color {
+        self.material.emitted(r, rec)
+    }
+
+    fn visible_to_camera(&self) -> bool {
+        self.material.visible_to_camera()
+    }
+
+    fn interior(&self) -> Option<Interior> {
+        self.material.interior()
+    }
+
+    fn eval(&self, r: &Ray, rec: &HitRecord, direction: Vec3) -> Option<(Color, crate::Float)> {
+        match self.shading_normal(rec) {
+            Some(normal) => eval_with_normal(self.material.as_ref(), r, rec, normal, direction),
+            None => self.material.eval(r, rec, direction),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material::tests::{assert_eval_matches_scatter, assert_total_internal_reflection};
+    use crate::material::{Dielectric, DiffuseLight, Lambertian};
+    use crate::texture::{NoiseTexture, SolidColor};
+
+    fn lambertian() -> Arc<dyn Material> {
+        Arc::new(Lambertian::new_with_color(Color::new(0.8, 0.2, 0.1)))
+    }
+
+    #[test]
+    fn eval_matches_scatter() {
+        // tilted towards +x, so grazing views from -x mirror directions
+        let tilt = Arc::new(SolidColor::new(Color::new(0.9, 0.5, 0.7)));
+        let normal_map = Arc::new(NormalMap::new(lambertian(), tilt));
+        assert_eval_matches_scatter(normal_map.clone(), Vec3::new(0.2, 1.0, 0.1), true);
+        assert_eval_matches_scatter(normal_map, Vec3::new(-1.0, 0.2, 0.0), true);
+
+        let bump_map = Arc::new(BumpMap::new(
+            lambertian(),
+            Arc::new(NoiseTexture::new(4.0)),
+            0.2,
+        ));
+        assert_eval_matches_scatter(bump_map, Vec3::new(1.0, 0.3, -0.5), true);
+    }
+
+    #[test]
+    fn forwards_interior() {
+        let flat = Arc::new(SolidColor::new(Color::new(0.5, 0.5, 1.0)));
+        let glass = Arc::new(Dielectric::new(1.5));
+        assert!(NormalMap::new(glass, flat.clone()).interior().is_some());
+        assert!(NormalMap::new(lambertian(), flat).interior().is_none());
+    }
+
+    #[test]
+    fn wrapped_glass_refracts() {
+        let flat = Arc::new(SolidColor::new(Color::new(0.5, 0.5, 1.0)));
+        let glass = Arc::new(Dielectric::new(1.5));
+        assert_total_internal_reflection(glass.clone());
+        assert_total_internal_reflection(Arc::new(NormalMap::new(glass.clone(), flat.clone())));
+        assert_total_internal_reflection(Arc::new(BumpMap::new(glass, flat, 1.0)));
+    }
+
+    #[test]
+    fn forwards_visible_to_camera() {
+        let hidden =
+            Arc::new(DiffuseLight::new_with_color(Color::splat(4.0)).with_visible_to_camera(false));
+        let flat = Arc::new(SolidColor::new(Color::new(0.5, 0.5, 1.0)));
+        assert!(!NormalMap::new(hidden.clone(), flat.clone()).visible_to_camera());
+        assert!(!BumpMap::new(hidden, flat.clone(), 1.0).visible_to_camera());
+        assert!(NormalMap::new(lambertian(), flat).visible_to_camera());
+    }
+}
@@ -0,0 +1,296 @@
+use std::sync::Arc;
+
+use crate::color::Color;
+use crate::hittable::HitRecord;
+use crate::onb::Onb;
+use crate::ray::Ray;
+use crate::texture::{SolidColor, Texture};
+use crate::types::PI;
+use crate::utils;
+use crate::Vec3;
+
+use super::{Material, Scatter};
+
+enum Model {
+    /// Oren and Nayar's qualitative model, roughness is the standard
+    /// deviation of the facet angle in radians
+    Qualitative,
+    /// Fujii's improved model with a multiple scattering term (Portsmouth et
+    /// al., EON: A practical energy-preserving rough diffuse BRDF),
+    /// roughness in [0, 1]
+    EnergyPreserving(Box<AlbedoTable>),
+}
+
+/// Rough diffuse reflection for clay, concrete or cloth, which is brighter
+/// towards the light than `Lambertian` and flatter at the rims.
+pub struct OrenNayar {
+    albedo: Arc<dyn Texture>,
+    roughness: Arc<dyn Texture>,
+    model: Model,
+}
+
+impl OrenNayar {
+    /// * `sigma` - Standard deviation of the facet angle in radians, read from
+    ///   the red channel. 0 is `Lambertian`.
+    pub fn new(albedo: Arc<dyn Texture>, sigma: Arc<dyn Texture>) -> Self {
+        Self {
+            albedo,
+            roughness: sigma,
+            model: Model::Qualitative,
+        }
+    }
+
+    pub fn new_with_color(albedo: Color, sigma: crate::Float) -> Self {
+        Self::new(
+            Arc::new(SolidColor::new(albedo)),
+            Arc::new(SolidColor::new(Color::splat(sigma))),
+        )
+    }
+
+    /// Energy preserving variant, a white surface reflects all light at any
+    /// roughness and there is some retro reflection.
+    ///
+    /// * `roughness` - In [0, 1], read from the red channel. 0 is `Lambertian`.
+    pub fn new_energy_preserving(albedo: Arc<dyn Texture>, roughness: Arc<dyn Texture>) -> Self {
+        Self {
+            albedo,
+            roughness,
+            model: Model::EnergyPreserving(Box::new(AlbedoTable::integrate())),
+        }
+    }
+
+    pub fn new_energy_preserving_with_color(albedo: Color, roughness: crate::Float) -> Self {
+        Self::new_energy_preserving(
+            Arc::new(SolidColor::new(albedo)),
+            Arc::new(SolidColor::new(Color::splat(roughness))),
+        )
+    }
+}
+
+/// f * pi / albedo of the qualitative model.
+fn qualitative(sigma: crate::Float, wo: Vec3, wi: Vec3) -> crate::Float {
+    let sigma_2 = sigma * sigma;
+    let a = 1.0 - sigma_2 / (2.0 * (sigma_2 + 0.33));
+    let b = 0.45 * sigma_2 / (sigma_2 + 0.09);
+
+    let sin_theta_o = (1.0 - wo.z * wo.z).max(0.0).sqrt();
+    let sin_theta_i = (1.0 - wi.z * wi.z).max(0.0).sqrt();
+    let cos_phi_difference = if sin_theta_o > 1e-4 && sin_theta_i > 1e-4 {
+        ((wo.x * wi.x + wo.y * wi.y) / (sin_theta_o * sin_theta_i)).max(0.0)
+    } else {
+        0.0
+    };
+    // sin of the larger angle, tan of the smaller one
+    let (sin_alpha, tan_beta) = if wi.z < wo.z {
+        (sin_theta_i, sin_theta_o / wo.z)
+    } else {
+        (sin_theta_o, sin_theta_i / wi.z)
+    };
+    a + b * cos_phi_difference * sin_alpha * tan_beta
+}
+
+/// s / t of Fujii's model.
+fn fujii_s_over_t(wo: Vec3, wi: Vec3) -> crate::Float {
+    let s = wo.dot(wi) - wo.z * wi.z;
+    if s > 0.0 {
+        s / wo.z.max(wi.z)
+    } else {
+        s
+    }
+}
+
+const ALBEDO_TABLE_SIZE: usize = 32;
+
+/// Directional albedo of the s / t term against cos theta, the albedo of
+/// Fujii's model is then A + B g(cos theta). Also its cosine weighted average.
+struct AlbedoTable {
+    g: [crate::Float; ALBEDO_TABLE_SIZE],
+    average: crate::Float,
+}
+
+impl AlbedoTable {
+    /// Midpoint rule over cos theta and phi of the incoming direction.
+    fn integrate() -> Self {
+        const STEPS: usize = 128;
+        let mut g = [0.0; ALBEDO_TABLE_SIZE];
+        for (index, g) in g.iter_mut().enumerate() {
+            let cos_theta_o = index as crate::Float / (ALBEDO_TABLE_SIZE - 1) as crate::Float;
+            let wo = Vec3::new((1.0 - cos_theta_o * cos_theta_o).sqrt(), 0.0, cos_theta_o);
+            let mut sum = 0.0;
+            for i in 0..STEPS {
+                let cos_theta_i = (i as crate::Float + 0.5) / STEPS as crate::Float;
+                let sin_theta_i = (1.0 - cos_theta_i * cos_theta_i).sqrt();
+                for j in 0..STEPS {
+                    let phi = 2.0 * PI * (j as crate::Float + 0.5) / STEPS as crate::Float;
+                    let wi = Vec3::new(
+                        sin_theta_i * phi.cos(),
+                        sin_theta_i * phi.sin(),
+                        cos_theta_i,
+                    );
+                    sum += fujii_s_over_t(wo, wi) * cos_theta_i;
+                }
+            }
+            *g = sum * 2.0 * PI / (STEPS * STEPS) as crate::Float / PI;
+        }
+
+        let table = Self { g, average: 0.0 };
+        let average = (0..STEPS)
+            .map(|i| {
+                let cos_theta = (i as crate::Float + 0.5) / STEPS as crate::Float;
+                2.0 * table.g(cos_theta) * cos_theta
+            })
+            .sum::<crate::Float>()
+            / STEPS as crate::Float;
+        Self { average, ..table }
+    }
+
+    fn g(&self, cos_theta: crate::Float) -> crate::Float {
+        let x = cos_theta.clamp(0.0, 1.0) * (ALBEDO_TABLE_SIZE - 1) as crate::Float;
+        let index = (x as usize).min(ALBEDO_TABLE_SIZE - 2);
+        let t = x - index as crate::Float;
+        (1.0 - t) * self.g[index] + t * self.g[index + 1]
+    }
+}
+
+/// f * pi of the energy preserving model.
+fn energy_preserving(
+    table: &AlbedoTable,
+    albedo: Color,
+    roughness: crate::Float,
+    wo: Vec3,
+    wi: Vec3,
+) -> Color {
+    let a = 1.0 / (1.0 + (0.5 - 2.0 / (3.0 * PI)) * roughness);
+    let b = roughness * a;
+    let single = albedo * (a + b * fujii_s_over_t(wo, wi));
+
+    // light lost by single scattering comes back, spread like the missing albedo
+    let albedo_o = a + b * table.g(wo.z);
+    let albedo_i = a + b * table.g(wi.z);
+    let average = a + b * table.average;
+    if average >= 1.0 {
+        return single;
+    }
+    let multiple_albedo =
+        albedo * albedo * average / (Color::splat(1.0) - albedo * (1.0 - average));
+    let multiple = multiple_albedo * (1.0 - albedo_o) * (1.0 - albedo_i) / (1.0 - average);
+    single + multiple
+}
+
+impl OrenNayar {
+    /// f * pi, directions in the shading frame.
+    fn reflectance(&self, rec: &HitRecord, wo: Vec3, wi: Vec3) -> Color {
+        let albedo = self.albedo.value_at(rec);
+        let roughness = self.roughness.scalar(rec.u, rec.v, rec.p).max(0.0);
+        if wo.z <= 0.0 {
+            albedo
+        } else {
+            match &self.model {
+                Model::Qualitative => albedo * qualitative(roughness, wo, wi),
+                Model::EnergyPreserving(table) => {
+                    energy_preserving(table, albedo, roughness.min(1.0), wo, wi)
+                }
+            }
+        }
+    }
+}
+
+impl Material for OrenNayar {
+    fn scatter(&self, r: &Ray, rec: &HitRecord) -> Option<Scatter> {
+        let frame = Onb::new_from_w(rec.normal);
+        let wo = frame.to_local(-r.direction().normalize());
+        let wi = utils::rand_cosine_direction();
+
+        // cosine sampling leaves f * pi as the weight
+        Some(Scatter {
+            attenuation: self.reflectance(rec, wo, wi),
+            ray: Ray::new_with_time(rec.p, frame.to_world(wi), r.time()),
+        })
+    }
+
+    fn eval(&self, r: &Ray, rec: &HitRecord, direction: Vec3) -> Option<(Color, crate::Float)> {
+        let frame = Onb::new_from_w(rec.normal);
+        let wo = frame.to_local(-r.direction().normalize());
+        let wi = frame.to_local(direction.normalize());
+        if wi.z <= 0.0 {
+            return Some((Color::splat(0.0), 0.0));
+        }
+        Some((self.reflectance(rec, wo, wi) * wi.z / PI, wi.z / PI))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material::tests::assert_eval_matches_scatter;
+    use crate::material::Lambertian;
+    use crate::types::Point3;
+
+    const TOLERANCE: crate::Float = 1e3 * crate::Float::EPSILON;
+
+    fn hit(material: &Arc<dyn Material>, outgoing: Vec3) -> (Ray, HitRecord) {
+        let outgoing = outgoing.normalize();
+        (
+            Ray::new(outgoing, -outgoing),
+            HitRecord::new(&Point3::ZERO, &Vec3::Z, 1.0, material),
+        )
+    }
+
+    #[test]
+    fn white_furnace() {
+        const SAMPLES: usize = 200_000;
+        for roughness in [0.25, 0.5, 1.0] {
+            let white: Arc<dyn Material> = Arc::new(OrenNayar::new_energy_preserving_with_color(
+                Color::splat(1.0),
+                roughness,
+            ));
+            for outgoing in [Vec3::Z, Vec3::new(1.0, 0.0, 1.0), Vec3::new(1.0, 0.3, 0.1)] {
+                let (r, rec) = hit(&white, outgoing);
+                let reflected = (0..SAMPLES)
+                    .map(|_| white.scatter(&r, &rec).unwrap().attenuation)
+                    .fold(Color::splat(0.0), |sum, attenuation| sum + attenuation)
+                    / SAMPLES as crate::Float;
+                assert!(
+                    (reflected - Color::splat(1.0)).abs().max_element() < 0.02,
+                    "roughness {} from {} reflects {}",
+                    roughness,
+                    outgoing,
+                    reflected
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn smooth_is_lambertian() {
+        let albedo = Color::new(0.8, 0.5, 0.2);
+        let lambertian: Arc<dyn Material> = Arc::new(Lambertian::new_with_color(albedo));
+        let smooth: [Arc<dyn Material>; 2] = [
+            Arc::new(OrenNayar::new_with_color(albedo, 0.0)),
+            Arc::new(OrenNayar::new_energy_preserving_with_color(albedo, 0.0)),
+        ];
+        for material in smooth {
+            for _ in 0..1000 {
+                let (r, rec) = hit(&material, utils::rand_cosine_direction());
+                let direction = utils::rand_vec3_unit();
+                let (f, pdf) = material.eval(&r, &rec, direction).unwrap();
+                let (expected_f, expected_pdf) = lambertian.eval(&r, &rec, direction).unwrap();
+                assert!((f - expected_f).abs().max_element() < TOLERANCE);
+                assert!((pdf - expected_pdf).abs() < TOLERANCE);
+            }
+        }
+    }
+
+    #[test]
+    fn eval_matches_scatter() {
+        let albedo = Color::new(0.8, 0.5, 0.2);
+        let materials: [Arc<dyn Material>; 2] = [
+            Arc::new(OrenNayar::new_with_color(albedo, 0.5)),
+            Arc::new(OrenNayar::new_energy_preserving_with_color(albedo, 0.7)),
+        ];
+        for material in materials {
+            assert_eval_matches_scatter(material.clone(), Vec3::new(0.3, 1.0, 0.2), true);
+            assert_eval_matches_scatter(material, Vec3::new(1.0, 0.2, 0.0), true);
+        }
+    }
+}
@@ -0,0 +1,371 @@
+use std::sync::Arc;
+
+use crate::color::{self, Color};
+use crate::hittable::HitRecord;
+use crate::onb::Onb;
+use crate::ray::{self, Ray};
+use crate::texture::{SolidColor, Texture};
+use crate::types::PI;
+use crate::utils;
+use crate::Vec3;
+
+use super::fresnel;
+use super::microfacet::TrowbridgeReitz;
+use super::rough_dielectric;
+use super::{material_id, Interior, Ior, Material, Scatter};
+
+/// Disney style uber material (Burley, Physically Based Shading at Disney),
+/// covering plastics, metals, cloth, lacquered surfaces and glass with one
+/// set of artist friendly parameters.
+///
+/// Every parameter is a texture, scalar ones read the red channel and are
+/// expected in [0, 1]. The material is a mix of a rough dielectric, weighted
+/// by `(1 - metallic) * transmission`, and a reflective part made of
+/// diffuse with sheen, GGX specular and a GGX clear coat. One lobe is picked
+/// in proportion to its estimated reflectance and the reflective lobes are
+/// combined with one sample MIS, so a glossy highlight on a diffuse base
+/// does not produce fireflies.
+pub struct Principled {
+    pub base_color: Arc<dyn Texture>,
+    pub metallic: Arc<dyn Texture>,
+    pub roughness: Arc<dyn Texture>,
+    /// Stretches the specular highlight along the tangent
+    pub anisotropic: Arc<dyn Texture>,
+    /// Dielectric reflectance at normal incidence, 0.5 maps to 4%
+    pub specular: Arc<dyn Texture>,
+    /// Tints the dielectric specular towards the base color
+    pub specular_tint: Arc<dyn Texture>,
+    /// Retro reflective rim for cloth
+    pub sheen: Arc<dyn Texture>,
+    pub sheen_tint: Arc<dyn Texture>,
+    /// Strength of a second, colorless specular layer
+    pub clearcoat: Arc<dyn Texture>,
+    pub clearcoat_gloss: Arc<dyn Texture>,
+    /// None for opaque surfaces, which then never take part in dielectric nesting
+    pub transmission: Option<Arc<dyn Texture>>,
+    /// Index of refraction of the transmissive part
+    pub ir: crate::Float,
+    pub emission: Arc<dyn Texture>,
+}
+
+fn constant(value: crate::Float) -> Arc<dyn Texture> {
+    Arc::new(SolidColor::new(Color::splat(value)))
+}
+
+impl Default for Principled {
+    fn default() -> Self {
+        Self {
+            base_color: constant(0.8),
+            metallic: constant(0.0),
+            roughness: constant(0.5),
+            anisotropic: constant(0.0),
+            specular: constant(0.5),
+            specular_tint: constant(0.0),
+            sheen: constant(0.0),
+            sheen_tint: constant(0.5),
+            clearcoat: constant(0.0),
+            clearcoat_gloss: constant(1.0),
+            transmission: None,
+            ir: 1.5,
+            emission: constant(0.0),
+        }
+    }
+}
+
+impl Principled {
+    pub fn new(
+        base_color: Arc<dyn Texture>,
+        metallic: Arc<dyn Texture>,
+        roughness: Arc<dyn Texture>,
+    ) -> Self {
+        Self {
+            base_color,
+            metallic,
+            roughness,
+            ..Default::default()
+        }
+    }
+
+    pub fn new_with_color(
+        base_color: Color,
+        metallic: crate::Float,
+        roughness: crate::Float,
+    ) -> Self {
+        Self::new(
+            Arc::new(SolidColor::new(base_color)),
+            constant(metallic),
+            constant(roughness),
+        )
+    }
+
+    /// Glass with the given color, index of refraction and roughness.
+    pub fn new_glass(base_color: Color, ir: crate::Float, roughness: crate::Float) -> Self {
+        Self {
+            transmission: Some(constant(1.0)),
+            ir,
+            ..Self::new_with_color(base_color, 0.0, roughness)
+        }
+    }
+
+    /// Index on the far side of the surface over the one `r` travels in.
+    fn eta(&self, r: &Ray, rec: &HitRecord) -> crate::Float {
+        if rec.front_face {
+            self.ir / r.interiors.ir(r.wavelength)
+        } else {
+            r.interiors
+                .ir_outside(material_id(rec.material.as_ref()), r.wavelength)
+                / self.ir
+        }
+    }
+
+    fn lobes(&self, rec: &HitRecord) -> Lobes {
+        let (u, v, p) = (rec.u, rec.v, rec.p);
+        let scalar = |texture: &Arc<dyn Texture>| texture.scalar(u, v, p).clamp(0.0, 1.0);
+
+        let base_color = self.base_color.value_at(rec);
+        let metallic = scalar(&self.metallic);
+        let roughness = scalar(&self.roughness);
+        let transmission = self.transmission.as_ref().map_or(0.0, scalar);
+
+        // hue and saturation of the base color, without its luminance
+        let luminance = color::luminance(base_color);
+        let tint = if luminance > 0.0 {
+            base_color / luminance
+        } else {
+            Color::splat(1.0)
+        };
+
+        let aspect = (1.0 - 0.9 * scalar(&self.anisotropic)).sqrt();
+        let alpha = roughness * roughness;
+        let dielectric_f0 = 0.08
+            * scalar(&self.specular)
+            * Color::splat(1.0).lerp(tint, scalar(&self.specular_tint));
+
+        let transmission_weight = (1.0 - metallic) * transmission;
+        // the diffuse weight relative to the reflective part of the mix
+        let diffuse_weight = if transmission_weight < 1.0 {
+            (1.0 - metallic) * (1.0 - transmission) / (1.0 - transmission_weight)
+        } else {
+            0.0
+        };
+
+        Lobes {
+            base_color,
+            roughness,
+            diffuse_weight,
+            sheen: scalar(&self.sheen) * Color::splat(1.0).lerp(tint, scalar(&self.sheen_tint)),
+            specular_f0: dielectric_f0.lerp(base_color, metallic),
+            specular: TrowbridgeReitz::new(alpha / aspect, alpha * aspect),
+            clearcoat: 0.25 * scalar(&self.clearcoat),
+            clearcoat_distribution: {
+                let alpha = 0.1 + (0.001 - 0.1) * scalar(&self.clearcoat_gloss);
+                TrowbridgeReitz::new(alpha, alpha)
+            },
+            transmission_weight,
+        }
+    }
+}
+
+/// Parameters of the lobes at a hit point, in the local shading frame.
+struct Lobes {
+    base_color: Color,
+    roughness: crate::Float,
+    diffuse_weight: crate::Float,
+    sheen: Color,
+    specular_f0: Color,
+    specular: TrowbridgeReitz,
+    clearcoat: crate::Float,
+    clearcoat_distribution: TrowbridgeReitz,
+    transmission_weight: crate::Float,
+}
+
+/// Reflectance at normal incidence of the clear coat, an index of 1.5
+const CLEARCOAT_F0: crate::Float = 0.04;
+
+impl Lobes {
+    /// Probabilities of sampling the diffuse, specular and clear coat lobes.
+    fn sampling_weights(&self, wo: Vec3) -> [crate::Float; 3] {
+        let diffuse = self.diffuse_weight * color::luminance(self.base_color + self.sheen);
+        let specular = color::luminance(fresnel::schlick(wo.z, self.specular_f0));
+        let clearcoat = self.clearcoat * fresnel::schlick(wo.z, Color::splat(CLEARCOAT_F0)).x;
+        let total = diffuse + specular + clearcoat;
+        if total <= 0.0 {
+            return [1.0, 0.0, 0.0];
+        }
+        [diffuse / total, specular / total, clearcoat / total]
+    }
+
+    /// Sum of the reflective lobes, without the cosine.
+    fn f(&self, wo: Vec3, wi: Vec3) -> Color {
+        let wh = (wo + wi).normalize();
+        let cos_theta_d = wi.dot(wh);
+
+        let mut f = Color::splat(0.0);
+        if self.diffuse_weight > 0.0 {
+            // retro reflection at grazing angles of rough surfaces
+            let fd90 = 0.5 + 2.0 * self.roughness * cos_theta_d * cos_theta_d;
+            let schlick_weight = |cos: crate::Float| (1.0 - cos).clamp(0.0, 1.0).powi(5);
+            let fd = (1.0 + (fd90 - 1.0) * schlick_weight(wi.z))
+                * (1.0 + (fd90 - 1.0) * schlick_weight(wo.z));
+            let diffuse = self.base_color / PI * fd;
+            let sheen = self.sheen * schlick_weight(cos_theta_d);
+            f += self.diffuse_weight * (diffuse + sheen);
+        }
+
+        let denominator = 4.0 * wo.z * wi.z;
+        f += fresnel::schlick(cos_theta_d, self.specular_f0)
+            * (self.specular.d(wh) * self.specular.g(wo, wi) / denominator);
+        if self.clearcoat > 0.0 {
+            let distribution = &self.clearcoat_distribution;
+            let fresnel = fresnel::schlick(cos_theta_d, Color::splat(CLEARCOAT_F0)).x;
+            f += Color::splat(
+                self.clearcoat * fresnel * distribution.d(wh) * distribution.g(wo, wi)
+                    / denominator,
+            );
+        }
+        f
+    }
+
+    /// Combined density of sampling `wi` from any of the reflective lobes.
+    fn pdf(&self, wo: Vec3, wi: Vec3, weights: [crate::Float; 3]) -> crate::Float {
+        let wh = (wo + wi).normalize();
+        // Jacobian of the reflection about the half vector
+        let reflect_jacobian = 4.0 * wo.dot(wh);
+        weights[0] * wi.z / PI
+            + weights[1] * self.specular.d_visible(wo, wh) / reflect_jacobian
+            + weights[2] * self.clearcoat_distribution.d_visible(wo, wh) / reflect_jacobian
+    }
+
+    fn sample_reflection(&self, wo: Vec3) -> Option<(Vec3, Color)> {
+        let weights = self.sampling_weights(wo);
+        let choice = utils::gen_float();
+        let wi = if choice < weights[0] {
+            utils::rand_cosine_direction()
+        } else {
+            let distribution = if choice < weights[0] + weights[1] {
+                &self.specular
+            } else {
+                &self.clearcoat_distribution
+            };
+            let wm =
+                distribution.sample_visible_normal(wo, (utils::gen_float(), utils::gen_float()));
+            ray::reflect(-wo, wm)
+        };
+        if wi.z <= 0.0 {
+            return None;
+        }
+
+        let pdf = self.pdf(wo, wi, weights);
+        if pdf <= 0.0 {
+            return None;
+        }
+        Some((wi, self.f(wo, wi) * wi.z / pdf))
+    }
+}
+
+impl Material for Principled {
+    fn scatter(&self, r: &Ray, rec: &HitRecord) -> Option<Scatter> {
+        let lobes = self.lobes(rec);
+        let frame = Onb::new_from_w_and_u(rec.normal, rec.dpdu);
+        let wo = frame.to_local(-r.direction().normalize());
+        if wo.z <= 0.0 {
+            return None;
+        }
+
+        // the transmissive and reflective parts are picked with their own
+        // weights, which then cancel out
+        let (wi, attenuation) = if utils::gen_float() < lobes.transmission_weight {
+            let distribution = TrowbridgeReitz::from_roughness(lobes.roughness, lobes.roughness);
+            let (wi, weight) = rough_dielectric::sample(&distribution, self.eta(r, rec), wo)?;
+            // light passing through is tinted, reflections are not
+            let tint = if wi.z < 0.0 {
+                lobes.base_color
+            } else {
+                Color::splat(1.0)
+            };
+            (wi, weight * tint)
+        } else {
+            lobes.sample_reflection(wo)?
+        };
+
+        Some(Scatter {
+            attenuation,
+            ray: Ray::new_with_time(rec.p, frame.to_world(wi), r.time()),
+        })
+    }
+
+    fn emitted(&self, r: &Ray, rec: &HitRecord) -> Color {
+        self.emission.value_at(rec)
+    }
+
+    fn eval(&self, r: &Ray, rec: &HitRecord, direction: Vec3) -> Option<(Color, crate::Float)> {
+        let lobes = self.lobes(rec);
+        let frame = Onb::new_from_w_and_u(rec.normal, rec.dpdu);
+        let wo = frame.to_local(-r.direction().normalize());
+        let wi = frame.to_local(direction.normalize());
+        if wo.z <= 0.0 {
+            return Some((Color::splat(0.0), 0.0));
+        }
+
+        let reflective_weight = 1.0 - lobes.transmission_weight;
+        let (mut f, mut pdf) = if wi.z > 0.0 && reflective_weight > 0.0 {
+            let weights = lobes.sampling_weights(wo);
+            (
+                reflective_weight * lobes.f(wo, wi) * wi.z,
+                reflective_weight * lobes.pdf(wo, wi, weights),
+            )
+        } else {
+            (Color::splat(0.0), 0.0)
+        };
+        if lobes.transmission_weight > 0.0 {
+            let distribution = TrowbridgeReitz::from_roughness(lobes.roughness, lobes.roughness);
+            if distribution.effectively_smooth() {
+                // smooth glass is only found by scatter
+                return None;
+            }
+            let (transmission_f, transmission_pdf) =
+                rough_dielectric::eval(&distribution, self.eta(r, rec), wo, wi);
+            let tint = if wi.z < 0.0 {
+                lobes.base_color
+            } else {
+                Color::splat(1.0)
+            };
+            f += lobes.transmission_weight * transmission_f * tint;
+            pdf += lobes.transmission_weight * transmission_pdf;
+        }
+        Some((f, pdf))
+    }
+
+    fn interior(&self) -> Option<Interior> {
+        self.transmission.as_ref().map(|_| Interior {
+            ior: Ior::Constant(self.ir),
+            absorption: Color::splat(0.0),
+            scattering: Color::splat(0.0),
+            priority: 0,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material::tests::assert_eval_matches_scatter;
+
+    #[test]
+    fn eval_matches_scatter() {
+        let plastic = Principled {
+            anisotropic: constant(0.6),
+            clearcoat: constant(0.8),
+            clearcoat_gloss: constant(0.0),
+            sheen: constant(0.5),
+            ..Principled::new_with_color(Color::new(0.8, 0.3, 0.1), 0.2, 0.4)
+        };
+        let plastic = Arc::new(plastic);
+        assert_eval_matches_scatter(plastic.clone(), Vec3::new(0.3, 1.0, 0.2), true);
+        assert_eval_matches_scatter(plastic, Vec3::new(2.0, 0.3, -1.0), true);
+
+        let glass = Arc::new(Principled::new_glass(Color::new(0.9, 0.8, 0.7), 1.5, 0.6));
+        assert_eval_matches_scatter(glass.clone(), Vec3::new(0.5, 1.0, 0.0), false);
+        assert_eval_matches_scatter(glass, Vec3::new(0.5, -1.0, 0.3), false);
+    }
+}
@@ -0,0 +1,210 @@
+use crate::color::Color;
+use crate::hittable::HitRecord;
+use crate::onb::Onb;
+use crate::ray::{self, Ray};
+use crate::Vec3;
+use crate::{spectrum, utils};
+
+use super::fresnel;
+use super::microfacet::TrowbridgeReitz;
+use super::{material_id, Interior, Ior, Material, Scatter};
+
+/// Frosted glass, GGX microfacet reflection and refraction
+/// (Walter et al., Microfacet Models for Refraction through Rough Surfaces).
+pub struct RoughDielectric {
+    ior: Ior,
+    distribution: TrowbridgeReitz,
+    absorption: Color,
+    priority: u32,
+}
+
+impl RoughDielectric {
+    /// * `ior` - Index of refraction inside the surface, see `Dielectric::new`
+    /// * `roughness` - Perceptual roughness, 0 gives smooth glass
+    pub fn new(ior: impl Into<Ior>, roughness: crate::Float) -> Self {
+        Self::new_with_absorption(ior, roughness, Color::splat(0.0), 0)
+    }
+
+    /// Colored frosted glass, see `Interior` for `absorption` and `priority`.
+    pub fn new_with_absorption(
+        ior: impl Into<Ior>,
+        roughness: crate::Float,
+        absorption: Color,
+        priority: u32,
+    ) -> Self {
+        Self {
+            ior: ior.into(),
+            distribution: TrowbridgeReitz::from_roughness(roughness, roughness),
+            absorption,
+            priority,
+        }
+    }
+}
+
+impl RoughDielectric {
+    /// Index on the far side of the surface over the one `r` travels in.
+    fn eta(&self, r: &Ray, rec: &HitRecord, wavelength: Option<crate::Float>) -> crate::Float {
+        let ir = self.ior.at(wavelength);
+        if rec.front_face {
+            ir / r.interiors.ir(wavelength)
+        } else {
+            r.interiors
+                .ir_outside(material_id(rec.material.as_ref()), wavelength)
+                / ir
+        }
+    }
+}
+
+impl Material for RoughDielectric {
+    fn scatter(&self, r: &Ray, rec: &HitRecord) -> Option<Scatter> {
+        // rec.normal faces the incoming ray, so eta is the index on the far
+        // side over the one the ray travels in
+        let (wavelength, tint) = if self.ior.is_dispersive() {
+            let (wavelength, tint) = spectrum::hero_wavelength(r.wavelength);
+            (Some(wavelength), tint)
+        } else {
+            (r.wavelength, Color::splat(1.0))
+        };
+        let frame = Onb::new_from_w(rec.normal);
+        let wo = frame.to_local(-r.direction().normalize());
+        let (wi, weight) = sample(&self.distribution, self.eta(r, rec, wavelength), wo)?;
+
+        let mut ray = Ray::new_with_time(rec.p, frame.to_world(wi), r.time());
+        ray.wavelength = wavelength;
+        Some(Scatter {
+            attenuation: weight * tint,
+            ray,
+        })
+    }
+
+    /// Dispersive glass picks the wavelength of the ray in `scatter`, then
+    /// white light is only found by it.
+    fn eval(&self, r: &Ray, rec: &HitRecord, direction: Vec3) -> Option<(Color, crate::Float)> {
+        if self.distribution.effectively_smooth()
+            || (self.ior.is_dispersive() && r.wavelength.is_none())
+        {
+            return None;
+        }
+        let frame = Onb::new_from_w(rec.normal);
+        let wo = frame.to_local(-r.direction().normalize());
+        let wi = frame.to_local(direction.normalize());
+        let eta = self.eta(r, rec, r.wavelength);
+        let (f, pdf) = eval(&self.distribution, eta, wo, wi);
+        Some((Color::splat(f), pdf))
+    }
+
+    fn interior(&self) -> Option<Interior> {
+        Some(Interior {
+            ior: self.ior,
+            absorption: self.absorption,
+            scattering: Color::splat(0.0),
+            priority: self.priority,
+        })
+    }
+}
+
+/// Sample reflection or refraction through a rough interface, `eta` being
+/// the index on the far side over the one on the side of `wo`.
+/// Returns the direction and f |cos| / pdf.
+pub(super) fn sample(
+    distribution: &TrowbridgeReitz,
+    eta: crate::Float,
+    wo: Vec3,
+) -> Option<(Vec3, crate::Float)> {
+    if wo.z <= 0.0 {
+        return None;
+    }
+
+    let wm = if distribution.effectively_smooth() {
+        Vec3::new(0.0, 0.0, 1.0)
+    } else {
+        distribution.sample_visible_normal(wo, (utils::gen_float(), utils::gen_float()))
+    };
+    let reflectance = fresnel::dielectric(wo.dot(wm), eta);
+
+    // Pick reflection or refraction with the Fresnel term as probability,
+    // which cancels it out of the weight.
+    let wi = if utils::gen_float() < reflectance {
+        let wi = ray::reflect(-wo, wm);
+        if wi.z <= 0.0 {
+            return None;
+        }
+        wi
+    } else {
+        let wi = ray::refract(-wo, wm, 1.0 / eta);
+        if wi.z >= 0.0 {
+            return None;
+        }
+        wi
+    };
+
+    // f |cos| / pdf of the visible normal sampling, the Jacobians of
+    // reflection and refraction cancel as well
+    let weight = if distribution.effectively_smooth() {
+        1.0
+    } else {
+        distribution.g(wo, wi) / distribution.g1(wo)
+    };
+    Some((wi, weight))
+}
+
+/// f |cos| and the density of `sample` returning `wi`, with `eta` as for
+/// `sample`. The distribution must not be effectively smooth.
+pub(super) fn eval(
+    distribution: &TrowbridgeReitz,
+    eta: crate::Float,
+    wo: Vec3,
+    wi: Vec3,
+) -> (crate::Float, crate::Float) {
+    if wo.z <= 0.0 || wi.z == 0.0 {
+        return (0.0, 0.0);
+    }
+    let reflect = wi.z > 0.0;
+    // generalized half vector
+    let wm = if reflect { wo + wi } else { wo + eta * wi };
+    let wm = match wm.try_normalize() {
+        Some(wm) if wm.z < 0.0 => -wm,
+        Some(wm) => wm,
+        None => return (0.0, 0.0),
+    };
+    // microfacets seen from behind
+    let (cos_o, cos_i) = (wo.dot(wm), wi.dot(wm));
+    if cos_o <= 0.0 || (cos_i > 0.0) != reflect {
+        return (0.0, 0.0);
+    }
+
+    let reflectance = fresnel::dielectric(cos_o, eta);
+    let d = distribution.d(wm);
+    let g = distribution.g(wo, wi);
+    let d_visible = distribution.d_visible(wo, wm);
+    if reflect {
+        (
+            reflectance * d * g / (4.0 * wo.z),
+            reflectance * d_visible / (4.0 * cos_o),
+        )
+    } else {
+        // Jacobian of the refraction about the half vector
+        let denominator = (cos_i + cos_o / eta).powi(2);
+        let transmittance = 1.0 - reflectance;
+        (
+            transmittance * d * g * (cos_i * cos_o).abs() / (wo.z * denominator),
+            transmittance * d_visible * cos_i.abs() / denominator,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material::tests::assert_eval_matches_scatter;
+    use std::sync::Arc;
+
+    #[test]
+    fn eval_matches_scatter() {
+        let frosted = Arc::new(RoughDielectric::new(1.5, 0.6));
+        assert_eval_matches_scatter(frosted.clone(), Vec3::new(0.3, 1.0, 0.2), true);
+        // from inside, with total internal reflection at grazing angles
+        assert_eval_matches_scatter(frosted.clone(), Vec3::new(0.3, -1.0, 0.2), true);
+        assert_eval_matches_scatter(frosted, Vec3::new(1.0, -0.5, 0.0), true);
+    }
+}
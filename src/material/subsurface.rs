@@ -0,0 +1,120 @@
+use crate::color::Color;
+use crate::hittable::HitRecord;
+use crate::onb::Onb;
+use crate::ray::Ray;
+use crate::Vec3;
+
+use super::microfacet::TrowbridgeReitz;
+use super::rough_dielectric;
+use super::{material_id, Interior, Ior, Material, Scatter};
+
+/// Skin, wax, marble or milk: a dielectric surface over a scattering
+/// interior.
+///
+/// Light refracted inside takes a random walk, the integrator scatters it
+/// through the interior until it leaves the closed surface again or is
+/// absorbed, see `InteriorStack::sample_medium`.
+pub struct Subsurface {
+    ir: crate::Float,
+    distribution: TrowbridgeReitz,
+    absorption: Color,
+    scattering: Color,
+}
+
+impl Subsurface {
+    /// * `albedo` - Color of the surface once light scattered many times inside
+    /// * `mean_free_path` - Average distance between scattering events per
+    ///   channel, in world units, larger values look more translucent
+    /// * `ir` - Index of refraction of the surface
+    /// * `roughness` - Perceptual roughness of the surface
+    pub fn new(
+        albedo: Color,
+        mean_free_path: Color,
+        ir: crate::Float,
+        roughness: crate::Float,
+    ) -> Self {
+        // single scattering albedo giving that multiple scattering albedo
+        // (Chiang et al., Practical and Controllable Subsurface Scattering
+        // for Production Path Tracing)
+        let single = |albedo: crate::Float| {
+            let albedo = albedo.clamp(0.0, 0.999);
+            let t = 4.09712 + 4.20863 * albedo
+                - (9.59217 + 41.6808 * albedo + 17.7126 * albedo * albedo).sqrt();
+            1.0 - t * t
+        };
+        let single_albedo = Color::new(single(albedo.x), single(albedo.y), single(albedo.z));
+        let extinction = Color::splat(1.0) / mean_free_path.max(Color::splat(1e-6));
+
+        Self {
+            ir,
+            distribution: TrowbridgeReitz::from_roughness(roughness, roughness),
+            absorption: extinction * (Color::splat(1.0) - single_albedo),
+            scattering: extinction * single_albedo,
+        }
+    }
+}
+
+impl Subsurface {
+    /// Index on the far side of the surface over the one `r` travels in.
+    fn eta(&self, r: &Ray, rec: &HitRecord) -> crate::Float {
+        if rec.front_face {
+            self.ir / r.interiors.ir(r.wavelength)
+        } else {
+            r.interiors
+                .ir_outside(material_id(rec.material.as_ref()), r.wavelength)
+                / self.ir
+        }
+    }
+}
+
+impl Material for Subsurface {
+    fn scatter(&self, r: &Ray, rec: &HitRecord) -> Option<Scatter> {
+        let frame = Onb::new_from_w(rec.normal);
+        let wo = frame.to_local(-r.direction().normalize());
+        let (wi, weight) = rough_dielectric::sample(&self.distribution, self.eta(r, rec), wo)?;
+
+        Some(Scatter {
+            attenuation: Color::splat(weight),
+            ray: Ray::new_with_time(rec.p, frame.to_world(wi), r.time()),
+        })
+    }
+
+    /// Only the surface, light scattered inside is found by the walk.
+    fn eval(&self, r: &Ray, rec: &HitRecord, direction: Vec3) -> Option<(Color, crate::Float)> {
+        if self.distribution.effectively_smooth() {
+            return None;
+        }
+        let frame = Onb::new_from_w(rec.normal);
+        let wo = frame.to_local(-r.direction().normalize());
+        let wi = frame.to_local(direction.normalize());
+        let (f, pdf) = rough_dielectric::eval(&self.distribution, self.eta(r, rec), wo, wi);
+        Some((Color::splat(f), pdf))
+    }
+
+    fn interior(&self) -> Option<Interior> {
+        Some(Interior {
+            ior: Ior::Constant(self.ir),
+            absorption: self.absorption,
+            scattering: self.scattering,
+            priority: 0,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material::tests::assert_eval_matches_scatter;
+    use std::sync::Arc;
+
+    #[test]
+    fn eval_matches_scatter() {
+        let wax = Arc::new(Subsurface::new(
+            Color::new(0.9, 0.7, 0.5),
+            Color::splat(0.1),
+            1.4,
+            0.5,
+        ));
+        assert_eval_matches_scatter(wax, Vec3::new(0.3, 1.0, 0.2), true);
+    }
+}
@@ -0,0 +1,175 @@
+use std::sync::Arc;
+
+use crate::color::Color;
+use crate::hittable::HitRecord;
+use crate::spectrum;
+use crate::texture::{SolidColor, Texture};
+
+use super::fresnel;
+
+/// Wavelengths the film reflectance is integrated over for rays that have
+/// not been split, interference fringes of films up to a micrometre thick
+/// are a few periods across the visible range.
+const SPECTRAL_SAMPLES: usize = 32;
+
+/// Transparent coating thinner than a few wavelengths, soap bubbles, oil
+/// slicks or tempered steel, whose reflections interfere into colors.
+#[derive(Clone)]
+pub struct ThinFilm {
+    ir: crate::Float,
+    /// in [0, 1] from the red channel, mapped to the thickness range
+    thickness: Arc<dyn Texture>,
+    min_thickness: crate::Float,
+    max_thickness: crate::Float,
+}
+
+impl ThinFilm {
+    /// * `thickness` - In nanometres
+    pub fn new(ir: crate::Float, thickness: crate::Float) -> Self {
+        Self::new_with_texture(
+            ir,
+            Arc::new(SolidColor::new(Color::splat(1.0))),
+            thickness,
+            thickness,
+        )
+    }
+
+    /// Film whose thickness varies between `min_thickness` and
+    /// `max_thickness` nanometres with the texture.
+    pub fn new_with_texture(
+        ir: crate::Float,
+        thickness: Arc<dyn Texture>,
+        min_thickness: crate::Float,
+        max_thickness: crate::Float,
+    ) -> Self {
+        Self {
+            ir,
+            thickness,
+            min_thickness,
+            max_thickness,
+        }
+    }
+
+    /// Reflectance of the film over whatever is below it, as RGB.
+    ///
+    /// * `wavelength` - Of the ray if it was split, otherwise the reflectance
+    ///   is integrated over the spectrum
+    /// * `media` - Indices of refraction at a wavelength: the one of the side
+    ///   the light comes from and the complex one of the substrate
+    pub(super) fn reflectance(
+        &self,
+        rec: &HitRecord,
+        cos_theta_i: crate::Float,
+        wavelength: Option<crate::Float>,
+        media: impl Fn(crate::Float) -> (crate::Float, crate::Float, crate::Float),
+    ) -> Color {
+        let t = self.thickness.scalar(rec.u, rec.v, rec.p).clamp(0.0, 1.0);
+        let thickness = self.min_thickness + t * (self.max_thickness - self.min_thickness);
+        let at = |wavelength: crate::Float| {
+            let (outside_ir, substrate_eta, substrate_k) = media(wavelength);
+            fresnel::thin_film(
+                cos_theta_i,
+                outside_ir,
+                self.ir,
+                thickness,
+                substrate_eta,
+                substrate_k,
+                wavelength,
+            )
+        };
+
+        match wavelength {
+            Some(wavelength) => Color::splat(at(wavelength)),
+            None => {
+                spectrum::wavelengths(SPECTRAL_SAMPLES)
+                    .map(|wavelength| at(wavelength) * spectrum::wavelength_to_rgb(wavelength))
+                    .fold(Color::splat(0.0), |sum, color| sum + color)
+                    / SPECTRAL_SAMPLES as crate::Float
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material::{Lambertian, Material};
+    use crate::types::Point3;
+    use crate::Vec3;
+
+    const TOLERANCE: crate::Float = 1e3 * crate::Float::EPSILON;
+
+    fn reflectance(
+        film: &ThinFilm,
+        cos_theta_i: crate::Float,
+        wavelength: crate::Float,
+        media: (crate::Float, crate::Float, crate::Float),
+    ) -> crate::Float {
+        let material: Arc<dyn Material> = Arc::new(Lambertian::new_with_color(Color::splat(0.5)));
+        let rec = HitRecord::new(&Point3::ZERO, &Vec3::Y, 1.0, &material);
+        film.reflectance(&rec, cos_theta_i, Some(wavelength), |_| media)
+            .x
+    }
+
+    #[test]
+    fn vanishing_film_is_fresnel() {
+        for cos_theta_i in [1.0, 0.8, 0.5, 0.2, 0.05] {
+            for wavelength in [450.0, 550.0, 650.0] {
+                // no thickness, the substrate is seen directly
+                let glass = reflectance(
+                    &ThinFilm::new(1.8, 0.0),
+                    cos_theta_i,
+                    wavelength,
+                    (1.0, 1.5, 0.0),
+                );
+                let expected = fresnel::dielectric(cos_theta_i, 1.5);
+                assert!(
+                    (glass - expected).abs() < TOLERANCE,
+                    "{} != {}",
+                    glass,
+                    expected
+                );
+
+                let metal = reflectance(
+                    &ThinFilm::new(1.8, 0.0),
+                    cos_theta_i,
+                    wavelength,
+                    (1.0, 0.2, 3.0),
+                );
+                let expected =
+                    fresnel::conductor(cos_theta_i, Color::splat(0.2), Color::splat(3.0)).x;
+                assert!(
+                    (metal - expected).abs() < TOLERANCE,
+                    "{} != {}",
+                    metal,
+                    expected
+                );
+
+                // a film matching the outside is not there at any thickness
+                let matched = reflectance(
+                    &ThinFilm::new(1.0, 300.0),
+                    cos_theta_i,
+                    wavelength,
+                    (1.0, 1.5, 0.0),
+                );
+                let expected = fresnel::dielectric(cos_theta_i, 1.5);
+                assert!(
+                    (matched - expected).abs() < TOLERANCE,
+                    "{} != {}",
+                    matched,
+                    expected
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn interference() {
+        // a quarter wave thick film of index sqrt(n) between air and glass
+        // of index n cancels the reflection
+        let ir = crate::Float::sqrt(1.5);
+        let film = ThinFilm::new(ir, 550.0 / (4.0 * ir));
+        assert!(reflectance(&film, 1.0, 550.0, (1.0, 1.5, 0.0)) < TOLERANCE);
+        assert!(reflectance(&film, 1.0, 400.0, (1.0, 1.5, 0.0)) > 1e-3);
+    }
+}
@@ -0,0 +1,45 @@
+use crate::Vec3;
+
+/// Orthonormal basis, `w` is the normal and the z axis of the local frame.
+pub struct Onb {
+    pub u: Vec3,
+    pub v: Vec3,
+    pub w: Vec3,
+}
+
+impl Onb {
+    /// Arbitrary tangents around `w`, which must be normalized
+    /// (Duff et al., Building an Orthonormal Basis, Revisited).
+    pub fn new_from_w(w: Vec3) -> Self {
+        let sign = (1.0 as crate::Float).copysign(w.z);
+        let a = -1.0 / (sign + w.z);
+        let b = w.x * w.y * a;
+        Self {
+            u: Vec3::new(1.0 + sign * w.x * w.x * a, sign * b, -sign * w.x),
+            v: Vec3::new(b, sign + w.y * w.y * a, -w.y),
+            w,
+        }
+    }
+
+    /// `u` along `tangent` projected onto the plane of `w`, e.g. the `dpdu`
+    /// of a hit for anisotropic materials. Arbitrary tangents when it is
+    /// degenerate.
+    pub fn new_from_w_and_u(w: Vec3, tangent: Vec3) -> Self {
+        match (tangent - tangent.dot(w) * w).try_normalize() {
+            Some(u) => Self {
+                u,
+                v: w.cross(u),
+                w,
+            },
+            None => Self::new_from_w(w),
+        }
+    }
+
+    pub fn to_world(&self, a: Vec3) -> Vec3 {
+        a.x * self.u + a.y * self.v + a.z * self.w
+    }
+
+    pub fn to_local(&self, a: Vec3) -> Vec3 {
+        Vec3::new(a.dot(self.u), a.dot(self.v), a.dot(self.w))
+    }
+}
@@ -0,0 +1,240 @@
+//! Real root finding for low order polynomials.
+//!
+//! Closed form solutions lose a lot of precision when coefficients differ by
+//! orders of magnitude, which is exactly what happens with grazing rays, so
+//! every root is polished with a few Newton iterations on the original
+//! polynomial before being returned.
+
+const EPSILON: crate::Float = 1e-9;
+const NEWTON_ITERATIONS: usize = 4;
+
+/// Real roots of `a x^2 + b x + c`, in ascending order.
+pub fn solve_quadratic(a: crate::Float, b: crate::Float, c: crate::Float) -> Vec<crate::Float> {
+    if a.abs() < EPSILON {
+        if b.abs() < EPSILON {
+            return vec![];
+        }
+        return vec![-c / b];
+    }
+    let discriminant = b * b - 4.0 * a * c;
+    if discriminant < 0.0 {
+        return vec![];
+    }
+    // avoid cancellation between -b and sqrt(discriminant)
+    let q = -0.5 * (b + b.signum() * discriminant.sqrt());
+    let mut roots = if q.abs() < EPSILON {
+        vec![-b / (2.0 * a)]
+    } else {
+        vec![q / a, c / q]
+    };
+    roots.sort_unstable_by(|a, b| a.partial_cmp(b).unwrap());
+    roots
+}
+
+/// Real roots of `x^3 + a x^2 + b x + c`, in ascending order.
+pub fn solve_cubic(a: crate::Float, b: crate::Float, c: crate::Float) -> Vec<crate::Float> {
+    // depressed cubic t^3 + p t + q with x = t - a / 3
+    let a_3 = a / 3.0;
+    let p = b - a * a_3;
+    let q = 2.0 * a_3 * a_3 * a_3 - a_3 * b + c;
+
+    let mut roots = if p.abs() < EPSILON {
+        vec![-q.cbrt()]
+    } else {
+        let discriminant = (q / 2.0).powi(2) + (p / 3.0).powi(3);
+        if discriminant > 0.0 {
+            let sqrtd = discriminant.sqrt();
+            vec![(-q / 2.0 + sqrtd).cbrt() + (-q / 2.0 - sqrtd).cbrt()]
+        } else {
+            // three real roots, trigonometric form
+            let m = 2.0 * (-p / 3.0).sqrt();
+            let theta = (3.0 * q / (p * m)).clamp(-1.0, 1.0).acos() / 3.0;
+            (0..3)
+                .map(|k| m * (theta - 2.0 * crate::types::PI * k as crate::Float / 3.0).cos())
+                .collect()
+        }
+    };
+
+    for root in roots.iter_mut() {
+        *root = polish(&[1.0, a, b, c], *root - a_3);
+    }
+    roots.sort_unstable_by(|a, b| a.partial_cmp(b).unwrap());
+    roots
+}
+
+/// Real roots of `c[0] x^4 + c[1] x^3 + c[2] x^2 + c[3] x + c[4]`, in ascending order.
+pub fn solve_quartic(coefficients: [crate::Float; 5]) -> Vec<crate::Float> {
+    let [c4, c3, c2, c1, c0] = coefficients;
+    if c4.abs() < EPSILON {
+        let mut roots = solve_cubic_general(c3, c2, c1, c0);
+        for root in roots.iter_mut() {
+            *root = polish(&coefficients, *root);
+        }
+        return roots;
+    }
+
+    let a = c3 / c4;
+    let b = c2 / c4;
+    let c = c1 / c4;
+    let d = c0 / c4;
+
+    // depressed quartic y^4 + p y^2 + q y + r with x = y - a / 4
+    let a_4 = a / 4.0;
+    let a_4_sqrd = a_4 * a_4;
+    let p = b - 6.0 * a_4_sqrd;
+    let q = c - 2.0 * b * a_4 + 8.0 * a_4 * a_4_sqrd;
+    let r = d - c * a_4 + b * a_4_sqrd - 3.0 * a_4_sqrd * a_4_sqrd;
+
+    let mut depressed_roots = vec![];
+    if q.abs() < EPSILON {
+        // biquadratic
+        for z in solve_quadratic(1.0, p, r) {
+            if z >= 0.0 {
+                depressed_roots.push(z.sqrt());
+                depressed_roots.push(-z.sqrt());
+            }
+        }
+    } else {
+        // Ferrari: the resolvent cubic always has a positive root when q != 0
+        let m = match solve_cubic(p, p * p / 4.0 - r, -q * q / 8.0).last() {
+            Some(&m) if m > 0.0 => m,
+            _ => return vec![],
+        };
+        let s = (2.0 * m).sqrt();
+        let q_term = q / (2.0 * s);
+        depressed_roots.extend(solve_quadratic(1.0, -s, p / 2.0 + m + q_term));
+        depressed_roots.extend(solve_quadratic(1.0, s, p / 2.0 + m - q_term));
+    }
+
+    let mut roots: Vec<crate::Float> = depressed_roots
+        .into_iter()
+        .map(|y| polish(&[1.0, a, b, c, d], y - a_4))
+        .collect();
+    roots.sort_unstable_by(|a, b| a.partial_cmp(b).unwrap());
+    roots
+}
+
+fn solve_cubic_general(
+    a: crate::Float,
+    b: crate::Float,
+    c: crate::Float,
+    d: crate::Float,
+) -> Vec<crate::Float> {
+    if a.abs() < EPSILON {
+        solve_quadratic(b, c, d)
+    } else {
+        solve_cubic(b / a, c / a, d / a)
+    }
+}
+
+/// Refine a root with Newton iterations, keeping the initial guess if the
+/// derivative vanishes or an iteration makes things worse.
+fn polish(coefficients: &[crate::Float], x: crate::Float) -> crate::Float {
+    let mut x = x;
+    let mut value = evaluate(coefficients, x).0;
+    for _ in 0..NEWTON_ITERATIONS {
+        let (f, df) = evaluate(coefficients, x);
+        if df == 0.0 {
+            break;
+        }
+        let next = x - f / df;
+        let next_value = evaluate(coefficients, next).0;
+        if next_value.abs() > value.abs() {
+            break;
+        }
+        x = next;
+        value = next_value;
+    }
+    x
+}
+
+/// Horner evaluation of the polynomial and its derivative,
+/// coefficients ordered from highest degree down.
+fn evaluate(coefficients: &[crate::Float], x: crate::Float) -> (crate::Float, crate::Float) {
+    let mut f = 0.0;
+    let mut df = 0.0;
+    for &coefficient in coefficients {
+        df = df * x + f;
+        f = f * x + coefficient;
+    }
+    (f, df)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Error allowed for simple roots, about the rounding of the coefficients.
+    const SIMPLE: crate::Float = 1e4 * crate::Float::EPSILON;
+
+    /// Coefficients of the monic polynomial with the given roots.
+    fn from_roots(roots: [crate::Float; 4]) -> [crate::Float; 5] {
+        let mut c = [1.0, 0.0, 0.0, 0.0, 0.0];
+        for (degree, root) in roots.iter().enumerate() {
+            for i in (1..=degree + 1).rev() {
+                c[i] -= root * c[i - 1];
+            }
+        }
+        c
+    }
+
+    fn assert_has_root(roots: &[crate::Float], expected: crate::Float, tolerance: crate::Float) {
+        assert!(
+            roots.iter().any(|r| (r - expected).abs() < tolerance),
+            "{} not in {:?}",
+            expected,
+            roots
+        );
+    }
+
+    #[test]
+    fn quartic_distinct_roots() {
+        let roots = solve_quartic(from_roots([1.0, 2.0, 3.0, 4.0]));
+        assert_eq!(roots.len(), 4);
+        for (root, expected) in roots.iter().zip([1.0, 2.0, 3.0, 4.0]) {
+            assert!((root - expected).abs() < SIMPLE);
+        }
+    }
+
+    #[test]
+    fn quartic_repeated_roots() {
+        let roots = solve_quartic(from_roots([1.0, 1.0, -2.0, 3.0]));
+        // a double root only has half the digits
+        let double = 1e2 * crate::Float::EPSILON.sqrt();
+        assert_has_root(&roots, 1.0, double);
+        assert_has_root(&roots, -2.0, SIMPLE);
+        assert_has_root(&roots, 3.0, SIMPLE);
+
+        let roots = solve_quartic(from_roots([0.5, 0.5, 0.5, 0.5]));
+        assert_has_root(&roots, 0.5, 10.0 * crate::Float::EPSILON.powf(0.25));
+    }
+
+    #[test]
+    fn quartic_near_double_roots() {
+        let coefficients = from_roots([1.0, 1.0 + 1e-6, -2.0, 5.0]);
+        let roots = solve_quartic(coefficients);
+        assert_has_root(&roots, 1.0, 1e-6 + 1e2 * crate::Float::EPSILON.sqrt());
+        assert_has_root(&roots, -2.0, SIMPLE);
+        assert_has_root(&roots, 5.0, SIMPLE);
+        for root in roots {
+            let (value, _) = evaluate(&coefficients, root);
+            assert!(value.abs() < 1e2 * SIMPLE, "residual {} at {}", value, root);
+        }
+    }
+
+    #[test]
+    fn quartic_biquadratic_and_degenerate() {
+        let roots = solve_quartic(from_roots([-2.0, -1.0, 1.0, 2.0]));
+        assert_eq!(roots.len(), 4);
+        assert_has_root(&roots, -2.0, SIMPLE);
+        assert_has_root(&roots, 1.0, SIMPLE);
+
+        // x^4 + 1 has no real roots
+        assert!(solve_quartic([1.0, 0.0, 0.0, 0.0, 1.0]).is_empty());
+
+        // leading coefficient zero, a cubic
+        let roots = solve_quartic([0.0, 1.0, -6.0, 11.0, -6.0]);
+        assert_eq!(roots.len(), 3);
+        assert_has_root(&roots, 3.0, SIMPLE);
+    }
+}
@@ -1,11 +1,22 @@
+use crate::material::InteriorStack;
 use crate::Point3;
 use crate::Vec3;
 
-#[derive(Default)]
+#[derive(Clone, Copy, Default)]
 pub struct Ray {
     pub orig: Point3,
     pub dir: Vec3,
     time: crate::Float,
+    /// dielectrics the ray travels inside, maintained by the integrator
+    pub interiors: InteriorStack,
+    /// in nanometres, once dispersion split the ray into a single wavelength
+    pub wavelength: Option<crate::Float>,
+    /// seen straight from the camera, before any bounce
+    pub from_camera: bool,
+    /// width of the cone of rays of a camera pixel at the origin
+    pub width: crate::Float,
+    /// how fast the cone widens, in radians
+    pub spread: crate::Float,
 }
 
 impl Ray {
@@ -14,6 +25,11 @@ impl Ray {
             orig: origin.to_owned(),
             dir: direction.to_owned(),
             time: 0.0,
+            interiors: InteriorStack::default(),
+            wavelength: None,
+            from_camera: false,
+            width: 0.0,
+            spread: 0.0,
         }
     }
 
@@ -22,6 +38,11 @@ impl Ray {
             orig: origin.to_owned(),
             dir: direction.to_owned(),
             time,
+            interiors: InteriorStack::default(),
+            wavelength: None,
+            from_camera: false,
+            width: 0.0,
+            spread: 0.0,
         }
     }
 
@@ -40,6 +61,20 @@ impl Ray {
     pub fn at(&self, t: crate::Float) -> Point3 {
         self.orig + t * self.dir
     }
+
+    /// Width of the cone of rays at `at(t)`.
+    pub fn width_at(&self, t: crate::Float) -> crate::Float {
+        self.width + self.spread * t * self.dir.length()
+    }
+
+    /// Continues the cone of `r` from `at(t)`, for a ray leaving the hit.
+    pub fn with_cone_of(self, r: &Ray, t: crate::Float) -> Self {
+        Self {
+            width: r.width_at(t),
+            spread: r.spread,
+            ..self
+        }
+    }
 }
 
 pub fn reflect(i: Vec3, n: Vec3) -> Vec3 {
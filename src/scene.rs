@@ -3,15 +3,21 @@ use std::sync::Arc;
 use crate::color::Color;
 use crate::hittable::Box;
 use crate::hittable::ConstantMedium;
+use crate::hittable::Heightfield;
 use crate::hittable::HittableList;
 use crate::hittable::MovingSphere;
+use crate::hittable::Plane;
 use crate::hittable::Sphere;
+use crate::hittable::{Hittable, Quad, Triangle};
 use crate::hittable::{RotateY, Translate};
 use crate::hittable::{XYRect, XZRect, YZRect};
+use crate::light::{AreaLight, DirectionalLight, LightList, PointLight, SpotLight};
+use crate::material::Conductor;
 use crate::material::Dielectric;
-use crate::material::Metal;
+use crate::material::Subsurface;
 use crate::material::{DiffuseLight, Lambertian};
-use crate::texture::{CheckerTexture, ImageTexture, NoiseTexture};
+use crate::perlin::Perlin;
+use crate::texture::{CheckerTexture, ImageTexture, NoiseTexture, UvCheckerTexture};
 use crate::types::Point3;
 use crate::Vec3;
 use crate::{material, utils};
@@ -19,14 +25,15 @@ use crate::{material, utils};
 pub fn random_scene() -> HittableList {
     let mut world = HittableList::new();
 
-    let checker = Arc::new(CheckerTexture::new_with_color(
+    let checker = Arc::new(UvCheckerTexture::new_with_color(
         Color::new(0.2, 0.3, 0.1),
         Color::splat(0.9),
+        10.0,
     ));
     let ground_material = Arc::new(material::Lambertian::new(checker));
-    world.add(Arc::new(Sphere::new(
-        Point3::new(0.0, -1000.0, 0.0),
-        1000.0,
+    world.add(Arc::new(Plane::new(
+        Point3::splat(0.0),
+        Vec3::new(0.0, 1.0, 0.0),
         ground_material,
     )));
 
@@ -55,8 +62,9 @@ pub fn random_scene() -> HittableList {
             } else if choose_mat < 0.85 {
                 // metal
                 let albedo = utils::rand_vec3_range(0.5, 1.0);
-                let fuzz = utils::gen_range(0.0..0.5);
-                let sphere_material = Arc::new(material::Metal::new(albedo, fuzz));
+                let roughness = utils::gen_range(0.0..0.5);
+                let sphere_material =
+                    Arc::new(material::Conductor::new_with_color(albedo, roughness));
                 world.add(Arc::new(Sphere::new(center, 0.2, sphere_material)));
             } else {
                 let sphere_material = Arc::new(material::Dielectric::new(1.5));
@@ -81,7 +89,10 @@ pub fn random_scene() -> HittableList {
         material2,
     )));
 
-    let material3 = Arc::new(material::Metal::new(Color::new(0.7, 0.6, 0.5), 0.0));
+    let material3 = Arc::new(material::Conductor::new_with_color(
+        Color::new(0.7, 0.6, 0.5),
+        0.0,
+    ));
     world.add(Arc::new(Sphere::new(
         Point3::new(4.0, 1.0, 0.0),
         1.0,
@@ -113,9 +124,9 @@ pub fn two_spheres() -> HittableList {
 pub fn two_perlin_spheres() -> HittableList {
     let mut objects = HittableList::new();
     let perlin_texture = Arc::new(NoiseTexture::new(4.0));
-    objects.add(Arc::new(Sphere::new(
-        Point3::new(0.0, -1000.0, 0.0),
-        1000.0,
+    objects.add(Arc::new(Plane::new(
+        Point3::splat(0.0),
+        Vec3::new(0.0, 1.0, 0.0),
         Arc::new(material::Lambertian::new(perlin_texture.clone())),
     )));
     objects.add(Arc::new(Sphere::new(
@@ -136,13 +147,14 @@ pub fn earth() -> HittableList {
     list
 }
 
-pub fn simple_light() -> HittableList {
+pub fn simple_light() -> (HittableList, LightList) {
     let mut objects = HittableList::new();
+    let mut lights = LightList::new();
 
     let perlin_texture = Arc::new(NoiseTexture::new(4.0));
-    objects.add(Arc::new(Sphere::new(
-        Point3::new(0.0, -1000.0, 0.0),
-        1000.0,
+    objects.add(Arc::new(Plane::new(
+        Point3::splat(0.0),
+        Vec3::new(0.0, 1.0, 0.0),
         Arc::new(material::Lambertian::new(perlin_texture.clone())),
     )));
     objects.add(Arc::new(Sphere::new(
@@ -152,20 +164,134 @@ pub fn simple_light() -> HittableList {
     )));
 
     let diffuse_light = Arc::new(DiffuseLight::new_with_color(Color::new(4.0, 4.0, 4.0)));
-    objects.add(Arc::new(XYRect::new(
+    let light = Arc::new(AreaLight::new(Arc::new(XYRect::new(
         3.0,
         5.0,
         1.0,
         3.0,
         -2.0,
         diffuse_light,
+    ))));
+    objects.add(light.clone());
+    lights.add(light);
+
+    (objects, lights)
+}
+
+pub fn delta_lights() -> (HittableList, LightList) {
+    let mut objects = HittableList::new();
+
+    let perlin_texture = Arc::new(NoiseTexture::new(4.0));
+    objects.add(Arc::new(Plane::new(
+        Point3::splat(0.0),
+        Vec3::new(0.0, 1.0, 0.0),
+        Arc::new(material::Lambertian::new(perlin_texture.clone())),
+    )));
+    objects.add(Arc::new(Sphere::new(
+        Point3::new(0.0, 2.0, 0.0),
+        2.0,
+        Arc::new(material::Lambertian::new(perlin_texture)),
+    )));
+    objects.add(Arc::new(Sphere::new(
+        Point3::new(2.0, 0.7, 3.0),
+        0.7,
+        Arc::new(Conductor::new_with_color(Color::new(0.8, 0.6, 0.2), 0.2)),
     )));
 
-    objects
+    let mut lights = LightList::new();
+    lights.add(Arc::new(SpotLight::new(
+        Point3::new(4.0, 8.0, -4.0),
+        Vec3::new(-4.0, -6.0, 4.0),
+        Color::new(150.0, 140.0, 110.0),
+        25.0,
+        15.0,
+    )));
+    lights.add(Arc::new(PointLight::new(
+        Point3::new(4.0, 3.0, 5.0),
+        Color::new(2.0, 6.0, 12.0),
+    )));
+    lights.add(Arc::new(DirectionalLight::new(
+        Vec3::new(-1.0, -1.0, -0.5),
+        Color::splat(0.1),
+    )));
+
+    (objects, lights)
+}
+
+pub fn area_lights() -> (HittableList, LightList) {
+    let mut objects = HittableList::new();
+    let mut lights = LightList::new();
+    let mut add_light = |objects: &mut HittableList, shape: Arc<dyn Hittable>| {
+        let light = Arc::new(AreaLight::new(shape));
+        objects.add(light.clone());
+        lights.add(light);
+    };
+
+    let perlin_texture = Arc::new(NoiseTexture::new(4.0));
+    objects.add(Arc::new(Plane::new(
+        Point3::splat(0.0),
+        Vec3::new(0.0, 1.0, 0.0),
+        Arc::new(material::Lambertian::new(perlin_texture.clone())),
+    )));
+    objects.add(Arc::new(Sphere::new(
+        Point3::new(0.0, 2.0, 0.0),
+        2.0,
+        Arc::new(material::Lambertian::new(perlin_texture)),
+    )));
+
+    // a screen showing the earth, lighting only the side it faces
+    let screen = Arc::new(
+        DiffuseLight::new(Arc::new(ImageTexture::new("image/earthmap.jpg"))).with_two_sided(false),
+    );
+    add_light(
+        &mut objects,
+        Arc::new(Quad::new(
+            Point3::new(-3.0, 0.5, 7.0),
+            Vec3::new(0.0, 0.0, -6.0),
+            Vec3::new(0.0, 3.0, 0.0),
+            screen,
+        )),
+    );
+
+    // a glowing checkered ball
+    let checker = Arc::new(DiffuseLight::new(Arc::new(
+        UvCheckerTexture::new_with_color(
+            Color::new(6.0, 2.0, 0.5),
+            Color::new(0.5, 0.2, 0.05),
+            20.0,
+        ),
+    )));
+    add_light(
+        &mut objects,
+        Arc::new(Sphere::new(Point3::new(3.0, 0.6, 3.0), 0.6, checker)),
+    );
+
+    // a neon tetrahedron, each triangle of the mesh is a light
+    let neon = Arc::new(DiffuseLight::new_with_color(Color::new(4.0, 0.5, 6.0)));
+    let corners = [
+        Point3::new(2.5, 0.05, -3.0),
+        Point3::new(4.0, 0.05, -5.0),
+        Point3::new(1.0, 0.05, -5.0),
+        Point3::new(2.5, 2.0, -4.3),
+    ];
+    for [a, b, c] in [[0, 1, 2], [0, 3, 1], [1, 3, 2], [2, 3, 0]] {
+        add_light(
+            &mut objects,
+            Arc::new(Triangle::new(
+                corners[a],
+                corners[b],
+                corners[c],
+                neon.clone(),
+            )),
+        );
+    }
+
+    (objects, lights)
 }
 
-pub fn cornell_box() -> HittableList {
+pub fn cornell_box() -> (HittableList, LightList) {
     let mut objects = HittableList::new();
+    let mut lights = LightList::new();
 
     let red = Arc::new(Lambertian::new_with_color(Color::new(0.65, 0.05, 0.05)));
     let white = Arc::new(Lambertian::new_with_color(Color::new(0.73, 0.73, 0.73)));
@@ -174,9 +300,11 @@ pub fn cornell_box() -> HittableList {
 
     objects.add(Arc::new(YZRect::new(0.0, 555.0, 0.0, 555.0, 555.0, green)));
     objects.add(Arc::new(YZRect::new(0.0, 555.0, 0.0, 555.0, 0.0, red)));
-    objects.add(Arc::new(XZRect::new(
+    let light = Arc::new(AreaLight::new(Arc::new(XZRect::new(
         213.0, 343.0, 227.0, 332.0, 554.0, light,
-    )));
+    ))));
+    objects.add(light.clone());
+    lights.add(light);
     objects.add(Arc::new(XZRect::new(
         0.0,
         555.0,
@@ -220,7 +348,7 @@ pub fn cornell_box() -> HittableList {
     let box2 = Arc::new(Translate::new(box2, Vec3::new(130.0, 0.0, 65.0)));
     objects.add(box2);
 
-    objects
+    (objects, lights)
 }
 
 pub fn cornell_smoke() -> HittableList {
@@ -292,35 +420,26 @@ pub fn cornell_smoke() -> HittableList {
     objects
 }
 
-pub fn final_scene() -> HittableList {
+pub fn final_scene() -> (HittableList, LightList) {
     let mut objects = HittableList::new();
+    let mut lights = LightList::new();
 
     let ground = Arc::new(Lambertian::new_with_color(Color::new(0.48, 0.83, 0.53)));
-    let boxes_per_side = 20;
-    let mut ground_boxes = HittableList::new();
-    for i in 0..boxes_per_side {
-        for j in 0..boxes_per_side {
-            let w = 100.0;
-            let x0 = -1000.0 + i as crate::Float * w;
-            let y0 = 0.0;
-            let z0 = -1000.0 + j as crate::Float * w;
-            let x1 = x0 + w;
-            let y1 = utils::gen_range(1.0..101.0);
-            let z1 = z0 + w;
-
-            ground_boxes.add(Arc::new(Box::new(
-                Point3::new(x0, y0, z0),
-                Point3::new(x1, y1, z1),
-                ground.clone(),
-            )));
-        }
-    }
-    objects.add(Arc::new(ground_boxes.build_bvh(0.0, 1.0)));
+    objects.add(Arc::new(Heightfield::from_noise(
+        &Perlin::default(),
+        129,
+        4.0,
+        Point3::new(-1000.0, 0.0, -1000.0),
+        Vec3::new(2000.0, 100.0, 2000.0),
+        ground,
+    )));
 
     let light = Arc::new(DiffuseLight::new_with_color(Color::splat(7.0)));
-    objects.add(Arc::new(XZRect::new(
+    let light = Arc::new(AreaLight::new(Arc::new(XZRect::new(
         123.0, 423.0, 147.0, 412.0, 554.0, light,
-    )));
+    ))));
+    objects.add(light.clone());
+    lights.add(light);
 
     let moving_sphere_material = Arc::new(Lambertian::new_with_color(Color::new(0.7, 0.3, 0.1)));
     let center_0 = Point3::new(400.0, 400.0, 200.0);
@@ -341,20 +460,18 @@ pub fn final_scene() -> HittableList {
     objects.add(Arc::new(Sphere::new(
         Point3::new(0.0, 150.0, 145.0),
         50.0,
-        Arc::new(Metal::new(Color::new(0.8, 0.8, 0.9), 1.0)),
+        Arc::new(Conductor::new_with_color(Color::new(0.8, 0.8, 0.9), 1.0)),
     )));
 
-    // subsurface
-    let boundary = Arc::new(Sphere::new(
+    objects.add(Arc::new(Sphere::new(
         Point3::new(360.0, 150.0, 145.0),
         70.0,
-        Arc::new(Dielectric::new(1.5)),
-    ));
-    objects.add(boundary.clone());
-    objects.add(Arc::new(ConstantMedium::new_with_color(
-        boundary,
-        0.2,
-        Color::new(0.2, 0.4, 0.9),
+        Arc::new(Subsurface::new(
+            Color::new(0.2, 0.4, 0.9),
+            Color::splat(5.0),
+            1.5,
+            0.0,
+        )),
     )));
 
     // fog
@@ -401,5 +518,5 @@ pub fn final_scene() -> HittableList {
         Vec3::new(-100.0, 270.0, 395.0),
     )));
 
-    objects
+    (objects, lights)
 }
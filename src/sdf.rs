@@ -0,0 +1,355 @@
+//! Signed distance fields, composed into trees and rendered by `hittable::SdfShape`.
+//!
+//! Distances follow the usual convention: negative inside, positive outside.
+//! Operators that bend space (twist, displacement, smooth blends) no longer
+//! return exact distances, only bounds, so shapes using them should be traced
+//! with a step scale below one.
+
+use std::sync::Arc;
+
+use crate::perlin::Perlin;
+use crate::types::Point3;
+use crate::Vec3;
+
+pub trait Sdf: Sync + Send {
+    fn distance(&self, p: Point3) -> crate::Float;
+}
+
+pub struct Sphere {
+    radius: crate::Float,
+}
+
+impl Sphere {
+    pub fn new(radius: crate::Float) -> Self {
+        Self { radius }
+    }
+}
+
+impl Sdf for Sphere {
+    fn distance(&self, p: Point3) -> crate::Float {
+        p.length() - self.radius
+    }
+}
+
+pub struct Box {
+    half_extent: Vec3,
+    rounding: crate::Float,
+}
+
+impl Box {
+    pub fn new(half_extent: Vec3) -> Self {
+        Self {
+            half_extent,
+            rounding: 0.0,
+        }
+    }
+
+    /// The rounding radius is taken out of `half_extent`.
+    pub fn new_rounded(half_extent: Vec3, rounding: crate::Float) -> Self {
+        Self {
+            half_extent: half_extent - Vec3::splat(rounding),
+            rounding,
+        }
+    }
+}
+
+impl Sdf for Box {
+    fn distance(&self, p: Point3) -> crate::Float {
+        let q = p.abs() - self.half_extent;
+        q.max(Vec3::splat(0.0)).length() + q.max_element().min(0.0) - self.rounding
+    }
+}
+
+/// Torus around the y axis, see `hittable::Torus`.
+pub struct Torus {
+    major_radius: crate::Float,
+    minor_radius: crate::Float,
+}
+
+impl Torus {
+    pub fn new(major_radius: crate::Float, minor_radius: crate::Float) -> Self {
+        Self {
+            major_radius,
+            minor_radius,
+        }
+    }
+}
+
+impl Sdf for Torus {
+    fn distance(&self, p: Point3) -> crate::Float {
+        let ring_distance = (p.x * p.x + p.z * p.z).sqrt() - self.major_radius;
+        (ring_distance * ring_distance + p.y * p.y).sqrt() - self.minor_radius
+    }
+}
+
+/// Distance estimator of the power `power` Mandelbulb, roughly inside a radius 1.2 sphere.
+pub struct Mandelbulb {
+    power: crate::Float,
+    iterations: u32,
+}
+
+impl Mandelbulb {
+    pub fn new(power: crate::Float, iterations: u32) -> Self {
+        Self { power, iterations }
+    }
+}
+
+impl Sdf for Mandelbulb {
+    fn distance(&self, p: Point3) -> crate::Float {
+        if self.iterations == 0 {
+            // the set lies within the escape radius
+            return p.length() - 2.0;
+        }
+        let mut z = p;
+        let mut dr = 1.0;
+        let mut r = 0.0;
+        for _ in 0..self.iterations {
+            r = z.length();
+            if r > 2.0 {
+                break;
+            }
+            if r == 0.0 {
+                // no angles at the origin, which maps straight to p
+                z = p;
+                dr = 1.0;
+                continue;
+            }
+            let theta = (z.z / r).acos() * self.power;
+            let phi = z.y.atan2(z.x) * self.power;
+            dr = r.powf(self.power - 1.0) * self.power * dr + 1.0;
+            let zr = r.powf(self.power);
+            z =
+                zr * Vec3::new(
+                    theta.sin() * phi.cos(),
+                    phi.sin() * theta.sin(),
+                    theta.cos(),
+                ) + p;
+        }
+        if r == 0.0 {
+            // stuck at the origin, which is in the set
+            return 0.0;
+        }
+        0.5 * r.ln() * r / dr
+    }
+}
+
+pub struct Union {
+    a: Arc<dyn Sdf>,
+    b: Arc<dyn Sdf>,
+}
+
+impl Union {
+    pub fn new(a: Arc<dyn Sdf>, b: Arc<dyn Sdf>) -> Self {
+        Self { a, b }
+    }
+}
+
+impl Sdf for Union {
+    fn distance(&self, p: Point3) -> crate::Float {
+        self.a.distance(p).min(self.b.distance(p))
+    }
+}
+
+pub struct Intersection {
+    a: Arc<dyn Sdf>,
+    b: Arc<dyn Sdf>,
+}
+
+impl Intersection {
+    pub fn new(a: Arc<dyn Sdf>, b: Arc<dyn Sdf>) -> Self {
+        Self { a, b }
+    }
+}
+
+impl Sdf for Intersection {
+    fn distance(&self, p: Point3) -> crate::Float {
+        self.a.distance(p).max(self.b.distance(p))
+    }
+}
+
+/// `a` minus `b`
+pub struct Subtraction {
+    a: Arc<dyn Sdf>,
+    b: Arc<dyn Sdf>,
+}
+
+impl Subtraction {
+    pub fn new(a: Arc<dyn Sdf>, b: Arc<dyn Sdf>) -> Self {
+        Self { a, b }
+    }
+}
+
+impl Sdf for Subtraction {
+    fn distance(&self, p: Point3) -> crate::Float {
+        self.a.distance(p).max(-self.b.distance(p))
+    }
+}
+
+/// Union blended over a distance of roughly `k`.
+pub struct SmoothUnion {
+    a: Arc<dyn Sdf>,
+    b: Arc<dyn Sdf>,
+    k: crate::Float,
+}
+
+impl SmoothUnion {
+    pub fn new(a: Arc<dyn Sdf>, b: Arc<dyn Sdf>, k: crate::Float) -> Self {
+        Self { a, b, k }
+    }
+}
+
+impl Sdf for SmoothUnion {
+    fn distance(&self, p: Point3) -> crate::Float {
+        let a = self.a.distance(p);
+        let b = self.b.distance(p);
+        let h = (0.5 + 0.5 * (b - a) / self.k).clamp(0.0, 1.0);
+        b + (a - b) * h - self.k * h * (1.0 - h)
+    }
+}
+
+/// `a` minus `b`, blended over a distance of roughly `k`.
+pub struct SmoothSubtraction {
+    a: Arc<dyn Sdf>,
+    b: Arc<dyn Sdf>,
+    k: crate::Float,
+}
+
+impl SmoothSubtraction {
+    pub fn new(a: Arc<dyn Sdf>, b: Arc<dyn Sdf>, k: crate::Float) -> Self {
+        Self { a, b, k }
+    }
+}
+
+impl Sdf for SmoothSubtraction {
+    fn distance(&self, p: Point3) -> crate::Float {
+        let a = self.a.distance(p);
+        let b = self.b.distance(p);
+        let h = (0.5 - 0.5 * (a + b) / self.k).clamp(0.0, 1.0);
+        a + (-b - a) * h + self.k * h * (1.0 - h)
+    }
+}
+
+pub struct Translate {
+    sdf: Arc<dyn Sdf>,
+    offset: Vec3,
+}
+
+impl Translate {
+    pub fn new(sdf: Arc<dyn Sdf>, offset: Vec3) -> Self {
+        Self { sdf, offset }
+    }
+}
+
+impl Sdf for Translate {
+    fn distance(&self, p: Point3) -> crate::Float {
+        self.sdf.distance(p - self.offset)
+    }
+}
+
+pub struct Scale {
+    sdf: Arc<dyn Sdf>,
+    scale: crate::Float,
+}
+
+impl Scale {
+    pub fn new(sdf: Arc<dyn Sdf>, scale: crate::Float) -> Self {
+        Self { sdf, scale }
+    }
+}
+
+impl Sdf for Scale {
+    fn distance(&self, p: Point3) -> crate::Float {
+        self.sdf.distance(p / self.scale) * self.scale
+    }
+}
+
+/// Rotates space around the y axis by `rate` radians per unit of height.
+pub struct Twist {
+    sdf: Arc<dyn Sdf>,
+    rate: crate::Float,
+}
+
+impl Twist {
+    pub fn new(sdf: Arc<dyn Sdf>, rate: crate::Float) -> Self {
+        Self { sdf, rate }
+    }
+}
+
+impl Sdf for Twist {
+    fn distance(&self, p: Point3) -> crate::Float {
+        let (sin, cos) = (self.rate * p.y).sin_cos();
+        self.sdf.distance(Point3::new(
+            cos * p.x - sin * p.z,
+            p.y,
+            sin * p.x + cos * p.z,
+        ))
+    }
+}
+
+/// Infinite repetition with cells of size `period` centered on the origin,
+/// a zero component disables repetition along that axis.
+pub struct Repeat {
+    sdf: Arc<dyn Sdf>,
+    period: Vec3,
+}
+
+impl Repeat {
+    pub fn new(sdf: Arc<dyn Sdf>, period: Vec3) -> Self {
+        Self { sdf, period }
+    }
+}
+
+impl Sdf for Repeat {
+    fn distance(&self, p: Point3) -> crate::Float {
+        let mut q = p;
+        for axis in 0..3 {
+            let period = self.period[axis];
+            if period > 0.0 {
+                q[axis] -= period * (p[axis] / period).round();
+            }
+        }
+        self.sdf.distance(q)
+    }
+}
+
+/// Offsets the surface by Perlin noise sampled at `frequency`.
+pub struct Displace {
+    sdf: Arc<dyn Sdf>,
+    noise: Perlin,
+    amplitude: crate::Float,
+    frequency: crate::Float,
+}
+
+impl Displace {
+    pub fn new(sdf: Arc<dyn Sdf>, amplitude: crate::Float, frequency: crate::Float) -> Self {
+        Self {
+            sdf,
+            noise: Perlin::default(),
+            amplitude,
+            frequency,
+        }
+    }
+}
+
+impl Sdf for Displace {
+    fn distance(&self, p: Point3) -> crate::Float {
+        self.sdf.distance(p) + self.amplitude * self.noise.noise(self.frequency * p)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mandelbulb_is_finite() {
+        let bulb = Mandelbulb::new(8.0, 10);
+        assert_eq!(bulb.distance(Point3::ZERO), 0.0);
+        assert!(bulb.distance(Point3::new(0.0, 0.0, 1e-3)).is_finite());
+        assert!(bulb.distance(Point3::new(3.0, 0.0, 0.0)) > 0.0);
+
+        let sphere = Mandelbulb::new(8.0, 0);
+        assert_eq!(sphere.distance(Point3::ZERO), -2.0);
+        assert_eq!(sphere.distance(Point3::new(3.0, 0.0, 0.0)), 1.0);
+    }
+}
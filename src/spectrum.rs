@@ -0,0 +1,125 @@
+//! Single wavelengths in an RGB renderer.
+//!
+//! A ray is split into one wavelength when dispersion makes its path depend
+//! on the color. The wavelength is picked uniformly and the ray then carries
+//! the RGB weight of that wavelength, so averaged over many rays white light
+//! stays white.
+
+use crate::color::{self, Color};
+use crate::utils;
+use crate::Vec3;
+
+/// Visible range in nanometres.
+pub const WAVELENGTH_MIN: crate::Float = 380.0;
+pub const WAVELENGTH_MAX: crate::Float = 780.0;
+/// Sodium D line, where the index of refraction of glasses is quoted.
+pub const WAVELENGTH_D_LINE: crate::Float = 589.3;
+
+/// Average of the clamped RGB response over the visible range.
+const RESPONSE_AVERAGE: Color = Color::new(0.440_456_7, 0.288_466_2, 0.273_240_4);
+
+fn lobe(
+    x: crate::Float,
+    mu: crate::Float,
+    sigma_1: crate::Float,
+    sigma_2: crate::Float,
+) -> crate::Float {
+    let sigma = if x < mu { sigma_1 } else { sigma_2 };
+    let t = (x - mu) / sigma;
+    (-0.5 * t * t).exp()
+}
+
+/// CIE 1931 color matching functions (Wyman et al., Simple Analytic
+/// Approximations to the CIE XYZ Color Matching Functions).
+fn cie_xyz(wavelength: crate::Float) -> Vec3 {
+    let x = 1.056 * lobe(wavelength, 599.8, 37.9, 31.0)
+        + 0.362 * lobe(wavelength, 442.0, 16.0, 26.7)
+        - 0.065 * lobe(wavelength, 501.1, 20.4, 26.2);
+    let y =
+        0.821 * lobe(wavelength, 568.8, 46.9, 40.5) + 0.286 * lobe(wavelength, 530.9, 16.3, 31.1);
+    let z =
+        1.217 * lobe(wavelength, 437.0, 11.8, 36.0) + 0.681 * lobe(wavelength, 459.0, 26.0, 13.8);
+    Vec3::new(x, y, z)
+}
+
+/// Linear RGB weight of a wavelength, scaled so the average over the
+/// visible range is white. Colors outside the RGB gamut are clamped.
+pub fn wavelength_to_rgb(wavelength: crate::Float) -> Color {
+    let rgb = color::xyz_to_rgb(cie_xyz(wavelength));
+    rgb.max(Color::splat(0.0)) / RESPONSE_AVERAGE
+}
+
+/// The wavelength of a ray, picking one if it still carries all of them.
+/// Returns the wavelength and the weight to apply to the ray.
+pub fn hero_wavelength(current: Option<crate::Float>) -> (crate::Float, Color) {
+    match current {
+        Some(wavelength) => (wavelength, Color::splat(1.0)),
+        None => {
+            let wavelength = utils::gen_range(WAVELENGTH_MIN..WAVELENGTH_MAX);
+            (wavelength, wavelength_to_rgb(wavelength))
+        }
+    }
+}
+
+/// `count` evenly spread wavelengths, for integrating a spectrum to RGB.
+pub fn wavelengths(count: usize) -> impl Iterator<Item = crate::Float> {
+    (0..count).map(move |i| {
+        WAVELENGTH_MIN
+            + (WAVELENGTH_MAX - WAVELENGTH_MIN) * (i as crate::Float + 0.5) / count as crate::Float
+    })
+}
+
+/// Wavelengths standing for the red, green and blue channels.
+pub const PRIMARY_WAVELENGTHS: [crate::Float; 3] = [630.0, 532.0, 465.0];
+
+/// An RGB quantity such as the index of refraction of a metal at a
+/// wavelength, interpolated between the primaries and held beyond them.
+pub fn rgb_at_wavelength(color: Color, wavelength: crate::Float) -> crate::Float {
+    let [red, green, blue] = PRIMARY_WAVELENGTHS;
+    if wavelength >= red {
+        color.x
+    } else if wavelength >= green {
+        let t = (wavelength - green) / (red - green);
+        color.y + t * (color.x - color.y)
+    } else if wavelength >= blue {
+        let t = (wavelength - blue) / (green - blue);
+        color.z + t * (color.y - color.z)
+    } else {
+        color.z
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hero_wavelengths_average_to_white() {
+        const SAMPLES: usize = 200_000;
+        let mut sum = Color::splat(0.0);
+        for _ in 0..SAMPLES {
+            let (wavelength, weight) = hero_wavelength(None);
+            assert!((WAVELENGTH_MIN..WAVELENGTH_MAX).contains(&wavelength));
+            sum += weight;
+        }
+        let average = sum / SAMPLES as crate::Float;
+        assert!(
+            (average - Color::splat(1.0)).abs().max_element() < 0.02,
+            "{}",
+            average
+        );
+
+        // and evenly spread ones
+        let average = wavelengths(1000)
+            .map(wavelength_to_rgb)
+            .fold(Color::splat(0.0), |sum, color| sum + color)
+            / 1000.0;
+        assert!(
+            (average - Color::splat(1.0)).abs().max_element() < 1e-3,
+            "{}",
+            average
+        );
+
+        assert_eq!(hero_wavelength(Some(500.0)), (500.0, Color::splat(1.0)));
+    }
+}
@@ -0,0 +1,425 @@
+use std::path::Path;
+
+use crate::color::{self, Color};
+use crate::types::Point3;
+
+use super::Texture;
+
+/// What lies outside [0, 1] in texture space.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WrapMode {
+    /// Tiles the image
+    Repeat,
+    /// Stretches the edge texels
+    Clamp,
+    /// Tiles the image, every other tile flipped
+    Mirror,
+}
+
+/// How texels are combined into a value.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Filter {
+    Nearest,
+    Bilinear,
+    /// Bilinear in the two mipmap levels closest to the footprint, blended
+    Trilinear,
+}
+
+/// An image, full size then halved down to a single texel for trilinear
+/// filtering.
+struct MipLevel {
+    width: usize,
+    height: usize,
+    /// Rows from the top
+    texels: Vec<Color>,
+}
+
+impl MipLevel {
+    /// Box filtered to half the size, rounded up.
+    fn half(&self) -> Self {
+        let width = (self.width + 1) / 2;
+        let height = (self.height + 1) / 2;
+        let mut texels = Vec::with_capacity(width * height);
+        for y in 0..height {
+            for x in 0..width {
+                let mut sum = Color::splat(0.0);
+                for (dx, dy) in [(0, 0), (1, 0), (0, 1), (1, 1)] {
+                    let sx = (2 * x + dx).min(self.width - 1);
+                    let sy = (2 * y + dy).min(self.height - 1);
+                    sum += self.texels[sy * self.width + sx];
+                }
+                texels.push(sum / 4.0);
+            }
+        }
+        Self {
+            width,
+            height,
+            texels,
+        }
+    }
+
+    fn texel(&self, x: i64, y: i64, wrap: WrapMode) -> Color {
+        let x = wrap_index(x, self.width, wrap);
+        let y = wrap_index(y, self.height, wrap);
+        self.texels[y * self.width + x]
+    }
+
+    /// `s` and `t` in texels from the top left corner.
+    fn nearest(&self, s: crate::Float, t: crate::Float, wrap: WrapMode) -> Color {
+        self.texel(s.floor() as i64, t.floor() as i64, wrap)
+    }
+
+    fn bilinear(&self, s: crate::Float, t: crate::Float, wrap: WrapMode) -> Color {
+        // between the centres of the four closest texels
+        let s = s - 0.5;
+        let t = t - 0.5;
+        let (x, y) = (s.floor(), t.floor());
+        let (fx, fy) = (s - x, t - y);
+        let (x, y) = (x as i64, y as i64);
+        (1.0 - fx) * (1.0 - fy) * self.texel(x, y, wrap)
+            + fx * (1.0 - fy) * self.texel(x + 1, y, wrap)
+            + (1.0 - fx) * fy * self.texel(x, y + 1, wrap)
+            + fx * fy * self.texel(x + 1, y + 1, wrap)
+    }
+}
+
+/// Decodes an image file to linear values, rows from the top.
+///
+/// Radiance `.hdr` and OpenEXR files hold floats, used as they are. 8 and
+/// 16 bit images are scaled to [0, 1] and, for `color` images, decoded from
+/// sRGB, while data such as roughness or normals is stored linearly.
+pub fn load_image(p: &Path, color: bool) -> (usize, usize, Vec<Color>) {
+    let is_radiance = p
+        .extension()
+        .map_or(false, |extension| extension.eq_ignore_ascii_case("hdr"));
+    if is_radiance {
+        // image::open tone maps Radiance files to 8 bits
+        let reader = std::io::BufReader::new(std::fs::File::open(p).unwrap());
+        let decoder = image::codecs::hdr::HdrDecoder::new(reader).unwrap();
+        let metadata = decoder.metadata();
+        let pixels = decoder
+            .read_image_hdr()
+            .unwrap()
+            .into_iter()
+            .map(|p| to_color(p.0, false))
+            .collect();
+        return (metadata.width as usize, metadata.height as usize, pixels);
+    }
+
+    decode(image::open(p).unwrap(), color)
+}
+
+/// Linear values of a decoded image, as `load_image`.
+fn decode(image: image::DynamicImage, color: bool) -> (usize, usize, Vec<Color>) {
+    let is_float = matches!(
+        image,
+        image::DynamicImage::ImageRgb32F(_) | image::DynamicImage::ImageRgba32F(_)
+    );
+    let image = image.into_rgb32f();
+    let (width, height) = (image.width() as usize, image.height() as usize);
+    let pixels = image
+        .pixels()
+        .map(|p| to_color(p.0, color && !is_float))
+        .collect();
+    (width, height, pixels)
+}
+
+fn to_color(rgb: [f32; 3], srgb: bool) -> Color {
+    let [r, g, b] = rgb.map(|c| {
+        let c = c as crate::Float;
+        if srgb {
+            color::srgb_to_linear(c)
+        } else {
+            c
+        }
+    });
+    Color::new(r, g, b)
+}
+
+fn wrap_index(i: i64, n: usize, wrap: WrapMode) -> usize {
+    let n = n as i64;
+    let i = match wrap {
+        WrapMode::Repeat => i.rem_euclid(n),
+        WrapMode::Clamp => i.clamp(0, n - 1),
+        WrapMode::Mirror => {
+            let i = i.rem_euclid(2 * n);
+            if i >= n {
+                2 * n - 1 - i
+            } else {
+                i
+            }
+        }
+    };
+    i as usize
+}
+
+/// Texture from an image file, v running up from its bottom row.
+pub struct ImageTexture {
+    levels: Vec<MipLevel>,
+    filter: Filter,
+    wrap: WrapMode,
+    scale: (crate::Float, crate::Float),
+    offset: (crate::Float, crate::Float),
+    /// Sine and cosine of the rotation
+    rotation: (crate::Float, crate::Float),
+}
+
+impl ImageTexture {
+    /// Colors, decoded from sRGB unless the file holds floats. Filtered
+    /// bilinearly and clamped at the edges.
+    pub fn new(p: impl AsRef<Path>) -> Self {
+        let (width, height, texels) = load_image(p.as_ref(), true);
+        Self::new_with_texels(width, height, texels)
+    }
+
+    /// Data which is not a color, such as roughness or a normal map, read
+    /// as stored.
+    pub fn new_non_color(p: impl AsRef<Path>) -> Self {
+        let (width, height, texels) = load_image(p.as_ref(), false);
+        Self::new_with_texels(width, height, texels)
+    }
+
+    /// The alpha channel as a gray texture, e.g. for an `AlphaMask`.
+    pub fn new_alpha(p: impl AsRef<Path>) -> Self {
+        let rgba = image::open(p).unwrap().into_rgba32f();
+        let texels = rgba
+            .pixels()
+            .map(|p| Color::splat(p[3] as crate::Float))
+            .collect();
+        Self::new_with_texels(rgba.width() as usize, rgba.height() as usize, texels)
+    }
+
+    fn new_with_texels(width: usize, height: usize, texels: Vec<Color>) -> Self {
+        Self {
+            levels: vec![MipLevel {
+                width,
+                height,
+                texels,
+            }],
+            filter: Filter::Bilinear,
+            wrap: WrapMode::Clamp,
+            scale: (1.0, 1.0),
+            offset: (0.0, 0.0),
+            rotation: (0.0, 1.0),
+        }
+    }
+
+    /// Trilinear filtering builds the mipmap.
+    pub fn with_filter(self, filter: Filter) -> Self {
+        let mut levels = self.levels;
+        levels.truncate(1);
+        if filter == Filter::Trilinear {
+            loop {
+                let last = levels.last().unwrap();
+                if last.width == 1 && last.height == 1 {
+                    break;
+                }
+                let half = last.half();
+                levels.push(half);
+            }
+        }
+        Self {
+            levels,
+            filter,
+            ..self
+        }
+    }
+
+    pub fn with_wrap(self, wrap: WrapMode) -> Self {
+        Self { wrap, ..self }
+    }
+
+    /// Maps texture coordinates to the image by scaling, then rotating about
+    /// the origin, then offsetting them.
+    ///
+    /// * `scale` - Repeats of the image along u and v
+    /// * `rotation` - Counterclockwise, in degrees
+    pub fn with_uv_transform(
+        self,
+        scale: (crate::Float, crate::Float),
+        offset: (crate::Float, crate::Float),
+        rotation: crate::Float,
+    ) -> Self {
+        Self {
+            scale,
+            offset,
+            rotation: rotation.to_radians().sin_cos(),
+            ..self
+        }
+    }
+
+    /// The transformed coordinates and footprint.
+    fn transform(
+        &self,
+        u: crate::Float,
+        v: crate::Float,
+        footprint: (crate::Float, crate::Float),
+    ) -> (crate::Float, crate::Float, crate::Float, crate::Float) {
+        let (sin, cos) = self.rotation;
+        let (u, v) = (u * self.scale.0, v * self.scale.1);
+        let (du, dv) = (
+            (footprint.0 * self.scale.0).abs(),
+            (footprint.1 * self.scale.1).abs(),
+        );
+        (
+            cos * u - sin * v + self.offset.0,
+            sin * u + cos * v + self.offset.1,
+            cos.abs() * du + sin.abs() * dv,
+            sin.abs() * du + cos.abs() * dv,
+        )
+    }
+
+    fn lookup(&self, level: usize, u: crate::Float, v: crate::Float) -> Color {
+        let level = &self.levels[level];
+        let s = u * level.width as crate::Float;
+        let t = (1.0 - v) * level.height as crate::Float;
+        match self.filter {
+            Filter::Nearest => level.nearest(s, t, self.wrap),
+            Filter::Bilinear | Filter::Trilinear => level.bilinear(s, t, self.wrap),
+        }
+    }
+}
+
+impl Texture for ImageTexture {
+    fn value(&self, u: crate::Float, v: crate::Float, p: Point3) -> Color {
+        self.filtered(u, v, p, (0.0, 0.0))
+    }
+
+    fn filtered(
+        &self,
+        u: crate::Float,
+        v: crate::Float,
+        _p: Point3,
+        footprint: (crate::Float, crate::Float),
+    ) -> Color {
+        let (u, v, du, dv) = self.transform(u, v, footprint);
+        if self.filter != Filter::Trilinear {
+            return self.lookup(0, u, v);
+        }
+
+        // level where the footprint covers about a texel
+        let full = &self.levels[0];
+        let width = (du * full.width as crate::Float).max(dv * full.height as crate::Float);
+        let last = (self.levels.len() - 1) as crate::Float;
+        let level = width
+            .max(crate::Float::MIN_POSITIVE)
+            .log2()
+            .clamp(0.0, last);
+        let lower = level.floor();
+        let t = level - lower;
+        let lower = lower as usize;
+        if t == 0.0 {
+            return self.lookup(lower, u, v);
+        }
+        (1.0 - t) * self.lookup(lower, u, v) + t * self.lookup(lower + 1, u, v)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Black and white texels, every level of its mipmap is mid gray.
+    fn checkerboard(size: usize) -> ImageTexture {
+        let texels = (0..size * size)
+            .map(|i| Color::splat(((i / size + i % size) % 2) as crate::Float))
+            .collect();
+        ImageTexture::new_with_texels(size, size, texels).with_filter(Filter::Trilinear)
+    }
+
+    #[test]
+    fn srgb_decoding() {
+        let image = image::RgbImage::from_pixel(1, 1, image::Rgb([128, 0, 255]));
+        let (_, _, pixels) = decode(image::DynamicImage::ImageRgb8(image.clone()), true);
+        assert!(
+            (pixels[0] - Color::new(0.2158605, 0.0, 1.0))
+                .abs()
+                .max_element()
+                < 1e-6
+        );
+        let (_, _, pixels) = decode(image::DynamicImage::ImageRgb8(image), false);
+        assert!((pixels[0].x - 128.0 / 255.0).abs() < 1e-6);
+
+        // and through the file, color or not
+        let path = std::env::temp_dir().join(format!("srgb-{}.png", std::process::id()));
+        image::RgbImage::from_pixel(1, 1, image::Rgb([128, 64, 255]))
+            .save(&path)
+            .unwrap();
+        let color = ImageTexture::new(&path).value(0.5, 0.5, Point3::ZERO);
+        let data = ImageTexture::new_non_color(&path).value(0.5, 0.5, Point3::ZERO);
+        std::fs::remove_file(&path).unwrap();
+        assert!((color.x - 0.2158605).abs() < 1e-6);
+        let expected = Color::new(128.0, 64.0, 255.0) / 255.0;
+        assert!((data - expected).abs().max_element() < 1e-6, "{}", data);
+    }
+
+    #[test]
+    fn sixteen_bits() {
+        // between two 8 bit steps
+        let image = image::ImageBuffer::from_fn(2, 1, |x, _| image::Rgb([32_769 + x as u16; 3]));
+        let (width, height, pixels) = decode(image::DynamicImage::ImageRgb16(image), false);
+        assert_eq!((width, height), (2, 1));
+        assert!((pixels[0].x - 32_769.0 / 65_535.0).abs() < 1e-6);
+        assert!(pixels[1].x > pixels[0].x);
+    }
+
+    #[test]
+    fn wrap_modes() {
+        let repeat: Vec<_> = (-5..9)
+            .map(|i| wrap_index(i, 4, WrapMode::Repeat))
+            .collect();
+        assert_eq!(repeat, [3, 0, 1, 2, 3, 0, 1, 2, 3, 0, 1, 2, 3, 0]);
+        let clamp: Vec<_> = (-5..9).map(|i| wrap_index(i, 4, WrapMode::Clamp)).collect();
+        assert_eq!(clamp, [0, 0, 0, 0, 0, 0, 1, 2, 3, 3, 3, 3, 3, 3]);
+        let mirror: Vec<_> = (-5..9)
+            .map(|i| wrap_index(i, 4, WrapMode::Mirror))
+            .collect();
+        assert_eq!(mirror, [3, 3, 2, 1, 0, 0, 1, 2, 3, 3, 2, 1, 0, 0]);
+
+        for wrap in [WrapMode::Repeat, WrapMode::Clamp, WrapMode::Mirror] {
+            assert_eq!(wrap_index(-7, 1, wrap), 0);
+            assert_eq!(wrap_index(7, 1, wrap), 0);
+        }
+    }
+
+    #[test]
+    fn mip_levels() {
+        let texture = ImageTexture::new_with_texels(5, 3, vec![Color::splat(1.0); 15])
+            .with_filter(Filter::Trilinear);
+        let sizes: Vec<_> = texture
+            .levels
+            .iter()
+            .map(|level| (level.width, level.height))
+            .collect();
+        assert_eq!(sizes, [(5, 3), (3, 2), (2, 1), (1, 1)]);
+        assert_eq!(texture.with_filter(Filter::Bilinear).levels.len(), 1);
+    }
+
+    #[test]
+    fn mip_level_selection() {
+        const TOLERANCE: crate::Float = 1e2 * crate::Float::EPSILON;
+        let texture = checkerboard(8);
+        let texel = 1.0 / 8.0;
+        // centre of the second texel of the top row, a white one
+        let (u, v) = (1.5 * texel, 1.0 - 0.5 * texel);
+        let at = |footprint: (crate::Float, crate::Float)| {
+            texture.filtered(u, v, Point3::ZERO, footprint).x
+        };
+
+        // a texel or less reads the full image
+        assert!((at((0.0, 0.0)) - 1.0).abs() < TOLERANCE);
+        assert!((at((texel, texel)) - 1.0).abs() < TOLERANCE);
+        assert!((at((texel / 4.0, 0.0)) - 1.0).abs() < TOLERANCE);
+        // two texels or more are averaged away
+        assert!((at((2.0 * texel, texel)) - 0.5).abs() < TOLERANCE);
+        assert!((at((texel, 2.0 * texel)) - 0.5).abs() < TOLERANCE);
+        assert!((at((100.0, 100.0)) - 0.5).abs() < TOLERANCE);
+        // in between, blended by the log of the footprint
+        let t = (1.5 as crate::Float).log2();
+        assert!((at((1.5 * texel, 0.0)) - (1.0 - t / 2.0)).abs() < TOLERANCE);
+
+        // other filters ignore the footprint
+        let bilinear = checkerboard(8).with_filter(Filter::Bilinear);
+        assert!((bilinear.filtered(u, v, Point3::ZERO, (1.0, 1.0)).x - 1.0).abs() < TOLERANCE);
+    }
+}
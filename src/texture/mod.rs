@@ -1,11 +1,37 @@
-use std::path::Path;
+mod image;
+
+pub use self::image::{load_image, Filter, ImageTexture, WrapMode};
+
 use std::sync::Arc;
 
 use crate::color::Color;
+use crate::hittable::HitRecord;
 use crate::types::Point3;
 
 pub trait Texture: Sync + Send {
     fn value(&self, u: crate::Float, v: crate::Float, p: Point3) -> Color;
+
+    /// Average over the `footprint`, its width along u and v, around (u, v),
+    /// for textures which alias when only sampled at a point.
+    fn filtered(
+        &self,
+        u: crate::Float,
+        v: crate::Float,
+        p: Point3,
+        footprint: (crate::Float, crate::Float),
+    ) -> Color {
+        self.value(u, v, p)
+    }
+
+    /// At a hit, over its footprint.
+    fn value_at(&self, rec: &HitRecord) -> Color {
+        self.filtered(rec.u, rec.v, rec.p, rec.uv_footprint)
+    }
+
+    /// Single channel for data such as roughness, taken from red.
+    fn scalar(&self, u: crate::Float, v: crate::Float, p: Point3) -> crate::Float {
+        self.value(u, v, p).x
+    }
 }
 
 pub struct SolidColor {
@@ -55,6 +81,35 @@ impl Texture for CheckerTexture {
     }
 }
 
+/// Checker pattern in texture space, for surfaces where the solid
+/// `CheckerTexture` degenerates, such as a plane through the origin.
+pub struct UvCheckerTexture {
+    odd: Arc<dyn Texture>,
+    even: Arc<dyn Texture>,
+    frequency: crate::Float,
+}
+
+impl UvCheckerTexture {
+    pub fn new_with_color(odd: Color, even: Color, frequency: crate::Float) -> Self {
+        Self {
+            odd: Arc::new(SolidColor::new(odd)),
+            even: Arc::new(SolidColor::new(even)),
+            frequency,
+        }
+    }
+}
+
+impl Texture for UvCheckerTexture {
+    fn value(&self, u: crate::Float, v: crate::Float, p: Point3) -> Color {
+        let sines = (self.frequency * u).sin() * (self.frequency * v).sin();
+        if sines < 0.0 {
+            return self.odd.value(u, v, p);
+        } else {
+            return self.even.value(u, v, p);
+        }
+    }
+}
+
 #[derive(Default)]
 pub struct NoiseTexture {
     noise: super::perlin::Perlin,
@@ -75,40 +130,3 @@ impl NoiseTexture {
         }
     }
 }
-
-pub struct ImageTexture {
-    data: image::RgbImage,
-}
-
-impl ImageTexture {
-    pub fn new(p: impl AsRef<Path>) -> Self {
-        Self {
-            data: image::open(p).unwrap().into_rgb8(),
-        }
-    }
-}
-
-impl Texture for ImageTexture {
-    fn value(&self, u: crate::Float, v: crate::Float, _p: Point3) -> Color {
-        let u = u.clamp(0.0, 1.0);
-        let v = 1.0 - v.clamp(0.0, 1.0);
-
-        let width = self.data.width();
-        let height = self.data.height();
-
-        let i = (u * width as crate::Float) as u32;
-        let j = (v * height as crate::Float) as u32;
-
-        assert!(i <= width);
-        assert!(j <= height);
-
-        let color = self.data.get_pixel(i, j);
-        let color_scale = 1.0 / 255.0;
-
-        Color::new(
-            color[0] as crate::Float * color_scale,
-            color[1] as crate::Float * color_scale,
-            color[2] as crate::Float * color_scale,
-        )
-    }
-}
@@ -4,6 +4,7 @@ use rand::distributions::uniform::{SampleRange, SampleUniform};
 use rand::prelude::SmallRng;
 use rand::{Rng, SeedableRng};
 
+use crate::types::PI;
 use crate::Vec3;
 
 thread_local! {
@@ -70,3 +71,26 @@ pub fn rand_vec3_in_unit_disk() -> Vec3 {
         }
     }
 }
+
+/// Cosine weighted direction around +z.
+#[inline]
+pub fn rand_cosine_direction() -> Vec3 {
+    let r1 = gen_float();
+    let r2 = gen_float();
+    let phi = 2.0 * PI * r1;
+    let r = r2.sqrt();
+    Vec3::new(phi.cos() * r, phi.sin() * r, (1.0 - r2).sqrt())
+}
+
+/// Weight of a sample from a technique with density `pdf` against another
+/// technique with density `other_pdf`, for combining both
+/// (Veach, Robust Monte Carlo Methods for Light Transport Simulation, 9.2).
+#[inline]
+pub fn power_heuristic(pdf: crate::Float, other_pdf: crate::Float) -> crate::Float {
+    let (a, b) = (pdf * pdf, other_pdf * other_pdf);
+    if a + b > 0.0 {
+        a / (a + b)
+    } else {
+        0.0
+    }
+}